@@ -0,0 +1,230 @@
+//! Cooperative cancellation of in-flight requests and body reads.
+
+use std::{
+  fmt,
+  future::{poll_fn, Future},
+  pin::{pin, Pin},
+  sync::{Arc, Mutex, OnceLock, Weak},
+  task::{Context, Poll, Waker},
+};
+
+use bytes::Bytes;
+use futures_util::future::{select, Either};
+use http_body::{Body, Frame, SizeHint};
+
+use crate::Error;
+
+/// A cloneable cancellation flag; every clone observes the same abort.
+#[derive(Debug, Clone, Default)]
+pub struct AbortSignal {
+  state: Arc<AbortState>,
+}
+
+#[derive(Debug, Default)]
+struct AbortState {
+  reason: OnceLock<String>,
+  wakers: Mutex<Vec<Waker>>,
+  release: Mutex<Option<Release>>,
+}
+
+/// Called once the last clone of a signal is gone.
+struct Release(Box<dyn FnOnce() + Send>);
+
+impl fmt::Debug for Release {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("Release")
+  }
+}
+
+impl Drop for AbortState {
+  fn drop(&mut self) {
+    if let Some(Release(release)) = self.release.get_mut().unwrap().take() {
+      release();
+    }
+  }
+}
+
+/// Aborts a signal without keeping it alive, for sources that outlive the requests they cancel.
+#[derive(Debug, Clone)]
+pub struct AbortHandle {
+  state: Weak<AbortState>,
+}
+
+impl AbortHandle {
+  /// Abort the signal with `reason`, unless it is already gone.
+  pub fn abort(&self, reason: impl Into<String>) {
+    if let Some(state) = self.state.upgrade() {
+      AbortSignal { state }.abort(reason);
+    }
+  }
+}
+
+impl AbortSignal {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Abort with `reason`. Only the first call has an effect.
+  pub fn abort(&self, reason: impl Into<String>) {
+    if self.state.reason.set(reason.into()).is_ok() {
+      let wakers = std::mem::take(&mut *self.state.wakers.lock().unwrap());
+      wakers.into_iter().for_each(Waker::wake);
+    }
+  }
+
+  /// A handle that aborts this signal without keeping it alive.
+  pub fn handle(&self) -> AbortHandle {
+    AbortHandle {
+      state: Arc::downgrade(&self.state),
+    }
+  }
+
+  /// Run `release` once every clone of the signal is gone, which is once the request and the
+  /// reads of its response body are done with it.
+  pub fn on_release(&self, release: impl FnOnce() + Send + 'static) {
+    *self.state.release.lock().unwrap() = Some(Release(Box::new(release)));
+  }
+
+  /// The abort reason, once aborted.
+  pub fn reason(&self) -> Option<&str> {
+    self.state.reason.get().map(String::as_str)
+  }
+
+  pub fn is_aborted(&self) -> bool {
+    self.state.reason.get().is_some()
+  }
+
+  /// Resolve with the reason once the signal is aborted.
+  pub async fn aborted(&self) -> String {
+    poll_fn(|cx| self.poll_aborted(cx)).await
+  }
+
+  fn poll_aborted(&self, cx: &mut Context<'_>) -> Poll<String> {
+    if let Some(reason) = self.state.reason.get() {
+      return Poll::Ready(reason.clone());
+    }
+
+    {
+      let mut wakers = self.state.wakers.lock().unwrap();
+      if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+        wakers.push(cx.waker().clone());
+      }
+    }
+
+    // Re-check in case the signal was aborted while the waker was being registered.
+    match self.state.reason.get() {
+      Some(reason) => Poll::Ready(reason.clone()),
+      None => Poll::Pending,
+    }
+  }
+
+  /// Run `future` unless the signal is aborted first, in which case it is dropped.
+  pub async fn run<T>(&self, future: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+    let aborted = pin!(self.aborted());
+    let future = pin!(future);
+    match select(future, aborted).await {
+      Either::Left((result, _)) => result,
+      Either::Right((reason, _)) => Err(Error::Aborted(reason)),
+    }
+  }
+}
+
+/// Run `future` under an optional signal.
+pub(crate) async fn run_abortable<T>(
+  signal: Option<&AbortSignal>,
+  future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+  match signal {
+    Some(signal) => signal.run(future).await,
+    None => future.await,
+  }
+}
+
+/// A body that fails with [`Error::Aborted`] once its signal is aborted.
+pub struct AbortableBody<B> {
+  inner: B,
+  signal: AbortSignal,
+}
+
+impl<B> AbortableBody<B> {
+  pub fn new(inner: B, signal: AbortSignal) -> Self {
+    Self { inner, signal }
+  }
+}
+
+impl<B> Body for AbortableBody<B>
+where
+  B: Body<Data = Bytes> + Unpin,
+  B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+  type Data = Bytes;
+  type Error = Box<dyn std::error::Error + Send + Sync>;
+
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    if let Poll::Ready(reason) = self.signal.poll_aborted(cx) {
+      return Poll::Ready(Some(Err(Box::new(Error::Aborted(reason)))));
+    }
+
+    Pin::new(&mut self.inner)
+      .poll_frame(cx)
+      .map(|frame| frame.map(|frame| frame.map_err(Into::into)))
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn run_resolves_with_the_abort_reason() {
+    let signal = AbortSignal::new();
+    let trigger = signal.clone();
+
+    let result = futures::executor::block_on(signal.run(async move {
+      trigger.abort("stop");
+      std::future::pending::<Result<(), Error>>().await
+    }));
+
+    assert!(matches!(result, Err(Error::Aborted(reason)) if reason == "stop"));
+    assert_eq!(signal.reason(), Some("stop"));
+  }
+
+  #[test]
+  fn releases_once_the_last_clone_is_gone() {
+    let released = Arc::new(OnceLock::new());
+    let signal = AbortSignal::new();
+    let set = released.clone();
+    signal.on_release(move || set.set(()).unwrap());
+
+    let handle = signal.handle();
+    let clone = signal.clone();
+    drop(signal);
+    assert!(released.get().is_none());
+    handle.abort("stop");
+    assert_eq!(clone.reason(), Some("stop"));
+
+    drop(clone);
+    assert!(released.get().is_some());
+    // Aborting a signal that is gone does nothing.
+    handle.abort("again");
+  }
+
+  #[test]
+  fn run_passes_through_when_not_aborted() {
+    let signal = AbortSignal::new();
+    let result = futures::executor::block_on(signal.run(async { Ok::<_, Error>(7) }));
+    assert_eq!(result.unwrap(), 7);
+    assert!(!signal.is_aborted());
+  }
+}
@@ -0,0 +1,164 @@
+//! Many requests sent from one task with a bound on how many are in flight.
+
+use bytes::Bytes;
+use futures_util::{stream, StreamExt};
+use tokio::{sync::mpsc, task::JoinHandle};
+use wreq::Method;
+
+use crate::{execute_request, Client, Error, Request, Response};
+
+/// Requests in flight when no concurrency is given.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 16;
+
+/// One request of a batch.
+pub struct BatchRequest {
+  pub method: Method,
+  pub url: String,
+  pub request: Request,
+}
+
+/// A finished request of a batch, with its body read.
+#[derive(Debug)]
+pub struct BatchResult {
+  /// Position of the request in the batch.
+  pub index: usize,
+  pub outcome: Result<(Response, Bytes), Error>,
+}
+
+/// Sends a list of requests, yielding results in the order they finish.
+///
+/// Requests start once the first result is asked for and stop when the batch is dropped.
+pub struct Batch {
+  pending: Option<(Option<Client>, Vec<BatchRequest>)>,
+  concurrency: usize,
+  results: Option<mpsc::Receiver<BatchResult>>,
+  task: Option<JoinHandle<()>>,
+}
+
+impl Batch {
+  /// A batch sending at most `concurrency` requests at once, on top of any limits the client
+  /// sets.
+  pub fn new(client: Option<Client>, requests: Vec<BatchRequest>, concurrency: usize) -> Self {
+    Self {
+      pending: Some((client, requests)),
+      concurrency: concurrency.max(1),
+      results: None,
+      task: None,
+    }
+  }
+
+  /// The next request to finish, or `None` once every request has.
+  pub async fn next(&mut self) -> Option<BatchResult> {
+    if let Some((client, requests)) = self.pending.take() {
+      // Finished results wait for the caller, which holds back the next requests.
+      let (tx, rx) = mpsc::channel(self.concurrency);
+      self.task = Some(tokio::spawn(run(client, requests, self.concurrency, tx)));
+      self.results = Some(rx);
+    }
+    self.results.as_mut()?.recv().await
+  }
+
+  /// Stop sending requests and drop the results not yet taken.
+  pub fn cancel(&mut self) {
+    self.pending = None;
+    self.results = None;
+    if let Some(task) = self.task.take() {
+      task.abort();
+    }
+  }
+}
+
+impl Drop for Batch {
+  fn drop(&mut self) {
+    if let Some(task) = &self.task {
+      task.abort();
+    }
+  }
+}
+
+async fn run(
+  client: Option<Client>,
+  requests: Vec<BatchRequest>,
+  concurrency: usize,
+  tx: mpsc::Sender<BatchResult>,
+) {
+  let mut results = stream::iter(requests.into_iter().enumerate())
+    .map(|(index, request)| {
+      let client = client.clone();
+      async move {
+        BatchResult {
+          index,
+          outcome: send(client, request).await,
+        }
+      }
+    })
+    .buffer_unordered(concurrency);
+
+  while let Some(result) = results.next().await {
+    if tx.send(result).await.is_err() {
+      break;
+    }
+  }
+}
+
+async fn send(client: Option<Client>, request: BatchRequest) -> Result<(Response, Bytes), Error> {
+  let response = execute_request(client, request.method, &request.url, request.request).await?;
+  let body = response.bytes().await?;
+  Ok((response, body))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::client::{ClientBuilder, MockReply, MockRoute, MockTransport};
+
+  #[test]
+  fn yields_every_result_once() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .unwrap();
+    let mock = Arc::new(MockTransport::new());
+    mock.add(MockRoute {
+      matcher: Default::default(),
+      reply: MockReply {
+        body: Bytes::from_static(b"ok"),
+        ..MockReply::default()
+      },
+      times: Some(3),
+    });
+    let client = ClientBuilder {
+      mock: Some(mock),
+      ..ClientBuilder::default()
+    }
+    .build()
+    .unwrap();
+
+    let requests = (0..4)
+      .map(|page| BatchRequest {
+        method: Method::GET,
+        url: format!("https://api.test/items/{page}"),
+        request: Request::default(),
+      })
+      .collect();
+    let mut batch = Batch::new(Some(client), requests, 2);
+
+    let mut indexes = Vec::new();
+    let mut failed = 0;
+    runtime.block_on(async {
+      while let Some(result) = batch.next().await {
+        indexes.push(result.index);
+        match result.outcome {
+          Ok((_, body)) => assert_eq!(body, "ok"),
+          Err(Error::Mock(_)) => failed += 1,
+          Err(err) => panic!("unexpected error: {err}"),
+        }
+      }
+    });
+    indexes.sort_unstable();
+    assert_eq!(indexes, [0, 1, 2, 3]);
+    assert_eq!(failed, 1);
+  }
+}
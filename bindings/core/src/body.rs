@@ -0,0 +1,341 @@
+//! Request body adapters.
+
+use std::{
+  future::Future,
+  io,
+  path::PathBuf,
+  pin::Pin,
+  task::{ready, Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use http::HeaderMap;
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tokio::{io::AsyncReadExt, sync::mpsc};
+
+/// Chunk size used when streaming files from disk.
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+type Produce = Box<
+  dyn FnOnce(mpsc::Sender<io::Result<Bytes>>) -> Pin<Box<dyn Future<Output = ()> + Send>>
+    + Send
+    + Sync,
+>;
+
+/// A body whose chunks come from a producer task started the first time the body is polled.
+///
+/// The channel holds a single chunk, so the producer is paced by the connection.
+pub struct ProducerBody {
+  rx: mpsc::Receiver<io::Result<Bytes>>,
+  start: Option<(mpsc::Sender<io::Result<Bytes>>, Produce)>,
+  length: Option<u64>,
+}
+
+impl ProducerBody {
+  /// Create a body fed by `produce`; `length` is reported as the exact size when known.
+  pub fn new<F, Fut>(length: Option<u64>, produce: F) -> Self
+  where
+    F: FnOnce(mpsc::Sender<io::Result<Bytes>>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+  {
+    let (tx, rx) = mpsc::channel(1);
+    let produce: Produce = Box::new(move |tx| Box::pin(produce(tx)));
+    Self {
+      rx,
+      start: Some((tx, produce)),
+      length,
+    }
+  }
+
+  /// Stream a file from disk without loading it into memory.
+  pub fn file(path: impl Into<PathBuf>, length: Option<u64>) -> Self {
+    let path = path.into();
+    Self::new(length, move |tx| async move {
+      let mut file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(err) => {
+          let _ = tx.send(Err(err)).await;
+          return;
+        }
+      };
+
+      loop {
+        let mut buf = BytesMut::with_capacity(FILE_CHUNK_SIZE);
+        match file.read_buf(&mut buf).await {
+          Ok(0) => break,
+          Ok(_) => {
+            if tx.send(Ok(buf.freeze())).await.is_err() {
+              break;
+            }
+          }
+          Err(err) => {
+            let _ = tx.send(Err(err)).await;
+            break;
+          }
+        }
+      }
+    })
+  }
+}
+
+impl Body for ProducerBody {
+  type Data = Bytes;
+  type Error = io::Error;
+
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    if let Some((tx, produce)) = self.start.take() {
+      tokio::spawn(produce(tx));
+    }
+
+    self
+      .rx
+      .poll_recv(cx)
+      .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    match self.length {
+      Some(length) => SizeHint::with_exact(length),
+      None => SizeHint::default(),
+    }
+  }
+}
+
+pin_project! {
+  /// A body that emits `trailers` once the inner body has produced all of its data.
+  ///
+  /// Trailers already sent by the inner body are merged with the extra ones.
+  pub struct WithTrailers<B> {
+    #[pin]
+    inner: B,
+    trailers: Option<HeaderMap>,
+  }
+}
+
+impl<B> WithTrailers<B> {
+  pub fn new(inner: B, trailers: HeaderMap) -> Self {
+    Self {
+      inner,
+      trailers: Some(trailers),
+    }
+  }
+}
+
+impl<B> Body for WithTrailers<B>
+where
+  B: Body<Data = Bytes>,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let this = self.project();
+    match ready!(this.inner.poll_frame(cx)) {
+      Some(Ok(frame)) => match frame.into_trailers() {
+        Ok(mut trailers) => {
+          if let Some(extra) = this.trailers.take() {
+            trailers.extend(extra);
+          }
+          Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+        }
+        Err(frame) => Poll::Ready(Some(Ok(frame))),
+      },
+      Some(Err(err)) => Poll::Ready(Some(Err(err))),
+      None => Poll::Ready(
+        this
+          .trailers
+          .take()
+          .map(|trailers| Ok(Frame::trailers(trailers))),
+      ),
+    }
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.trailers.is_none() && self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    // Never report an exact size: HTTP/1.1 can only carry trailers with chunked encoding.
+    let mut hint = SizeHint::new();
+    hint.set_lower(self.inner.size_hint().lower());
+    hint
+  }
+}
+
+pin_project! {
+  /// A body that emits `prefix` before the data of the inner body, for bodies whose start has
+  /// already been read.
+  pub struct Prefixed<B> {
+    prefix: Option<Bytes>,
+    #[pin]
+    inner: B,
+  }
+}
+
+impl<B> Prefixed<B> {
+  pub fn new(prefix: Bytes, inner: B) -> Self {
+    Self {
+      prefix: Some(prefix).filter(|prefix| !prefix.is_empty()),
+      inner,
+    }
+  }
+}
+
+impl<B> Body for Prefixed<B>
+where
+  B: Body<Data = Bytes>,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let this = self.project();
+    match this.prefix.take() {
+      Some(prefix) => Poll::Ready(Some(Ok(Frame::data(prefix)))),
+      None => this.inner.poll_frame(cx),
+    }
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.prefix.is_none() && self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    let prefix = self.prefix.as_ref().map_or(0, |prefix| prefix.len() as u64);
+    let inner = self.inner.size_hint();
+    let mut hint = SizeHint::new();
+    hint.set_lower(inner.lower() + prefix);
+    if let Some(upper) = inner.upper() {
+      hint.set_upper(upper + prefix);
+    }
+    hint
+  }
+}
+
+pin_project! {
+  /// A body that holds its data back until `gate` completes.
+  pub struct Gated<B> {
+    gate: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
+    #[pin]
+    inner: B,
+  }
+}
+
+impl<B> Gated<B> {
+  pub fn new(inner: B, gate: impl Future<Output = ()> + Send + Sync + 'static) -> Self {
+    Self {
+      gate: Some(Box::pin(gate)),
+      inner,
+    }
+  }
+}
+
+impl<B> Body for Gated<B>
+where
+  B: Body<Data = Bytes>,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let this = self.project();
+    if let Some(gate) = this.gate {
+      ready!(gate.as_mut().poll(cx));
+      *this.gate = None;
+    }
+    this.inner.poll_frame(cx)
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use http::HeaderValue;
+  use http_body_util::{BodyExt, Full};
+
+  #[test]
+  fn streams_file_in_chunks() {
+    let path = std::env::temp_dir().join(format!("nitai-body-{}", std::process::id()));
+    let contents = vec![7u8; FILE_CHUNK_SIZE * 2 + 10];
+    std::fs::write(&path, &contents).unwrap();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .build()
+      .unwrap();
+    let body = ProducerBody::file(&path, Some(contents.len() as u64));
+    assert_eq!(body.size_hint().exact(), Some(contents.len() as u64));
+
+    let collected = runtime.block_on(body.collect()).unwrap().to_bytes();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(collected.as_ref(), contents.as_slice());
+  }
+
+  #[test]
+  fn emits_prefix_before_inner_data() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .build()
+      .unwrap();
+    let body = Prefixed::new(
+      Bytes::from_static(b"<html>"),
+      Full::new(Bytes::from_static(b"</html>")),
+    );
+    assert_eq!(body.size_hint().exact(), Some(13));
+
+    let collected = runtime.block_on(body.collect()).unwrap().to_bytes();
+    assert_eq!(collected, "<html></html>");
+  }
+
+  #[test]
+  fn appends_trailers_after_data() {
+    let mut trailers = HeaderMap::new();
+    trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+
+    let body = WithTrailers::new(Full::new(Bytes::from_static(b"hello")), trailers);
+    assert!(body.size_hint().exact().is_none());
+
+    let collected = futures::executor::block_on(body.collect()).unwrap();
+    assert_eq!(
+      collected.trailers().and_then(|t| t.get("x-checksum")),
+      Some(&HeaderValue::from_static("abc"))
+    );
+    assert_eq!(collected.to_bytes(), Bytes::from_static(b"hello"));
+  }
+
+  #[test]
+  fn holds_data_until_the_gate_opens() {
+    let (open, opened) = tokio::sync::oneshot::channel::<()>();
+    let mut body = Gated::new(Full::new(Bytes::from_static(b"hello")), async move {
+      let _ = opened.await;
+    });
+    assert_eq!(body.size_hint().exact(), Some(5));
+
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(Pin::new(&mut body).poll_frame(&mut cx).is_pending());
+
+    open.send(()).unwrap();
+    let collected = futures::executor::block_on(body.collect()).unwrap();
+    assert_eq!(collected.to_bytes(), "hello");
+  }
+}
@@ -1,24 +1,121 @@
+mod cache;
+mod cassette;
+mod circuit_breaker;
+pub(crate) mod concurrency;
 mod dns;
-
-use std::{fs, net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
-
+pub(crate) mod har;
+mod informational;
+mod middleware;
+mod mock;
+mod navigation;
+mod prepare;
+mod rate_limit;
+mod redirect;
+mod retry;
+mod single_flight;
+
+use std::{
+  fs,
+  future::Future,
+  mem,
+  net::IpAddr,
+  path::PathBuf,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use futures_util::TryFutureExt;
 use wreq::redirect::Policy;
 use wreq::{self, Proxy};
 use wreq_util::EmulationOption;
 
-use crate::{Error, Request, Response, WebSocket, WebSocketRequest};
-
+use crate::{
+  abort::{run_abortable, AbortSignal},
+  body::WithTrailers,
+  params::{append_query, encode_params, SpaceEncoding},
+  Error, Request, Response, WebSocket, WebSocketRequest,
+};
+
+pub use cache::{CacheStatus, HttpCache, DEFAULT_CACHE_ENTRIES, DEFAULT_MAX_CACHED_BODY};
+pub use cassette::{Cassette, CassetteMode, DEFAULT_REDACTED_HEADERS};
+pub use circuit_breaker::{
+  CircuitBreaker, CircuitBreakerPolicy, CircuitState, CircuitStatus, CircuitTicket,
+};
+pub use concurrency::{ConcurrencyLimiter, ConcurrencyPermit, ConcurrencyStats, HostConcurrency};
 pub use dns::HickoryDnsResolver;
+pub use har::HarRecorder;
+pub use informational::{InformationalCallback, InformationalResponse, DEFAULT_EXPECT_CONTINUE};
+pub use middleware::{HookAction, HookRequest, Middleware, MAX_HOOK_RETRIES};
+pub use mock::{
+  MockCall, MockFailure, MockMatcher, MockReply, MockRoute, MockTransport, UrlPattern,
+};
+pub use navigation::{find_meta_refresh, parse_refresh, Refresh};
+pub use prepare::{PreparedBody, PreparedRequest};
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use redirect::{RedirectAction, RedirectCallback, RedirectPolicy, RedirectStep};
+pub use retry::{RetryAttempt, RetryPolicy};
+pub use single_flight::{SingleFlight, DEFAULT_MAX_SHARED_BODY};
+
+use cassette::CassetteRequest;
+use har::{HarCapture, HarRequest};
+use informational::Informational;
+use middleware::{HookOutcome, MiddlewareStack};
+use mock::MockRequest;
+use navigation::Navigation;
+use prepare::{DefaultHeaders, HeaderLayers};
+use redirect::follow_redirects;
+
+/// Redirect limit used when neither the client nor the request sets one.
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
 
 /// Wrapper around the underlying wreq client.
 #[derive(Clone)]
 pub struct Client {
   inner: wreq::Client,
+  follow_refresh: bool,
+  allow_redirects: bool,
+  max_redirects: usize,
+  retry: Option<RetryPolicy>,
+  rate_limiter: Option<Arc<RateLimiter>>,
+  concurrency: Option<ConcurrencyLimiter>,
+  circuit_breaker: Option<Arc<CircuitBreaker>>,
+  redirect: Option<RedirectPolicy>,
+  detailed_history: bool,
+  har: Option<Arc<HarRecorder>>,
+  cassette: Option<Arc<Cassette>>,
+  mock: Option<Arc<MockTransport>>,
+  middleware: MiddlewareStack,
+  single_flight: Option<Arc<SingleFlight>>,
+  cache: Option<Arc<HttpCache>>,
+  default_headers: Arc<DefaultHeaders>,
+  // Kept for mocks, which answer in place of the inner client that applies them.
+  timeout: Option<Duration>,
+  read_timeout: Option<Duration>,
 }
 
 impl Client {
   pub fn new(inner: wreq::Client) -> Self {
-    Self { inner }
+    Self {
+      inner,
+      follow_refresh: false,
+      allow_redirects: true,
+      max_redirects: DEFAULT_MAX_REDIRECTS,
+      retry: None,
+      rate_limiter: None,
+      concurrency: None,
+      circuit_breaker: None,
+      redirect: None,
+      detailed_history: false,
+      har: None,
+      cassette: None,
+      mock: None,
+      middleware: Arc::new([]),
+      single_flight: None,
+      cache: None,
+      default_headers: Arc::default(),
+      timeout: None,
+      read_timeout: None,
+    }
   }
 
   pub fn inner(&self) -> &wreq::Client {
@@ -28,6 +125,39 @@ impl Client {
   pub fn into_inner(self) -> wreq::Client {
     self.inner
   }
+
+  /// In-flight and queued request counts, when the client limits concurrency.
+  pub fn concurrency_stats(&self) -> Option<ConcurrencyStats> {
+    self.concurrency.as_ref().map(ConcurrencyLimiter::stats)
+  }
+
+  /// The recorder capturing the client's exchanges, if any.
+  pub fn har(&self) -> Option<&Arc<HarRecorder>> {
+    self.har.as_ref()
+  }
+
+  /// The cassette answering the client's requests, if any.
+  pub fn cassette(&self) -> Option<&Arc<Cassette>> {
+    self.cassette.as_ref()
+  }
+
+  /// The mock transport answering the client's requests, if any.
+  pub fn mock(&self) -> Option<&Arc<MockTransport>> {
+    self.mock.as_ref()
+  }
+
+  /// The cache answering the client's `GET` requests, if any.
+  pub fn cache(&self) -> Option<&Arc<HttpCache>> {
+    self.cache.as_ref()
+  }
+
+  /// Circuit state of every host contacted so far, when the client has a circuit breaker.
+  pub fn circuit_statuses(&self) -> Option<Vec<CircuitStatus>> {
+    self
+      .circuit_breaker
+      .as_ref()
+      .map(|breaker| breaker.statuses())
+  }
 }
 
 impl From<wreq::Client> for Client {
@@ -55,6 +185,33 @@ pub struct ClientBuilder {
   pub history: Option<bool>,
   pub allow_redirects: Option<bool>,
   pub max_redirects: Option<usize>,
+  pub follow_refresh: Option<bool>,
+  /// Default policy for following HTTP redirects hop by hop.
+  pub redirect: Option<RedirectPolicy>,
+  /// Follow redirects one hop at a time so each hop's headers, address and timing are recorded.
+  pub detailed_history: Option<bool>,
+  /// Records every exchange in HAR format, shared by all clones of the client.
+  pub har: Option<Arc<HarRecorder>>,
+  /// Answers requests from recorded exchanges, shared by all clones of the client.
+  pub cassette: Option<Arc<Cassette>>,
+  /// Answers requests with canned replies, shared by all clones of the client.
+  pub mock: Option<Arc<MockTransport>>,
+  /// Hooks run around every request, in order.
+  pub middleware: Vec<Arc<dyn Middleware>>,
+  /// Shares one response between identical requests in flight at the same time, across all
+  /// clones of the client.
+  pub single_flight: Option<Arc<SingleFlight>>,
+  /// Answers `GET` requests with stored responses while they are fresh, shared by all clones of
+  /// the client.
+  pub cache: Option<Arc<HttpCache>>,
+  /// Default retry policy for requests that don't set their own.
+  pub retry: Option<RetryPolicy>,
+  /// Paces requests sent through the client, shared by all its clones.
+  pub rate_limiter: Option<Arc<RateLimiter>>,
+  /// Caps requests in flight through the client, shared by all its clones.
+  pub concurrency: Option<ConcurrencyLimiter>,
+  /// Fails requests fast for hosts that keep failing, shared by all clones of the client.
+  pub circuit_breaker: Option<Arc<CircuitBreaker>>,
   pub cookie_store: Option<bool>,
   pub cookie_provider: Option<Arc<wreq::cookie::Jar>>,
   pub timeout: Option<Duration>,
@@ -96,6 +253,14 @@ impl ClientBuilder {
   pub fn build(mut self) -> Result<Client, Error> {
     let mut builder = wreq::Client::builder();
 
+    // Kept for `Client::prepare`, which shows what the inner client adds to each request.
+    let default_headers = DefaultHeaders::new(
+      self.emulation.clone(),
+      self.user_agent.as_deref(),
+      self.headers.as_ref(),
+      self.orig_headers.as_ref(),
+    )?;
+
     if let Some(emulation) = self.emulation.take() {
       builder = builder.emulation(emulation);
     }
@@ -120,7 +285,26 @@ impl ClientBuilder {
       builder = builder.history(history);
     }
 
-    match (self.allow_redirects.take(), self.max_redirects.take()) {
+    let allow_redirects = self.allow_redirects.take();
+    let max_redirects = self.max_redirects.take();
+    let follow_refresh = allow_redirects != Some(false) && self.follow_refresh.unwrap_or(false);
+    let redirect = self
+      .redirect
+      .take()
+      .filter(|_| allow_redirects != Some(false));
+    let retry = self.retry.take();
+    let rate_limiter = self.rate_limiter.take();
+    let concurrency = self.concurrency.take();
+    let circuit_breaker = self.circuit_breaker.take();
+    let detailed_history = self.detailed_history.take().unwrap_or(false);
+    let har = self.har.take();
+    let cassette = self.cassette.take();
+    let mock = self.mock.take();
+    let middleware = mem::take(&mut self.middleware);
+    let single_flight = self.single_flight.take();
+    let cache = self.cache.take();
+
+    match (allow_redirects, max_redirects) {
       (Some(false), _) => {
         builder = builder.redirect(Policy::none());
       }
@@ -139,7 +323,8 @@ impl ClientBuilder {
       builder = builder.cookie_store(cookie_store);
     }
 
-    if let Some(timeout) = self.timeout.take() {
+    let timeout = self.timeout.take();
+    if let Some(timeout) = timeout {
       builder = builder.timeout(timeout);
     }
 
@@ -147,7 +332,8 @@ impl ClientBuilder {
       builder = builder.connect_timeout(connect_timeout);
     }
 
-    if let Some(read_timeout) = self.read_timeout.take() {
+    let read_timeout = self.read_timeout.take();
+    if let Some(read_timeout) = read_timeout {
       builder = builder.read_timeout(read_timeout);
     }
 
@@ -297,21 +483,344 @@ impl ClientBuilder {
     builder
       .dns_resolver(HickoryDnsResolver::new())
       .build()
-      .map(Client::new)
+      .map(|inner| Client {
+        inner,
+        follow_refresh,
+        allow_redirects: allow_redirects != Some(false),
+        max_redirects: max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS),
+        retry,
+        rate_limiter,
+        concurrency,
+        circuit_breaker,
+        redirect,
+        detailed_history,
+        har,
+        cassette,
+        mock,
+        middleware: middleware.into(),
+        single_flight,
+        cache,
+        default_headers: Arc::new(default_headers),
+        timeout,
+        read_timeout,
+      })
       .map_err(Error::Library)
   }
 }
 
 /// Execute an HTTP request using either an existing client or the global request builder.
+///
+/// Failed attempts are retried according to the request's retry policy, or the client's, as long
+/// as the request can be replayed. Clients with single flight on answer identical requests in
+/// flight at the same time with one response, and clients with a cache answer `GET` requests
+/// from it when they can.
 pub async fn execute_request(
+  client: Option<Client>,
+  method: wreq::Method,
+  url: &str,
+  params: Request,
+) -> Result<Response, Error> {
+  let cache = client.as_ref().and_then(|client| client.cache.clone());
+  match (client, cache) {
+    (Some(client), Some(cache)) => cache::send(cache, client, method, url, params).await,
+    (client, _) => send_coalesced(client, method, url, params).await,
+  }
+}
+
+async fn send_coalesced(
+  client: Option<Client>,
+  method: wreq::Method,
+  url: &str,
+  params: Request,
+) -> Result<Response, Error> {
+  let flight = client
+    .as_ref()
+    .and_then(|client| client.single_flight.clone())
+    .and_then(|flights| Some((flights, single_flight::flight_key(&method, url, &params)?)));
+  match flight {
+    Some((flights, key)) => {
+      let signal = params.signal.clone();
+      flights
+        .run(key, signal, || {
+          send_with_retries(client, method, url, params)
+        })
+        .await
+    }
+    None => send_with_retries(client, method, url, params).await,
+  }
+}
+
+async fn send_with_retries(
   client: Option<Client>,
   method: wreq::Method,
   url: &str,
   mut params: Request,
 ) -> Result<Response, Error> {
+  let policy = params
+    .retry
+    .take()
+    .or_else(|| client.as_ref().and_then(|client| client.retry.clone()))
+    .filter(|policy| policy.allows(&method));
+  // Detailed history, HAR recording, cassettes, mocks and middleware need every hop as a separate
+  // request, which the default policy provides. Like the inner client's redirects, it keeps
+  // credentials, the client's default ones included, from other origins.
+  let hop_by_hop = params.detailed_history.take().unwrap_or_else(|| {
+    client
+      .as_ref()
+      .is_some_and(|client| client.detailed_history)
+  }) || client
+    .as_ref()
+    .is_some_and(|client| client.captures() || !client.middleware.is_empty());
+  // Hops are followed here rather than by the inner client, so its redirect settings are
+  // applied here too.
+  let allow_redirects = params
+    .allow_redirects
+    .unwrap_or_else(|| client.as_ref().is_none_or(|client| client.allow_redirects));
+  let redirect = params
+    .redirect
+    .take()
+    .or_else(|| client.as_ref().and_then(|client| client.redirect.clone()))
+    .or_else(|| hop_by_hop.then(RedirectPolicy::default))
+    .filter(|_| allow_redirects);
+  let Some(policy) = policy else {
+    return send_attempt(client, method, url, params, redirect.as_ref()).await;
+  };
+
+  let signal = params.signal.clone();
+  let mut attempts = Vec::new();
+  let mut attempt = 1;
+  loop {
+    let replay = if attempt < policy.max_attempts {
+      params.try_clone()
+    } else {
+      None
+    };
+
+    let result = send_attempt(
+      client.clone(),
+      method.clone(),
+      url,
+      params,
+      redirect.as_ref(),
+    )
+    .await;
+    let (Some(replay), Some(delay)) = (replay, policy.retry_delay(attempt, &result)) else {
+      return result.map(|mut response| {
+        response.set_attempts(attempts);
+        response
+      });
+    };
+
+    attempts.push(match &result {
+      Ok(response) => {
+        response.close();
+        RetryAttempt {
+          attempt,
+          status: Some(response.status),
+          error: None,
+          delay,
+        }
+      }
+      Err(err) => RetryAttempt {
+        attempt,
+        status: None,
+        error: Some(err.to_string()),
+        delay,
+      },
+    });
+
+    run_abortable(signal.as_ref(), async {
+      tokio::time::sleep(delay).await;
+      Ok(())
+    })
+    .await?;
+
+    params = replay;
+    attempt += 1;
+  }
+}
+
+async fn send_attempt(
+  client: Option<Client>,
+  method: wreq::Method,
+  url: &str,
+  params: Request,
+  redirect: Option<&RedirectPolicy>,
+) -> Result<Response, Error> {
+  match redirect {
+    Some(policy) => follow_redirects(client, method, url, params, policy).await,
+    None => send_request(client, method, url, params, false).await,
+  }
+}
+
+/// Send a single request; with `manual_redirects` redirect responses are returned as they are.
+async fn send_request(
+  client: Option<Client>,
+  method: wreq::Method,
+  url: &str,
+  params: Request,
+  manual_redirects: bool,
+) -> Result<Response, Error> {
+  // Hooks, recording, replaying and mocking need the request itself before it is sent.
+  let Some(built) = client
+    .as_ref()
+    .filter(|client| client.captures() || !client.middleware.is_empty())
+    .cloned()
+  else {
+    let (builder, outgoing) =
+      build_request(client.as_ref(), method, url, params, manual_redirects)?;
+    let (inner, request) = builder.build_split();
+    let mut request = request.map_err(Error::Library)?;
+    outgoing.informational.attach(&mut request);
+    return dispatch(client, outgoing, move || inner.execute(request)).await;
+  };
+
+  let mut params = params;
+  let mut retries = 0;
+  loop {
+    let replay = if !built.middleware.is_empty() && retries < MAX_HOOK_RETRIES {
+      params.try_clone()
+    } else {
+      None
+    };
+
+    let layers = HeaderLayers::capture(&params);
+    let read_timeout = params.read_timeout;
+    let (builder, mut outgoing) =
+      build_request(Some(&built), method.clone(), url, params, manual_redirects)?;
+    let mut request = builder.build().map_err(Error::Library)?;
+    let hooked = built.before_request(&mut request).await?;
+    let headers = layers.resolve(&built, &request);
+    built.capture(&mut outgoing, &request, headers, read_timeout);
+    // Attached after the capture, which records the body before it is held back.
+    outgoing.informational.attach(&mut request);
+
+    let inner = built.inner().clone();
+    let result = dispatch(client.clone(), outgoing, move || inner.execute(request)).await;
+    let Some(hooked) = hooked else {
+      return result;
+    };
+    match (built.after_request(&hooked, result).await, replay) {
+      (HookOutcome::Retry(result), Some(replay)) => {
+        if let Ok(response) = &result {
+          response.close();
+        }
+        params = replay;
+        retries += 1;
+      }
+      (HookOutcome::Retry(result) | HookOutcome::Done(result), _) => return result,
+    }
+  }
+}
+
+impl Client {
+  /// Whether requests need [`capture`](Self::capture) before they are sent.
+  fn captures(&self) -> bool {
+    self.har.is_some() || self.cassette.is_some() || self.mock.is_some()
+  }
+
+  /// Attach the client's HAR recorder, cassette and mocks to a built request, which was given
+  /// `read_timeout`.
+  fn capture(
+    &self,
+    outgoing: &mut Outgoing,
+    request: &wreq::Request,
+    headers: Vec<(http::HeaderName, http::HeaderValue)>,
+    read_timeout: Option<Duration>,
+  ) {
+    let captured = HarRequest::new(request, headers);
+    if self.cassette.is_some() || self.mock.is_some() {
+      outgoing.uri = Some(request.uri().clone());
+    }
+    let timeout = request.timeout().copied().or(self.timeout);
+    let timeout = [timeout, read_timeout.or(self.read_timeout)]
+      .into_iter()
+      .flatten()
+      .min();
+    outgoing.mock = self
+      .mock
+      .clone()
+      .map(|transport| MockRequest::new(transport, captured.clone(), timeout));
+    outgoing.cassette = self
+      .cassette
+      .clone()
+      .map(|cassette| CassetteRequest::new(cassette, captured.clone()));
+    outgoing.har = self
+      .har
+      .clone()
+      .map(|recorder| HarCapture::new(recorder, captured));
+  }
+}
+
+/// What a built request still goes through on its way out: the client's circuit breaker,
+/// concurrency and rate limits, refresh navigation and cancellation.
+pub(crate) struct Outgoing {
+  navigation: Option<Navigation>,
+  informational: Informational,
+  circuit_breaker: Option<(Arc<CircuitBreaker>, String)>,
+  concurrency: Option<(ConcurrencyLimiter, String, i32, Option<Duration>)>,
+  rate_limit: Option<(Arc<RateLimiter>, String)>,
+  signal: Option<AbortSignal>,
+  har: Option<HarCapture>,
+  cassette: Option<CassetteRequest>,
+  mock: Option<MockRequest>,
+  /// The request URI, reported by responses that don't come from the network.
+  uri: Option<http::Uri>,
+}
+
+/// Turn `params` into a request builder, leaving the client-side gates to [`dispatch`].
+fn build_request(
+  client: Option<&Client>,
+  method: wreq::Method,
+  url: &str,
+  mut params: Request,
+  manual_redirects: bool,
+) -> Result<(wreq::RequestBuilder, Outgoing), Error> {
+  let navigation = Navigation::from_request(client, &mut params);
+  let informational = Informational::from_request(&mut params);
+
+  // Percent-encoded spaces need the pairs serialized here rather than by the builder.
+  let space_encoding = params.space_encoding.take().unwrap_or_default();
+  let url = match (space_encoding, params.query.take()) {
+    (SpaceEncoding::Percent, Some(query)) => {
+      append_query(url, &encode_params(&query, space_encoding))
+    }
+    (_, query) => {
+      params.query = query;
+      url.to_string()
+    }
+  };
+
+  let host = || {
+    url::Url::parse(&url)
+      .ok()
+      .and_then(|url| url.host_str().map(str::to_string))
+      .unwrap_or_default()
+  };
+
+  let circuit_breaker = client
+    .and_then(|client| client.circuit_breaker.clone())
+    .map(|breaker| (breaker, host()));
+
+  let concurrency = client
+    .and_then(|client| client.concurrency.clone())
+    .map(|limiter| {
+      let queue_timeout = params.queue_timeout.take().or(limiter.queue_timeout());
+      let priority = params.priority.take().unwrap_or_default();
+      (limiter, host(), priority, queue_timeout)
+    });
+
+  // Requests are paced per host unless the caller groups them under its own key.
+  let rate_limit = client
+    .and_then(|client| client.rate_limiter.clone())
+    .map(|limiter| {
+      let key = params.rate_limit_key.take().unwrap_or_else(host);
+      (limiter, key)
+    });
+
   let mut builder = match client {
-    Some(client) => client.into_inner().request(method, url),
-    None => wreq::request(method, url),
+    Some(client) => client.inner().request(method, &url),
+    None => wreq::request(method, &url),
   };
 
   if let Some(emulation) = params.emulation.take() {
@@ -397,12 +906,24 @@ pub async fn execute_request(
     (None, _) => {}
   }
 
+  if manual_redirects {
+    builder = builder.redirect(Policy::none());
+  }
+
   if let Some(query) = params.query.take() {
     builder = builder.query(&query);
   }
 
   if let Some(form) = params.form.take() {
-    builder = builder.form(&form);
+    builder = match space_encoding {
+      SpaceEncoding::Plus => builder.form(&form),
+      SpaceEncoding::Percent => builder
+        .header(
+          wreq::header::CONTENT_TYPE,
+          wreq::header::HeaderValue::from_static("application/x-www-form-urlencoded"),
+        )
+        .body(encode_params(&form, space_encoding)),
+    };
   }
 
   if let Some(json) = params.json.take() {
@@ -417,6 +938,10 @@ pub async fn execute_request(
     builder = builder.multipart(multipart);
   }
 
+  if let Some(trailers) = params.trailers.take() {
+    builder = with_trailers(builder, trailers)?;
+  }
+
   if let Some(gzip) = params.gzip.take() {
     builder = builder.gzip(gzip);
   }
@@ -433,11 +958,143 @@ pub async fn execute_request(
     builder = builder.zstd(zstd);
   }
 
-  builder
-    .send()
-    .await
-    .map(Response::new)
-    .map_err(Error::Library)
+  let outgoing = Outgoing {
+    navigation,
+    informational,
+    circuit_breaker,
+    concurrency,
+    rate_limit,
+    signal: params.signal.take(),
+    har: None,
+    cassette: None,
+    mock: None,
+    uri: None,
+  };
+  Ok((builder, outgoing))
+}
+
+/// Send `trailers` after the body `builder` ended up with, be it raw, JSON, a form or multipart.
+fn with_trailers(
+  builder: wreq::RequestBuilder,
+  trailers: http::HeaderMap,
+) -> Result<wreq::RequestBuilder, Error> {
+  let (inner, request) = builder.build_split();
+  let mut request = request.map_err(Error::Library)?;
+
+  // Announce the trailer fields up front; HTTP/1.1 peers drop undeclared trailers.
+  let names = trailers
+    .keys()
+    .map(|name| name.as_str())
+    .collect::<Vec<_>>()
+    .join(", ");
+  let headers = request.headers_mut();
+  headers.insert(
+    wreq::header::TRAILER,
+    wreq::header::HeaderValue::from_str(&names)?,
+  );
+  // Trailers need chunked encoding, which a fixed length would rule out.
+  headers.remove(wreq::header::CONTENT_LENGTH);
+
+  let body = request
+    .body_mut()
+    .take()
+    .unwrap_or_else(|| wreq::Body::from(Vec::new()));
+  *request.body_mut() = Some(wreq::Body::wrap(WithTrailers::new(body, trailers)));
+  Ok(wreq::RequestBuilder::from_parts(inner, request))
+}
+
+/// Pass the client-side gates, then send with `send`.
+async fn dispatch<F>(
+  client: Option<Client>,
+  outgoing: Outgoing,
+  send: impl FnOnce() -> F,
+) -> Result<Response, Error>
+where
+  F: Future<Output = Result<wreq::Response, wreq::Error>>,
+{
+  let Outgoing {
+    navigation,
+    informational,
+    circuit_breaker,
+    concurrency,
+    rate_limit,
+    signal,
+    har,
+    cassette,
+    mock,
+    uri,
+  } = outgoing;
+  let mut response = run_abortable(signal.as_ref(), async move {
+    let queued = Instant::now();
+    // Rejected before queueing so that requests to a failing host don't hold slots.
+    let ticket = circuit_breaker
+      .map(|(breaker, host)| breaker.admit(&host))
+      .transpose()?;
+
+    // Paced before queueing so that a request waiting for its turn doesn't hold a slot.
+    if let Some((limiter, key)) = rate_limit {
+      limiter.acquire(&key).await;
+    }
+
+    let permit = match concurrency {
+      Some((limiter, host, priority, Some(queue_timeout))) => Some(
+        tokio::time::timeout(queue_timeout, limiter.acquire(&host, priority))
+          .await
+          .map_err(|_| Error::QueueTimeout(queue_timeout))?,
+      ),
+      Some((limiter, host, priority, None)) => Some(limiter.acquire(&host, priority).await),
+      None => None,
+    };
+
+    let started = Instant::now();
+    let live = || send().map_err(Error::Library);
+    let response = match (&mock, &cassette) {
+      (Some(mock), Some(cassette)) => mock.send(|| cassette.send(live)).await,
+      (Some(mock), None) => mock.send(live).await,
+      (None, Some(cassette)) => cassette.send(live).await,
+      (None, None) => live().await,
+    };
+    let elapsed = started.elapsed();
+    if let Some(ticket) = ticket {
+      match &response {
+        Ok(response) => ticket.record_status(response.status()),
+        Err(_) => ticket.record_failure(),
+      }
+    }
+
+    let blocked = started.duration_since(queued);
+    let har_body = match (har, &response) {
+      (Some(capture), Ok(response)) => Some(capture.record(response, blocked, elapsed)),
+      (Some(capture), Err(err)) => {
+        capture.record_error(err, blocked, elapsed);
+        None
+      }
+      (None, _) => None,
+    };
+    let response = response?;
+
+    let mut response = Response::new(response);
+    response.set_elapsed(elapsed);
+    response.set_informational(informational.take());
+    response.set_har(har_body);
+    if let Some(uri) = uri {
+      response.uri = uri;
+    }
+
+    // The slot stays taken until the body has been read or the response is closed.
+    response.set_permit(permit);
+    Ok(response)
+  })
+  .await?;
+
+  // Body reads stay cancellable after the response head has arrived.
+  response.set_signal(signal);
+
+  // Refresh hops are requests of their own, which pass the gates above again.
+  match navigation {
+    Some(navigation) => navigation.follow(client, response).await,
+    None => Ok(response),
+  }
 }
 
 /// Execute a WebSocket request using either an existing client or the global builder.
@@ -541,6 +1198,10 @@ pub async fn execute_websocket_request(
     builder = builder.query(&query);
   }
 
-  let response = builder.send().await.map_err(Error::Library)?;
-  WebSocket::new(response).await
+  let signal = params.signal.take();
+  run_abortable(signal.as_ref(), async {
+    let response = builder.send().await.map_err(Error::Library)?;
+    WebSocket::new(response, signal.clone()).await
+  })
+  .await
 }
@@ -0,0 +1,876 @@
+//! A private HTTP cache following RFC 9111.
+
+use std::{
+  fs,
+  path::PathBuf,
+  process,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::{cassette, har, send_coalesced, Client};
+use crate::{
+  params::{append_query, encode_params},
+  Error, Request, Response,
+};
+
+/// Responses a memory cache keeps when no limit is given.
+pub const DEFAULT_CACHE_ENTRIES: usize = 1000;
+
+/// Largest body, in bytes, the cache stores when no limit is given.
+pub const DEFAULT_MAX_CACHED_BODY: usize = 10 * 1024 * 1024;
+
+/// How the cache answered a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+  /// Served from the cache while fresh.
+  Hit,
+  /// Served from the cache while stale, with a revalidation started in the background.
+  Stale,
+  /// Served from the cache after the server confirmed it with `304 Not Modified`.
+  Revalidated,
+  /// Sent to the server.
+  Miss,
+}
+
+/// Stores responses to `GET` requests and serves them while they are fresh, as a private cache
+/// such as a browser's would.
+///
+/// Freshness comes from `Cache-Control` and `Expires`, or from `Last-Modified` when neither is
+/// present. Stale responses are revalidated with `If-None-Match` and `If-Modified-Since`, and
+/// served while revalidating in the background within their `stale-while-revalidate` window.
+/// Responses are stored per `Vary` header values, and per credentials so that users of one client
+/// don't see each other's responses. Successful unsafe requests evict the URL they were sent to.
+///
+/// Responses that followed redirects or whose body is larger than the size limit are streamed
+/// to the caller without being stored.
+#[derive(Debug)]
+pub struct HttpCache {
+  store: Store,
+  max_body_size: usize,
+}
+
+#[derive(Debug)]
+enum Store {
+  Memory {
+    entries: Mutex<IndexMap<String, Vec<CacheEntry>>>,
+    max_entries: usize,
+  },
+  /// One JSON file per URL, named after a hash of the key. Files are replaced whole by renaming
+  /// a temporary file over them, so readers never see a partial write.
+  Disk { dir: PathBuf },
+}
+
+/// A stored response, and the request header values it was chosen for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+  /// Request headers named by the response's `Vary`, with their values.
+  vary: Vec<(String, Option<String>)>,
+  url: String,
+  version: String,
+  status: u16,
+  headers: Vec<(String, String)>,
+  /// Unix times in seconds when the request was sent and the response arrived.
+  requested: u64,
+  received: u64,
+  #[serde(with = "base64_body")]
+  body: Bytes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+  key: String,
+  entries: Vec<CacheEntry>,
+}
+
+/// The `Cache-Control` directives this cache acts on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Directives {
+  no_store: bool,
+  no_cache: bool,
+  must_revalidate: bool,
+  only_if_cached: bool,
+  max_age: Option<u64>,
+  stale_while_revalidate: Option<u64>,
+}
+
+impl HttpCache {
+  /// A cache held in memory, keeping the [`DEFAULT_CACHE_ENTRIES`] most recently stored URLs.
+  pub fn memory() -> Self {
+    Self {
+      store: Store::Memory {
+        entries: Mutex::default(),
+        max_entries: DEFAULT_CACHE_ENTRIES,
+      },
+      max_body_size: DEFAULT_MAX_CACHED_BODY,
+    }
+  }
+
+  /// A cache stored in `dir`, which is created when missing.
+  pub fn disk(dir: impl Into<PathBuf>) -> Result<Self, Error> {
+    let dir = dir.into();
+    fs::create_dir_all(&dir)?;
+    Ok(Self {
+      store: Store::Disk { dir },
+      max_body_size: DEFAULT_MAX_CACHED_BODY,
+    })
+  }
+
+  /// URLs a memory cache keeps before evicting the least recently stored. Ignored on disk.
+  pub fn with_max_entries(mut self, max: usize) -> Self {
+    if let Store::Memory { max_entries, .. } = &mut self.store {
+      *max_entries = max.max(1);
+    }
+    self
+  }
+
+  /// Largest body, in bytes, a stored response may have. Defaults to
+  /// [`DEFAULT_MAX_CACHED_BODY`].
+  pub fn with_max_body_size(mut self, max: usize) -> Self {
+    self.max_body_size = max;
+    self
+  }
+
+  /// Drop every stored response.
+  pub fn clear(&self) -> Result<(), Error> {
+    match &self.store {
+      Store::Memory { entries, .. } => entries.lock().unwrap().clear(),
+      Store::Disk { dir } => {
+        for file in fs::read_dir(dir)? {
+          let path = file?.path();
+          if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+          {
+            fs::remove_file(path)?;
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  async fn load(&self, key: &str) -> Vec<CacheEntry> {
+    match &self.store {
+      Store::Memory { entries, .. } => entries
+        .lock()
+        .unwrap()
+        .get(key)
+        .cloned()
+        .unwrap_or_default(),
+      Store::Disk { dir } => tokio::fs::read(file_path(dir, key))
+        .await
+        .ok()
+        .and_then(|file| serde_json::from_slice::<CacheFile>(&file).ok())
+        .filter(|file| file.key == key)
+        .map(|file| file.entries)
+        .unwrap_or_default(),
+    }
+  }
+
+  /// Replace the responses stored for `key`. Failing to write is not an error for the request.
+  async fn save(&self, key: &str, stored: Vec<CacheEntry>) {
+    match &self.store {
+      Store::Memory {
+        entries,
+        max_entries,
+      } => {
+        let mut entries = entries.lock().unwrap();
+        entries.shift_remove(key);
+        if !stored.is_empty() {
+          entries.insert(key.to_string(), stored);
+        }
+        while entries.len() > *max_entries {
+          entries.shift_remove_index(0);
+        }
+      }
+      Store::Disk { dir } => {
+        let path = file_path(dir, key);
+        if stored.is_empty() {
+          let _ = tokio::fs::remove_file(path).await;
+          return;
+        }
+        let file = CacheFile {
+          key: key.to_string(),
+          entries: stored,
+        };
+        if let Ok(file) = serde_json::to_vec(&file) {
+          let _ = write_atomically(&path, file).await;
+        }
+      }
+    }
+  }
+
+  /// The stored response chosen for a request with `headers`.
+  async fn lookup(&self, key: &str, headers: &HeaderMap) -> Option<CacheEntry> {
+    self
+      .load(key)
+      .await
+      .into_iter()
+      .rev()
+      .find(|entry| entry.matches(headers))
+  }
+
+  async fn store(&self, key: &str, entry: CacheEntry) {
+    let mut stored = self.load(key).await;
+    stored.retain(|stored| stored.vary != entry.vary);
+    stored.push(entry);
+    self.save(key, stored).await;
+  }
+
+  async fn invalidate(&self, key: &str) {
+    self.save(key, Vec::new()).await;
+  }
+}
+
+/// Send a request through `cache`, answering it from the cache when it can.
+pub(super) async fn send(
+  cache: Arc<HttpCache>,
+  client: Client,
+  method: Method,
+  url: &str,
+  params: Request,
+) -> Result<Response, Error> {
+  let key = cache_key(url, &params);
+  if method != Method::GET || !is_cacheable(&params) {
+    let unsafe_method = !method.is_safe();
+    let response = send_coalesced(Some(client), method, url, params).await?;
+    if unsafe_method && (response.status.is_success() || response.status.is_redirection()) {
+      cache.invalidate(&key).await;
+    }
+    return Ok(response);
+  }
+
+  let headers = params.headers.clone().unwrap_or_default();
+  let requested = Directives::from_request(&headers);
+  if requested.no_store {
+    return send_coalesced(Some(client), method, url, params).await;
+  }
+
+  let cached = cache.lookup(&key, &headers).await;
+  if let Some(entry) = &cached {
+    let now = SystemTime::now();
+    let age = entry.age(now);
+    let lifetime = entry.freshness_lifetime();
+    let directives = entry.directives();
+    let revalidate = directives.no_cache || requested.no_cache;
+    let fresh = age < lifetime && !revalidate && requested.max_age.is_none_or(|max| age <= max);
+    if fresh {
+      return Ok(entry.to_response(CacheStatus::Hit, age));
+    }
+
+    let within_window = directives
+      .stale_while_revalidate
+      .is_some_and(|window| age < lifetime.saturating_add(window));
+    if within_window && !revalidate && !directives.must_revalidate {
+      if let Some(replay) = params.try_clone() {
+        let stale = entry.to_response(CacheStatus::Stale, age);
+        let (cache, entry, url) = (cache.clone(), entry.clone(), url.to_string());
+        tokio::spawn(async move {
+          if let Ok(response) = refresh(&cache, client, &key, Some(entry), &url, replay).await {
+            response.close();
+          }
+        });
+        return Ok(stale);
+      }
+    }
+  }
+
+  if requested.only_if_cached {
+    let mut response = Response::buffered(
+      http::Version::HTTP_11,
+      StatusCode::GATEWAY_TIMEOUT,
+      HeaderMap::new(),
+      url.parse().unwrap_or_default(),
+      Bytes::new(),
+    );
+    response.set_cache_status(Some(CacheStatus::Miss));
+    return Ok(response);
+  }
+
+  refresh(&cache, client, &key, cached, url, params).await
+}
+
+/// Fetch the response to a `GET` request, revalidating `cached` when there is one, and store it.
+async fn refresh(
+  cache: &HttpCache,
+  client: Client,
+  key: &str,
+  cached: Option<CacheEntry>,
+  url: &str,
+  mut params: Request,
+) -> Result<Response, Error> {
+  let headers = params.headers.clone().unwrap_or_default();
+  let sent_to = url::Url::parse(&request_url(url, &params)).ok();
+  if let Some(entry) = &cached {
+    entry.add_validators(params.headers.get_or_insert_with(HeaderMap::new));
+  }
+
+  let requested = SystemTime::now();
+  let mut response = send_coalesced(Some(client), Method::GET, url, params).await?;
+  let received = SystemTime::now();
+
+  if let (Some(mut entry), StatusCode::NOT_MODIFIED) = (cached, response.status) {
+    response.close();
+    entry.freshen(&response.headers, requested, received);
+    let revalidated = entry.to_response(CacheStatus::Revalidated, entry.age(received));
+    cache.store(key, entry).await;
+    return Ok(revalidated);
+  }
+
+  // A redirected response belongs to another URL, and stays uncached rather than stored
+  // under this one. Clients without redirect history only tell by the final URL.
+  let redirected =
+    !response.history().is_empty() || url::Url::parse(&response.uri.to_string()).ok() != sent_to;
+  if is_storable(&response) && !redirected {
+    let declared = response
+      .headers
+      .get(header::CONTENT_LENGTH)
+      .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+    if declared.is_none_or(|length| length <= cache.max_body_size as u64) {
+      // Reading one byte past the limit tells a body that fits from one to stream on.
+      let limit = cache.max_body_size.saturating_add(1);
+      let body = response.peek(limit, |_| false).await?;
+      if body.len() <= cache.max_body_size {
+        let entry = CacheEntry::new(&response, &headers, body, requested, received);
+        cache.store(key, entry).await;
+      }
+    }
+  }
+  response.set_cache_status(Some(CacheStatus::Miss));
+  Ok(response)
+}
+
+impl CacheEntry {
+  fn new(
+    response: &Response,
+    request_headers: &HeaderMap,
+    body: Bytes,
+    requested: SystemTime,
+    received: SystemTime,
+  ) -> Self {
+    let vary = header_list(&response.headers, header::VARY)
+      .map(|name| {
+        let name = name.to_ascii_lowercase();
+        let value = joined(request_headers, &name);
+        (name, value)
+      })
+      .collect();
+
+    Self {
+      vary,
+      url: response.uri.to_string(),
+      version: format!("{:?}", response.version),
+      status: response.status.as_u16(),
+      headers: stored_headers(&response.headers).collect(),
+      requested: unix_seconds(requested),
+      received: unix_seconds(received),
+      body,
+    }
+  }
+
+  fn matches(&self, headers: &HeaderMap) -> bool {
+    self
+      .vary
+      .iter()
+      .all(|(name, value)| joined(headers, name) == *value)
+  }
+
+  fn header_map(&self) -> HeaderMap {
+    self
+      .headers
+      .iter()
+      .filter_map(|(name, value)| {
+        let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+        let value = HeaderValue::from_str(value).ok()?;
+        Some((name, value))
+      })
+      .collect()
+  }
+
+  fn header(&self, name: &HeaderName) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(stored, _)| stored.eq_ignore_ascii_case(name.as_str()))
+      .map(|(_, value)| value.as_str())
+  }
+
+  fn directives(&self) -> Directives {
+    Directives::parse(
+      self
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case(header::CACHE_CONTROL.as_str()))
+        .map(|(_, value)| value.as_str()),
+    )
+  }
+
+  fn date(&self) -> u64 {
+    self
+      .header(&header::DATE)
+      .and_then(parse_date)
+      .unwrap_or(self.received)
+  }
+
+  /// Seconds the response stays fresh for, per RFC 9111 section 4.2.1.
+  fn freshness_lifetime(&self) -> u64 {
+    if let Some(max_age) = self.directives().max_age {
+      return max_age;
+    }
+    if let Some(expires) = self.header(&header::EXPIRES) {
+      // Invalid dates, such as `0`, mean already expired.
+      return parse_date(expires).map_or(0, |expires| expires.saturating_sub(self.date()));
+    }
+
+    // Heuristic freshness: a tenth of the time since the last modification.
+    let heuristic = matches!(
+      self.status,
+      200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    );
+    match self.header(&header::LAST_MODIFIED).and_then(parse_date) {
+      Some(modified) if heuristic => self.date().saturating_sub(modified) / 10,
+      _ => 0,
+    }
+  }
+
+  /// Seconds since the response was generated, per RFC 9111 section 4.2.3.
+  fn age(&self, now: SystemTime) -> u64 {
+    let age_value = self
+      .header(&header::AGE)
+      .and_then(|age| age.trim().parse::<u64>().ok())
+      .unwrap_or(0);
+    let apparent_age = self.received.saturating_sub(self.date());
+    let response_delay = self.received.saturating_sub(self.requested);
+    let corrected_initial_age = apparent_age.max(age_value + response_delay);
+    corrected_initial_age + unix_seconds(now).saturating_sub(self.received)
+  }
+
+  fn add_validators(&self, headers: &mut HeaderMap) {
+    let validators = [
+      (header::ETAG, header::IF_NONE_MATCH),
+      (header::LAST_MODIFIED, header::IF_MODIFIED_SINCE),
+    ];
+    for (validator, condition) in validators {
+      if let Some(value) = self
+        .header(&validator)
+        .and_then(|value| HeaderValue::from_str(value).ok())
+      {
+        headers.insert(condition, value);
+      }
+    }
+  }
+
+  /// Take the header fields of a `304 Not Modified` answering a revalidation.
+  fn freshen(&mut self, headers: &HeaderMap, requested: SystemTime, received: SystemTime) {
+    let updated = stored_headers(headers)
+      .filter(|(name, _)| name != header::CONTENT_LENGTH.as_str())
+      .collect::<Vec<_>>();
+    self.headers.retain(|(stored, _)| {
+      !updated
+        .iter()
+        .any(|(name, _)| stored.eq_ignore_ascii_case(name))
+    });
+    self.headers.extend(updated);
+    self.requested = unix_seconds(requested);
+    self.received = unix_seconds(received);
+  }
+
+  fn to_response(&self, status: CacheStatus, age: u64) -> Response {
+    let mut headers = self.header_map();
+    headers.insert(header::AGE, HeaderValue::from(age));
+    let mut response = Response::buffered(
+      cassette::parse_version(Some(&self.version)),
+      StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK),
+      headers,
+      self.url.parse::<Uri>().unwrap_or_default(),
+      self.body.clone(),
+    );
+    response.set_cache_status(Some(status));
+    response
+  }
+}
+
+impl Directives {
+  fn parse<'a>(values: impl Iterator<Item = &'a str>) -> Self {
+    let mut directives = Self::default();
+    for directive in values.flat_map(|value| value.split(',')) {
+      let (name, argument) = match directive.split_once('=') {
+        Some((name, argument)) => (name, Some(argument.trim().trim_matches('"'))),
+        None => (directive, None),
+      };
+      let seconds = || argument.and_then(|argument| argument.parse::<u64>().ok());
+      match name.trim().to_ascii_lowercase().as_str() {
+        "no-store" => directives.no_store = true,
+        "no-cache" => directives.no_cache = true,
+        "must-revalidate" => directives.must_revalidate = true,
+        "only-if-cached" => directives.only_if_cached = true,
+        "max-age" => directives.max_age = seconds(),
+        "stale-while-revalidate" => directives.stale_while_revalidate = seconds(),
+        _ => {}
+      }
+    }
+    directives
+  }
+
+  /// The directives of a request, where `Pragma: no-cache` stands in for a missing
+  /// `Cache-Control`.
+  fn from_request(headers: &HeaderMap) -> Self {
+    if !headers.contains_key(header::CACHE_CONTROL)
+      && header_list(headers, header::PRAGMA).any(|value| value.eq_ignore_ascii_case("no-cache"))
+    {
+      return Self {
+        no_cache: true,
+        ..Self::default()
+      };
+    }
+    Self::parse(
+      headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok()),
+    )
+  }
+}
+
+/// Whether a `GET` request can be answered from the cache. Requests carrying their own
+/// conditions or ranges are left to the server.
+fn is_cacheable(params: &Request) -> bool {
+  let conditional = params.headers.as_ref().is_some_and(|headers| {
+    [
+      header::IF_NONE_MATCH,
+      header::IF_MODIFIED_SINCE,
+      header::IF_MATCH,
+      header::IF_UNMODIFIED_SINCE,
+      header::IF_RANGE,
+      header::RANGE,
+    ]
+    .iter()
+    .any(|name| headers.contains_key(name))
+  });
+  !conditional
+    && params.body.is_none()
+    && params.json.is_none()
+    && params.form.is_none()
+    && params.multipart.is_none()
+    && params.trailers.is_none()
+}
+
+/// Whether a response may be stored, per RFC 9111 section 3.
+fn is_storable(response: &Response) -> bool {
+  let directives = Directives::parse(
+    response
+      .headers
+      .get_all(header::CACHE_CONTROL)
+      .iter()
+      .filter_map(|value| value.to_str().ok()),
+  );
+  let explicit = directives.max_age.is_some() || response.headers.contains_key(header::EXPIRES);
+  let heuristic = matches!(
+    response.status.as_u16(),
+    200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+  );
+  !directives.no_store
+    && !header_list(&response.headers, header::VARY).any(|name| name == "*")
+    && (heuristic
+      || explicit
+        && !matches!(
+          response.status,
+          StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED
+        ))
+}
+
+/// The URL a request goes to, with its query merged in.
+fn request_url(url: &str, params: &Request) -> String {
+  match &params.query {
+    Some(query) => append_query(
+      url,
+      &encode_params(query, params.space_encoding.unwrap_or_default()),
+    ),
+    None => url.to_string(),
+  }
+}
+
+/// The URL with its query, and a hash of any credentials sent with it.
+fn cache_key(url: &str, params: &Request) -> String {
+  let url = request_url(url, params);
+  let mut key = url::Url::parse(&url).map_or(url, String::from);
+
+  let headers = params.headers.as_ref();
+  let authorization = headers
+    .and_then(|headers| headers.get(header::AUTHORIZATION))
+    .map(HeaderValue::as_bytes);
+  let cookie = headers
+    .map(|headers| {
+      headers
+        .get_all(header::COOKIE)
+        .iter()
+        .map(HeaderValue::as_bytes)
+        .collect::<Vec<_>>()
+    })
+    .filter(|cookie| !cookie.is_empty());
+  let cookies = params.cookies.as_ref().map(|cookies| {
+    cookies
+      .iter()
+      .map(HeaderValue::as_bytes)
+      .collect::<Vec<_>>()
+  });
+  let credentials = format!(
+    "{:?} {:?} {:?} {:?} {:?} {:?}",
+    authorization, cookie, cookies, params.auth, params.bearer_auth, params.basic_auth
+  );
+  if credentials != "None None None None None None" {
+    key.push_str(&format!(" #{:016x}", fnv1a(credentials.as_bytes())));
+  }
+  key
+}
+
+fn file_path(dir: &std::path::Path, key: &str) -> PathBuf {
+  dir.join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+}
+
+/// Write `contents` to a temporary file beside `path`, then rename it over `path`.
+async fn write_atomically(path: &std::path::Path, contents: Vec<u8>) -> std::io::Result<()> {
+  static WRITES: AtomicU64 = AtomicU64::new(0);
+  let temp = path.with_extension(format!(
+    "{}-{}.tmp",
+    process::id(),
+    WRITES.fetch_add(1, Ordering::Relaxed)
+  ));
+  if let Err(err) = tokio::fs::write(&temp, contents).await {
+    let _ = tokio::fs::remove_file(&temp).await;
+    return Err(err);
+  }
+  tokio::fs::rename(&temp, path).await
+}
+
+/// FNV-1a, which unlike the standard hasher is stable across builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+  })
+}
+
+/// Comma-separated values of a list header, such as `Vary`.
+fn header_list(headers: &HeaderMap, name: HeaderName) -> impl Iterator<Item = String> + '_ {
+  headers
+    .get_all(name)
+    .into_iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .map(|value| value.trim().to_string())
+    .filter(|value| !value.is_empty())
+}
+
+fn joined(headers: &HeaderMap, name: &str) -> Option<String> {
+  let values = headers
+    .get_all(name)
+    .iter()
+    .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+    .collect::<Vec<_>>();
+  (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// Header fields worth storing: everything but the connection-specific ones.
+fn stored_headers(headers: &HeaderMap) -> impl Iterator<Item = (String, String)> + '_ {
+  headers
+    .iter()
+    .filter(|(name, _)| {
+      !matches!(
+        name.as_str(),
+        "connection" | "keep-alive" | "transfer-encoding" | "te" | "trailer" | "upgrade"
+      )
+    })
+    .map(|(name, value)| {
+      (
+        name.to_string(),
+        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+      )
+    })
+}
+
+fn parse_date(value: &str) -> Option<u64> {
+  httpdate::parse_http_date(value.trim())
+    .ok()
+    .map(unix_seconds)
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+  time
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or(Duration::ZERO)
+    .as_secs()
+}
+
+mod base64_body {
+  use bytes::Bytes;
+  use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+  use super::har;
+
+  pub fn serialize<S: Serializer>(body: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&har::base64(body))
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    har::decode_base64(&text)
+      .map(Bytes::from)
+      .ok_or_else(|| D::Error::custom("invalid base64 body"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(headers: &[(&str, &str)]) -> CacheEntry {
+    CacheEntry {
+      vary: Vec::new(),
+      url: "https://api.test/items".to_string(),
+      version: "HTTP/1.1".to_string(),
+      status: 200,
+      headers: headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect(),
+      requested: 1_000_000,
+      received: 1_000_000,
+      body: Bytes::from_static(b"items"),
+    }
+  }
+
+  fn at(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+  }
+
+  #[test]
+  fn parses_directives() {
+    let directives = Directives::parse(
+      [
+        "max-age=\"60\", No-Cache",
+        "stale-while-revalidate=30, private",
+      ]
+      .into_iter(),
+    );
+    assert_eq!(directives.max_age, Some(60));
+    assert_eq!(directives.stale_while_revalidate, Some(30));
+    assert!(directives.no_cache);
+    assert!(!directives.no_store);
+  }
+
+  #[test]
+  fn computes_freshness_and_age() {
+    let cached = entry(&[("cache-control", "max-age=60"), ("age", "10")]);
+    assert_eq!(cached.freshness_lifetime(), 60);
+    assert_eq!(cached.age(at(1_000_020)), 30);
+
+    let expires = entry(&[
+      ("date", "Mon, 12 Jan 1970 13:46:40 GMT"),
+      ("expires", "Mon, 12 Jan 1970 13:48:20 GMT"),
+    ]);
+    assert_eq!(expires.freshness_lifetime(), 100);
+    assert_eq!(entry(&[("expires", "0")]).freshness_lifetime(), 0);
+
+    let modified = entry(&[("last-modified", "Mon, 12 Jan 1970 11:00:00 GMT")]);
+    assert_eq!(modified.freshness_lifetime(), 1_000);
+  }
+
+  #[test]
+  fn keys_responses_by_vary_values() {
+    let mut cached = entry(&[]);
+    cached.vary = vec![("accept".to_string(), Some("text/html".to_string()))];
+
+    let mut headers = HeaderMap::new();
+    assert!(!cached.matches(&headers));
+    headers.insert(header::ACCEPT, HeaderValue::from_static("text/html"));
+    assert!(cached.matches(&headers));
+  }
+
+  fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+      .build()
+      .unwrap()
+      .block_on(future)
+  }
+
+  #[test]
+  fn memory_store_evicts_oldest() {
+    let cache = HttpCache::memory().with_max_entries(2);
+    block_on(async {
+      for key in ["a", "b", "c"] {
+        cache.store(key, entry(&[])).await;
+      }
+      assert!(cache.load("a").await.is_empty());
+      assert_eq!(cache.load("c").await.len(), 1);
+
+      cache.clear().unwrap();
+      assert!(cache.load("c").await.is_empty());
+    });
+  }
+
+  #[test]
+  fn disk_store_replaces_files_whole() {
+    let dir = std::env::temp_dir().join(format!("nitai-cache-{}", process::id()));
+    let cache = HttpCache::disk(&dir).unwrap();
+    block_on(async {
+      cache.store("a", entry(&[])).await;
+      cache.store("a", entry(&[("etag", "\"v2\"")])).await;
+      let stored = cache.load("a").await;
+      assert_eq!(stored.len(), 1);
+      assert_eq!(stored[0].header(&header::ETAG), Some("\"v2\""));
+
+      let files = fs::read_dir(&dir).unwrap().count();
+      assert_eq!(files, 1);
+
+      cache.invalidate("a").await;
+      assert!(cache.load("a").await.is_empty());
+    });
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn keys_credentials_apart() {
+    let anonymous = cache_key("https://api.test/items", &Request::default());
+    let signed_in = cache_key(
+      "https://api.test/items",
+      &Request {
+        bearer_auth: Some("token".to_string()),
+        ..Request::default()
+      },
+    );
+    assert_eq!(anonymous, "https://api.test/items");
+    assert!(signed_in.starts_with("https://api.test/items #"));
+    assert!(!signed_in.contains("token"));
+
+    let with_cookie = |cookie: &'static str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::COOKIE, HeaderValue::from_static(cookie));
+      cache_key(
+        "https://api.test/items",
+        &Request {
+          headers: Some(headers),
+          ..Request::default()
+        },
+      )
+    };
+    let session = cache_key(
+      "https://api.test/items",
+      &Request {
+        cookies: Some(vec![HeaderValue::from_static("session=a")]),
+        ..Request::default()
+      },
+    );
+    assert_ne!(with_cookie("session=a"), anonymous);
+    assert_ne!(with_cookie("session=a"), with_cookie("session=b"));
+    assert_ne!(session, anonymous);
+  }
+}
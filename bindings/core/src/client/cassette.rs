@@ -0,0 +1,459 @@
+//! Replaying recorded exchanges in place of the network.
+
+use std::{
+  fs,
+  future::Future,
+  path::PathBuf,
+  sync::{Arc, Mutex},
+  time::Instant,
+};
+
+use bytes::Bytes;
+use http::{header, HeaderName, HeaderValue, StatusCode, Version};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+
+use super::har::{self, HarRequest};
+use crate::Error;
+
+/// What a [`Cassette`] does with requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CassetteMode {
+  /// Serve recorded responses only; requests without one fail.
+  #[default]
+  Replay,
+  /// Serve recorded responses, and send and record requests without one.
+  RecordMissing,
+  /// Send and record every request, replacing what the file held.
+  Record,
+}
+
+/// Exchanges stored in a HAR 1.2 file and served in place of the network.
+///
+/// A request matches a recording with the same method, URL and, unless body matching is off,
+/// body. Matching recordings are served in order, and the last one repeats once all have been
+/// served unless the cassette is strict. Credentials are redacted from recorded requests.
+#[derive(Debug)]
+pub struct Cassette {
+  path: PathBuf,
+  mode: CassetteMode,
+  match_body: bool,
+  strict: bool,
+  redact: Vec<HeaderName>,
+  interactions: Mutex<Vec<Interaction>>,
+  /// Held while the file is written, so writes land in the order they were made.
+  writer: tokio::sync::Mutex<()>,
+}
+
+/// Request headers whose values are left out of recordings by default.
+pub const DEFAULT_REDACTED_HEADERS: [HeaderName; 3] = [
+  header::AUTHORIZATION,
+  header::PROXY_AUTHORIZATION,
+  header::COOKIE,
+];
+
+const REDACTED: &str = "[redacted]";
+
+/// How a request fared against the recordings.
+enum Lookup {
+  Found(wreq::Response),
+  /// Matching recordings exist, but a strict cassette has served them all.
+  Exhausted,
+  Missing,
+}
+
+#[derive(Debug)]
+struct Interaction {
+  entry: Value,
+  method: String,
+  url: String,
+  body: Bytes,
+  version: Version,
+  status: StatusCode,
+  headers: Vec<(HeaderName, HeaderValue)>,
+  content: Bytes,
+  served: bool,
+}
+
+/// A request about to be answered from a cassette or the network.
+pub(crate) struct CassetteRequest {
+  cassette: Arc<Cassette>,
+  request: HarRequest,
+}
+
+impl Cassette {
+  /// Open the cassette at `path`. Replaying needs the file to exist; recording creates it on
+  /// the first recorded exchange.
+  pub fn open(path: impl Into<PathBuf>, mode: CassetteMode) -> Result<Self, Error> {
+    let path = path.into();
+    let interactions = match mode {
+      CassetteMode::Record => Vec::new(),
+      CassetteMode::RecordMissing if !path.exists() => Vec::new(),
+      _ => load(&fs::read(&path)?)?,
+    };
+    Ok(Self {
+      path,
+      mode,
+      match_body: true,
+      strict: false,
+      redact: DEFAULT_REDACTED_HEADERS.to_vec(),
+      interactions: Mutex::new(interactions),
+      writer: tokio::sync::Mutex::new(()),
+    })
+  }
+
+  /// Whether requests must have the recorded body to match. Defaults to `true`.
+  pub fn with_match_body(mut self, match_body: bool) -> Self {
+    self.match_body = match_body;
+    self
+  }
+
+  /// Serve each recording once. Requests whose recordings were all served are then treated as
+  /// unmatched: replaying fails them, and recording missing exchanges sends them.
+  pub fn with_strict(mut self, strict: bool) -> Self {
+    self.strict = strict;
+    self
+  }
+
+  /// Request headers, and with `Cookie` the request's cookies, whose values are replaced in
+  /// recordings. Defaults to [`DEFAULT_REDACTED_HEADERS`].
+  pub fn with_redacted_headers(mut self, names: Vec<HeaderName>) -> Self {
+    self.redact = names;
+    self
+  }
+
+  pub fn mode(&self) -> CassetteMode {
+    self.mode
+  }
+
+  /// Number of recorded exchanges.
+  pub fn len(&self) -> usize {
+    self.interactions.lock().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  fn replay(&self, request: &HarRequest) -> Lookup {
+    let url = normalize_url(request.url());
+    let matches = |interaction: &Interaction| {
+      interaction.method.eq_ignore_ascii_case(request.method())
+        && interaction.url == url
+        && (!self.match_body
+          || request.is_streamed()
+          || interaction.body == request.body().cloned().unwrap_or_default())
+    };
+
+    let mut interactions = self.interactions.lock().unwrap();
+    let unserved = interactions
+      .iter()
+      .position(|interaction| !interaction.served && matches(interaction));
+    let index = match unserved {
+      Some(index) => index,
+      None if self.strict && interactions.iter().any(matches) => return Lookup::Exhausted,
+      None if self.strict => return Lookup::Missing,
+      None => match interactions.iter().rposition(matches) {
+        Some(index) => index,
+        None => return Lookup::Missing,
+      },
+    };
+    let interaction = &mut interactions[index];
+    interaction.served = true;
+    Lookup::Found(interaction.to_response())
+  }
+
+  async fn record(&self, mut entry: Value) -> Result<(), Error> {
+    redact(&mut entry, &self.redact);
+    let _writing = self.writer.lock().await;
+    let document = {
+      let mut interactions = self.interactions.lock().unwrap();
+      if let Some(mut interaction) = Interaction::from_entry(entry) {
+        interaction.served = true;
+        interactions.push(interaction);
+      }
+      let entries = interactions
+        .iter()
+        .map(|interaction| interaction.entry.clone())
+        .collect();
+      har::document(entries)
+    };
+
+    let document =
+      serde_json::to_vec_pretty(&document).map_err(|err| Error::Cassette(err.to_string()))?;
+    tokio::fs::write(&self.path, document).await?;
+    Ok(())
+  }
+}
+
+impl CassetteRequest {
+  pub(crate) fn new(cassette: Arc<Cassette>, request: HarRequest) -> Self {
+    Self { cassette, request }
+  }
+
+  /// Answer from the cassette, falling back to `send` when the mode records.
+  pub(crate) async fn send<F>(&self, send: impl FnOnce() -> F) -> Result<wreq::Response, Error>
+  where
+    F: Future<Output = Result<wreq::Response, Error>>,
+  {
+    let mode = self.cassette.mode;
+    if mode != CassetteMode::Record {
+      let (method, url) = (self.request.method(), self.request.url());
+      match self.cassette.replay(&self.request) {
+        Lookup::Found(response) => return Ok(response),
+        Lookup::Exhausted if mode == CassetteMode::Replay => {
+          return Err(Error::Cassette(format!(
+            "every recording for {method} {url} was already served"
+          )))
+        }
+        Lookup::Missing if mode == CassetteMode::Replay => {
+          return Err(Error::Cassette(format!("no recording for {method} {url}")))
+        }
+        Lookup::Exhausted | Lookup::Missing => {}
+      }
+    }
+
+    let started = Instant::now();
+    let response = send().await?;
+    let (parts, body) = http::Response::from(response).into_parts();
+    let body = BodyExt::collect(body)
+      .await
+      .map_err(Error::Library)?
+      .to_bytes();
+
+    let entry = har::exchange_json(
+      self.request.clone(),
+      &parts,
+      body.clone(),
+      started.elapsed(),
+    );
+    self.cassette.record(entry).await?;
+    Ok(wreq::Response::from(http::Response::from_parts(
+      parts,
+      wreq::Body::from(body),
+    )))
+  }
+}
+
+impl Interaction {
+  /// The exchange in a HAR entry, or `None` for entries without a response.
+  fn from_entry(entry: Value) -> Option<Self> {
+    let request = entry.get("request")?;
+    let response = entry.get("response")?;
+    let status = StatusCode::from_u16(response.get("status")?.as_u64()? as u16).ok()?;
+    let headers = response
+      .get("headers")
+      .and_then(Value::as_array)
+      .into_iter()
+      .flatten()
+      .filter_map(|header| {
+        let name = HeaderName::from_bytes(header.get("name")?.as_str()?.as_bytes()).ok()?;
+        let value = HeaderValue::from_str(header.get("value")?.as_str()?).ok()?;
+        Some((name, value))
+      })
+      .collect();
+
+    Some(Self {
+      method: request.get("method")?.as_str()?.to_string(),
+      url: normalize_url(request.get("url")?.as_str()?),
+      body: request
+        .get("postData")
+        .and_then(har::content_bytes)
+        .unwrap_or_default(),
+      version: parse_version(response.get("httpVersion").and_then(Value::as_str)),
+      status,
+      headers,
+      content: response
+        .get("content")
+        .and_then(har::content_bytes)
+        .unwrap_or_default(),
+      served: false,
+      entry,
+    })
+  }
+
+  fn to_response(&self) -> wreq::Response {
+    let mut response = http::Response::new(wreq::Body::from(self.content.clone()));
+    *response.version_mut() = self.version;
+    *response.status_mut() = self.status;
+    for (name, value) in &self.headers {
+      response.headers_mut().append(name.clone(), value.clone());
+    }
+    wreq::Response::from(response)
+  }
+}
+
+/// Replace the values of the `names` request headers in a HAR entry, and its cookies when
+/// `Cookie` is one of them.
+fn redact(entry: &mut Value, names: &[HeaderName]) {
+  let Some(request) = entry.get_mut("request") else {
+    return;
+  };
+  let redacted = |name: &str| {
+    names
+      .iter()
+      .any(|redacted| redacted.as_str().eq_ignore_ascii_case(name))
+  };
+  if let Some(headers) = request.get_mut("headers").and_then(Value::as_array_mut) {
+    for header in headers {
+      if header
+        .get("name")
+        .and_then(Value::as_str)
+        .is_some_and(redacted)
+      {
+        header["value"] = json!(REDACTED);
+      }
+    }
+  }
+  if names.contains(&header::COOKIE) {
+    if let Some(cookies) = request.get_mut("cookies").and_then(Value::as_array_mut) {
+      for cookie in cookies {
+        cookie["value"] = json!(REDACTED);
+      }
+    }
+  }
+}
+
+fn load(document: &[u8]) -> Result<Vec<Interaction>, Error> {
+  let document: Value =
+    serde_json::from_slice(document).map_err(|err| Error::Cassette(err.to_string()))?;
+  let entries = document
+    .pointer("/log/entries")
+    .and_then(Value::as_array)
+    .ok_or_else(|| Error::Cassette("not a HAR document".to_string()))?;
+  Ok(
+    entries
+      .iter()
+      .cloned()
+      .filter_map(Interaction::from_entry)
+      .collect(),
+  )
+}
+
+/// URLs compare after parsing, so recordings made elsewhere match despite formatting.
+fn normalize_url(url: &str) -> String {
+  url::Url::parse(url).map_or_else(|_| url.to_string(), String::from)
+}
+
+pub(super) fn parse_version(version: Option<&str>) -> Version {
+  match version.map(str::to_ascii_uppercase).as_deref() {
+    Some("HTTP/0.9") => Version::HTTP_09,
+    Some("HTTP/1.0") => Version::HTTP_10,
+    Some("HTTP/2" | "HTTP/2.0" | "H2") => Version::HTTP_2,
+    Some("HTTP/3" | "HTTP/3.0" | "H3") => Version::HTTP_3,
+    _ => Version::HTTP_11,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DOCUMENT: &str = r#"{
+    "log": {
+      "version": "1.2",
+      "entries": [
+        {
+          "request": { "method": "GET", "url": "https://example.com/items?page=1" },
+          "response": {
+            "status": 200,
+            "httpVersion": "HTTP/2.0",
+            "headers": [{ "name": "content-type", "value": "text/plain" }],
+            "content": { "size": 5, "text": "first" }
+          }
+        },
+        {
+          "request": {
+            "method": "POST",
+            "url": "https://example.com/items",
+            "postData": { "mimeType": "text/plain", "text": "AAE=", "encoding": "base64" }
+          },
+          "response": { "status": 201, "content": { "size": 0, "text": "" } }
+        },
+        {
+          "request": { "method": "GET", "url": "https://example.com/broken" },
+          "response": { "status": 0 },
+          "_error": "connection refused"
+        }
+      ]
+    }
+  }"#;
+
+  #[test]
+  fn loads_har_entries() {
+    let interactions = load(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(interactions.len(), 2);
+    assert_eq!(interactions[0].version, Version::HTTP_2);
+    assert_eq!(interactions[0].content, "first");
+    assert_eq!(interactions[1].body, Bytes::from_static(&[0x00, 0x01]));
+    assert_eq!(interactions[1].status, StatusCode::CREATED);
+
+    assert!(matches!(load(b"{}"), Err(Error::Cassette(_))));
+  }
+
+  fn cassette(strict: bool) -> Cassette {
+    Cassette {
+      path: PathBuf::new(),
+      mode: CassetteMode::Replay,
+      match_body: true,
+      strict,
+      redact: DEFAULT_REDACTED_HEADERS.to_vec(),
+      interactions: Mutex::new(load(DOCUMENT.as_bytes()).unwrap()),
+      writer: tokio::sync::Mutex::new(()),
+    }
+  }
+
+  fn get(url: &str) -> HarRequest {
+    HarRequest {
+      method: "GET".to_string(),
+      url: url.to_string(),
+      headers: Vec::new(),
+      body: None,
+      body_streamed: false,
+    }
+  }
+
+  #[test]
+  fn repeats_the_last_recording_unless_strict() {
+    let request = get("https://example.com/items?page=1");
+
+    let lenient = cassette(false);
+    assert!(matches!(lenient.replay(&request), Lookup::Found(_)));
+    assert!(matches!(lenient.replay(&request), Lookup::Found(_)));
+
+    let strict = cassette(true);
+    assert!(matches!(strict.replay(&request), Lookup::Found(_)));
+    assert!(matches!(strict.replay(&request), Lookup::Exhausted));
+    assert!(matches!(
+      strict.replay(&get("https://example.com/other")),
+      Lookup::Missing
+    ));
+  }
+
+  #[test]
+  fn redacts_credentials() {
+    let mut entry = json!({
+      "request": {
+        "headers": [
+          { "name": "Authorization", "value": "Bearer secret" },
+          { "name": "cookie", "value": "session=abc" },
+          { "name": "accept", "value": "*/*" },
+        ],
+        "cookies": [{ "name": "session", "value": "abc" }],
+      }
+    });
+    redact(&mut entry, &DEFAULT_REDACTED_HEADERS);
+
+    let request = &entry["request"];
+    assert_eq!(request["headers"][0]["value"], REDACTED);
+    assert_eq!(request["headers"][1]["value"], REDACTED);
+    assert_eq!(request["headers"][2]["value"], "*/*");
+    assert_eq!(request["cookies"][0]["value"], REDACTED);
+  }
+
+  #[test]
+  fn normalizes_urls() {
+    assert_eq!(normalize_url("HTTPS://Example.com"), "https://example.com/");
+    assert_eq!(normalize_url("not a url"), "not a url");
+  }
+}
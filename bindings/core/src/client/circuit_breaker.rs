@@ -0,0 +1,359 @@
+//! Failing fast for hosts that keep failing.
+
+use std::{
+  collections::{HashMap, VecDeque},
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use http::StatusCode;
+
+use crate::Error;
+
+/// When a host's circuit opens and how it recovers.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerPolicy {
+  /// Share of failed requests within `window` that opens the circuit, from `0.0` to `1.0`.
+  pub failure_threshold: f64,
+  /// Requests within `window` needed before the failure rate is considered.
+  pub minimum_requests: usize,
+  /// How far back outcomes are counted.
+  pub window: Duration,
+  /// How long an open circuit rejects requests before letting trial requests through.
+  pub cooldown: Duration,
+  /// Trial requests allowed at once while half-open.
+  pub half_open_requests: u32,
+  /// Response statuses counted as failures, in addition to transport errors.
+  pub statuses: Vec<StatusCode>,
+}
+
+impl Default for CircuitBreakerPolicy {
+  fn default() -> Self {
+    Self {
+      failure_threshold: 0.5,
+      minimum_requests: 5,
+      window: Duration::from_secs(60),
+      cooldown: Duration::from_secs(30),
+      half_open_requests: 1,
+      statuses: vec![
+        StatusCode::INTERNAL_SERVER_ERROR,
+        StatusCode::BAD_GATEWAY,
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::GATEWAY_TIMEOUT,
+      ],
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+  /// Requests flow normally.
+  Closed,
+  /// Requests fail immediately with [`Error::CircuitOpen`].
+  Open,
+  /// Trial requests decide whether the circuit closes or opens again.
+  HalfOpen,
+}
+
+/// A snapshot of one host's circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitStatus {
+  pub host: String,
+  pub state: CircuitState,
+  /// Requests counted within the window.
+  pub requests: usize,
+  /// Failures counted within the window.
+  pub failures: usize,
+  /// Time left until an open circuit lets trial requests through.
+  pub retry_in: Option<Duration>,
+}
+
+#[derive(Debug)]
+enum Phase {
+  Closed,
+  Open { until: Instant },
+  HalfOpen { trials: u32 },
+}
+
+#[derive(Debug)]
+struct HostCircuit {
+  phase: Phase,
+  /// Completion time and whether the request failed, oldest first.
+  outcomes: VecDeque<(Instant, bool)>,
+}
+
+impl HostCircuit {
+  fn prune(&mut self, window: Duration, now: Instant) {
+    while let Some((at, _)) = self.outcomes.front() {
+      if now.saturating_duration_since(*at) <= window {
+        break;
+      }
+      self.outcomes.pop_front();
+    }
+  }
+
+  fn failures(&self) -> usize {
+    self.outcomes.iter().filter(|(_, failed)| *failed).count()
+  }
+}
+
+/// Tracks failures per host and rejects requests to hosts whose circuit is open.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+  policy: CircuitBreakerPolicy,
+  hosts: Mutex<HashMap<String, HostCircuit>>,
+}
+
+impl CircuitBreaker {
+  pub fn new(policy: CircuitBreakerPolicy) -> Self {
+    Self {
+      policy,
+      hosts: Mutex::default(),
+    }
+  }
+
+  pub fn policy(&self) -> &CircuitBreakerPolicy {
+    &self.policy
+  }
+
+  /// Let a request to `host` through, or fail with [`Error::CircuitOpen`].
+  ///
+  /// The outcome is reported through the returned ticket.
+  pub fn admit(self: &Arc<Self>, host: &str) -> Result<CircuitTicket, Error> {
+    self.admit_at(host, Instant::now())
+  }
+
+  fn admit_at(self: &Arc<Self>, host: &str, now: Instant) -> Result<CircuitTicket, Error> {
+    let host = host.to_ascii_lowercase();
+    let mut hosts = self.hosts.lock().unwrap();
+    let circuit = hosts.entry(host.clone()).or_insert(HostCircuit {
+      phase: Phase::Closed,
+      outcomes: VecDeque::new(),
+    });
+
+    let trial = match circuit.phase {
+      Phase::Closed => false,
+      Phase::Open { until } if now < until => return Err(Error::CircuitOpen(host)),
+      Phase::Open { .. } => {
+        circuit.phase = Phase::HalfOpen { trials: 1 };
+        true
+      }
+      Phase::HalfOpen { ref mut trials } if *trials < self.policy.half_open_requests.max(1) => {
+        *trials += 1;
+        true
+      }
+      Phase::HalfOpen { .. } => return Err(Error::CircuitOpen(host)),
+    };
+
+    Ok(CircuitTicket {
+      breaker: self.clone(),
+      host: Some(host),
+      trial,
+    })
+  }
+
+  fn record(&self, host: &str, trial: bool, failed: bool, now: Instant) {
+    let mut hosts = self.hosts.lock().unwrap();
+    let Some(circuit) = hosts.get_mut(host) else {
+      return;
+    };
+
+    match circuit.phase {
+      Phase::Closed => {
+        circuit.outcomes.push_back((now, failed));
+        circuit.prune(self.policy.window, now);
+        let requests = circuit.outcomes.len();
+        if requests >= self.policy.minimum_requests.max(1)
+          && circuit.failures() as f64 >= self.policy.failure_threshold * requests as f64
+        {
+          circuit.phase = Phase::Open {
+            until: now + self.policy.cooldown,
+          };
+          circuit.outcomes.clear();
+        }
+      }
+      Phase::HalfOpen { .. } if trial => {
+        circuit.phase = if failed {
+          Phase::Open {
+            until: now + self.policy.cooldown,
+          }
+        } else {
+          Phase::Closed
+        };
+        circuit.outcomes.clear();
+      }
+      // Late results from requests admitted before the circuit opened.
+      Phase::HalfOpen { .. } | Phase::Open { .. } => {}
+    }
+  }
+
+  /// Give back a trial slot whose request finished without an outcome.
+  fn abandon(&self, host: &str) {
+    let mut hosts = self.hosts.lock().unwrap();
+    if let Some(HostCircuit {
+      phase: Phase::HalfOpen { trials },
+      ..
+    }) = hosts.get_mut(host)
+    {
+      *trials = trials.saturating_sub(1);
+    }
+  }
+
+  /// The state of every host seen so far.
+  pub fn statuses(&self) -> Vec<CircuitStatus> {
+    let now = Instant::now();
+    let mut hosts = self.hosts.lock().unwrap();
+    hosts
+      .iter_mut()
+      .map(|(host, circuit)| {
+        circuit.prune(self.policy.window, now);
+        let (state, retry_in) = match circuit.phase {
+          Phase::Closed => (CircuitState::Closed, None),
+          Phase::Open { until } if now < until => (CircuitState::Open, Some(until - now)),
+          Phase::Open { .. } => (CircuitState::Open, Some(Duration::ZERO)),
+          Phase::HalfOpen { .. } => (CircuitState::HalfOpen, None),
+        };
+        CircuitStatus {
+          host: host.clone(),
+          state,
+          requests: circuit.outcomes.len(),
+          failures: circuit.failures(),
+          retry_in,
+        }
+      })
+      .collect()
+  }
+}
+
+/// An admitted request whose outcome still has to be recorded.
+///
+/// Dropping the ticket without recording leaves the failure rate untouched.
+#[derive(Debug)]
+pub struct CircuitTicket {
+  breaker: Arc<CircuitBreaker>,
+  host: Option<String>,
+  trial: bool,
+}
+
+impl CircuitTicket {
+  /// Record a response, which counts as a failure when its status is listed in the policy.
+  pub fn record_status(self, status: StatusCode) {
+    let failed = self.breaker.policy.statuses.contains(&status);
+    self.finish(failed, Instant::now());
+  }
+
+  /// Record a request that failed without a response.
+  pub fn record_failure(self) {
+    self.finish(true, Instant::now());
+  }
+
+  fn finish(mut self, failed: bool, now: Instant) {
+    if let Some(host) = self.host.take() {
+      self.breaker.record(&host, self.trial, failed, now);
+    }
+  }
+}
+
+impl Drop for CircuitTicket {
+  fn drop(&mut self) {
+    if let Some(host) = self.host.take() {
+      if self.trial {
+        self.breaker.abandon(&host);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn breaker() -> Arc<CircuitBreaker> {
+    Arc::new(CircuitBreaker::new(CircuitBreakerPolicy {
+      minimum_requests: 4,
+      cooldown: Duration::from_secs(10),
+      ..CircuitBreakerPolicy::default()
+    }))
+  }
+
+  fn state(breaker: &CircuitBreaker, host: &str) -> CircuitState {
+    breaker
+      .statuses()
+      .into_iter()
+      .find(|status| status.host == host)
+      .map(|status| status.state)
+      .unwrap()
+  }
+
+  #[test]
+  fn opens_once_the_failure_rate_is_reached() {
+    let breaker = breaker();
+    let now = Instant::now();
+
+    for failed in [false, true, false] {
+      breaker.admit_at("a.test", now).unwrap().finish(failed, now);
+    }
+    assert_eq!(state(&breaker, "a.test"), CircuitState::Closed);
+
+    breaker.admit_at("a.test", now).unwrap().finish(true, now);
+    assert_eq!(state(&breaker, "a.test"), CircuitState::Open);
+    assert!(matches!(
+      breaker.admit_at("a.test", now),
+      Err(Error::CircuitOpen(host)) if host == "a.test"
+    ));
+    assert!(breaker.admit_at("b.test", now).is_ok());
+  }
+
+  #[test]
+  fn half_opens_after_the_cooldown() {
+    let breaker = breaker();
+    let now = Instant::now();
+    for _ in 0..4 {
+      breaker.admit_at("a.test", now).unwrap().finish(true, now);
+    }
+
+    let later = now + Duration::from_secs(11);
+    let trial = breaker.admit_at("a.test", later).unwrap();
+    assert_eq!(state(&breaker, "a.test"), CircuitState::HalfOpen);
+    assert!(breaker.admit_at("a.test", later).is_err());
+
+    trial.finish(true, later);
+    assert_eq!(state(&breaker, "a.test"), CircuitState::Open);
+
+    let recovered = later + Duration::from_secs(11);
+    breaker
+      .admit_at("a.test", recovered)
+      .unwrap()
+      .finish(false, recovered);
+    assert_eq!(state(&breaker, "a.test"), CircuitState::Closed);
+  }
+
+  #[test]
+  fn abandoned_trials_free_their_slot() {
+    let breaker = breaker();
+    let now = Instant::now();
+    for _ in 0..4 {
+      breaker.admit_at("a.test", now).unwrap().finish(true, now);
+    }
+
+    let later = now + Duration::from_secs(11);
+    drop(breaker.admit_at("a.test", later).unwrap());
+    assert!(breaker.admit_at("a.test", later).is_ok());
+  }
+
+  #[test]
+  fn forgets_outcomes_outside_the_window() {
+    let breaker = breaker();
+    let now = Instant::now();
+    for _ in 0..3 {
+      breaker.admit_at("a.test", now).unwrap().finish(true, now);
+    }
+
+    let later = now + Duration::from_secs(61);
+    breaker
+      .admit_at("a.test", later)
+      .unwrap()
+      .finish(true, later);
+    assert_eq!(state(&breaker, "a.test"), CircuitState::Closed);
+  }
+}
@@ -0,0 +1,450 @@
+//! Capping in-flight requests, globally and per host, behind a priority queue.
+
+use std::{
+  cmp::Ordering,
+  collections::{BinaryHeap, HashMap},
+  future::Future,
+  pin::Pin,
+  sync::{Arc, Mutex},
+  task::{Context, Poll, Waker},
+  time::Duration,
+};
+
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+
+/// Counts of requests for one host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HostConcurrency {
+  pub in_flight: usize,
+  pub queued: usize,
+}
+
+/// A snapshot of a [`ConcurrencyLimiter`].
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyStats {
+  pub in_flight: usize,
+  pub queued: usize,
+  /// Hosts with requests in flight or queued.
+  pub hosts: HashMap<String, HostConcurrency>,
+}
+
+/// Limits how many requests are in flight at once.
+///
+/// Requests over the limit wait in a queue ordered by priority, then arrival. A queued request
+/// for a host at its own limit does not hold back requests for other hosts. Clones share the
+/// same counters.
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimiter {
+  shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+  max_in_flight: Option<usize>,
+  max_per_host: Option<usize>,
+  overrides: HashMap<String, usize>,
+  queue_timeout: Option<Duration>,
+  state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+  in_flight: usize,
+  hosts: HashMap<String, usize>,
+  /// Places of requests still waiting, best first. Places of requests that gave up are skipped
+  /// when they come up.
+  queue: BinaryHeap<Place>,
+  /// Queued requests by id, including those granted a slot but not polled since.
+  waiters: HashMap<u64, Waiter>,
+  /// Waiters not granted a slot yet.
+  queued: usize,
+  next_id: u64,
+}
+
+#[derive(Debug)]
+struct Waiter {
+  key: String,
+  granted: bool,
+  waker: Option<Waker>,
+}
+
+/// A waiter's position: higher priorities first, then earlier arrivals.
+#[derive(Debug, PartialEq, Eq)]
+struct Place {
+  priority: i32,
+  id: u64,
+}
+
+impl Ord for Place {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self
+      .priority
+      .cmp(&other.priority)
+      .then_with(|| other.id.cmp(&self.id))
+  }
+}
+
+impl PartialOrd for Place {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl ConcurrencyLimiter {
+  /// `max_in_flight` caps all requests, `max_per_host` the requests to any single host.
+  pub fn new(max_in_flight: Option<usize>, max_per_host: Option<usize>) -> Self {
+    Self {
+      shared: Arc::new(Shared {
+        max_in_flight,
+        max_per_host,
+        ..Shared::default()
+      }),
+    }
+  }
+
+  /// Use `limit` for `host` instead of the per-host default.
+  ///
+  /// # Panics
+  ///
+  /// Panics once the limiter has been cloned.
+  pub fn with_host_limit(mut self, host: impl Into<String>, limit: usize) -> Self {
+    Arc::get_mut(&mut self.shared)
+      .expect("limiter is not shared yet")
+      .overrides
+      .insert(host.into().to_ascii_lowercase(), limit);
+    self
+  }
+
+  /// Fail requests that waited longer than `timeout` for a slot.
+  ///
+  /// # Panics
+  ///
+  /// Panics once the limiter has been cloned.
+  pub fn with_queue_timeout(mut self, timeout: Duration) -> Self {
+    Arc::get_mut(&mut self.shared)
+      .expect("limiter is not shared yet")
+      .queue_timeout = Some(timeout);
+    self
+  }
+
+  /// Default time a request may wait in the queue.
+  pub fn queue_timeout(&self) -> Option<Duration> {
+    self.shared.queue_timeout
+  }
+
+  /// Wait for a slot for `host`; higher `priority` values are served first.
+  ///
+  /// The slot is held until the returned permit is dropped.
+  pub async fn acquire(&self, host: &str, priority: i32) -> ConcurrencyPermit {
+    let key = host.to_ascii_lowercase();
+    let id = {
+      let mut state = self.shared.state.lock().unwrap();
+      if state.queued == 0 && self.shared.has_capacity(&state, &key) {
+        state.start(&key);
+        return ConcurrencyPermit {
+          shared: self.shared.clone(),
+          key,
+        };
+      }
+
+      let id = state.next_id;
+      state.next_id += 1;
+      state.queue.push(Place { priority, id });
+      state.waiters.insert(
+        id,
+        Waiter {
+          key: key.clone(),
+          granted: false,
+          waker: None,
+        },
+      );
+      state.queued += 1;
+      self.shared.dispatch(&mut state);
+      id
+    };
+
+    Queued {
+      shared: self.shared.clone(),
+      id,
+      key: Some(key),
+    }
+    .await
+  }
+
+  pub fn stats(&self) -> ConcurrencyStats {
+    let state = self.shared.state.lock().unwrap();
+    let mut stats = ConcurrencyStats {
+      in_flight: state.in_flight,
+      queued: state.queued,
+      ..ConcurrencyStats::default()
+    };
+
+    for (host, in_flight) in &state.hosts {
+      stats.hosts.entry(host.clone()).or_default().in_flight = *in_flight;
+    }
+    for waiter in state.waiters.values().filter(|waiter| !waiter.granted) {
+      stats.hosts.entry(waiter.key.clone()).or_default().queued += 1;
+    }
+    stats
+  }
+}
+
+impl Shared {
+  fn has_capacity(&self, state: &State, key: &str) -> bool {
+    let host_limit = self.overrides.get(key).copied().or(self.max_per_host);
+    let host_in_flight = state.hosts.get(key).copied().unwrap_or(0);
+    self.max_in_flight.is_none_or(|max| state.in_flight < max)
+      && host_limit.is_none_or(|max| host_in_flight < max)
+  }
+
+  /// Grant slots to queued requests while there is room, best priority first.
+  ///
+  /// Requests for hosts at their own limit are passed over and keep their place.
+  fn dispatch(&self, state: &mut State) {
+    let mut passed_over = Vec::new();
+    while self.max_in_flight.is_none_or(|max| state.in_flight < max) {
+      let Some(place) = state.queue.pop() else {
+        break;
+      };
+      let Some(waiter) = state.waiters.get(&place.id) else {
+        continue;
+      };
+      if !self.has_capacity(state, &waiter.key) {
+        passed_over.push(place);
+        continue;
+      }
+
+      let key = waiter.key.clone();
+      state.start(&key);
+      state.queued -= 1;
+      let waiter = state
+        .waiters
+        .get_mut(&place.id)
+        .expect("waiter was just found");
+      waiter.granted = true;
+      if let Some(waker) = waiter.waker.take() {
+        waker.wake();
+      }
+    }
+    state.queue.extend(passed_over);
+  }
+
+  fn release(&self, key: &str) {
+    let mut state = self.state.lock().unwrap();
+    state.finish(key);
+    self.dispatch(&mut state);
+  }
+}
+
+impl State {
+  fn start(&mut self, key: &str) {
+    self.in_flight += 1;
+    *self.hosts.entry(key.to_string()).or_default() += 1;
+  }
+
+  fn finish(&mut self, key: &str) {
+    self.in_flight -= 1;
+    if let Some(count) = self.hosts.get_mut(key) {
+      *count -= 1;
+      if *count == 0 {
+        self.hosts.remove(key);
+      }
+    }
+  }
+}
+
+/// A queued [`ConcurrencyLimiter::acquire`]; leaves the queue when dropped.
+struct Queued {
+  shared: Arc<Shared>,
+  id: u64,
+  key: Option<String>,
+}
+
+impl Future for Queued {
+  type Output = ConcurrencyPermit;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let mut state = self.shared.state.lock().unwrap();
+    let id = self.id;
+    let waiter = state
+      .waiters
+      .get_mut(&id)
+      .expect("queued request is still tracked");
+
+    if waiter.granted {
+      state.waiters.remove(&id);
+      drop(state);
+      let key = self.key.take().unwrap_or_default();
+      return Poll::Ready(ConcurrencyPermit {
+        shared: self.shared.clone(),
+        key,
+      });
+    }
+
+    waiter.waker = Some(cx.waker().clone());
+    Poll::Pending
+  }
+}
+
+impl Drop for Queued {
+  fn drop(&mut self) {
+    let Some(key) = self.key.take() else {
+      return;
+    };
+
+    let mut state = self.shared.state.lock().unwrap();
+    match state.waiters.remove(&self.id) {
+      // A slot granted after the caller gave up goes to the next request.
+      Some(waiter) if waiter.granted => {
+        state.finish(&key);
+        self.shared.dispatch(&mut state);
+      }
+      Some(_) => state.queued -= 1,
+      None => {}
+    }
+    if state.queued == 0 {
+      state.queue.clear();
+    }
+  }
+}
+
+/// A slot held by an in-flight request.
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+  shared: Arc<Shared>,
+  key: String,
+}
+
+impl Drop for ConcurrencyPermit {
+  fn drop(&mut self) {
+    self.shared.release(&self.key);
+  }
+}
+
+/// A body that holds a permit until it is dropped.
+pub(crate) struct PermitBody<B> {
+  inner: B,
+  _permit: ConcurrencyPermit,
+}
+
+impl<B> PermitBody<B> {
+  pub(crate) fn new(inner: B, permit: ConcurrencyPermit) -> Self {
+    Self {
+      inner,
+      _permit: permit,
+    }
+  }
+}
+
+impl<B> Body for PermitBody<B>
+where
+  B: Body<Data = Bytes> + Unpin,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    Pin::new(&mut self.inner).poll_frame(cx)
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures::FutureExt;
+
+  #[test]
+  fn queues_requests_over_the_limit() {
+    let limiter = ConcurrencyLimiter::new(Some(1), None);
+    let first = limiter.acquire("a.test", 0).now_or_never().unwrap();
+
+    let mut second = Box::pin(limiter.acquire("a.test", 0));
+    assert!((&mut second).now_or_never().is_none());
+    assert_eq!(limiter.stats().in_flight, 1);
+    assert_eq!(limiter.stats().queued, 1);
+
+    drop(first);
+    let _second = second.now_or_never().unwrap();
+    assert_eq!(limiter.stats().queued, 0);
+  }
+
+  #[test]
+  fn serves_higher_priorities_first() {
+    let limiter = ConcurrencyLimiter::new(Some(1), None);
+    let first = limiter.acquire("a.test", 0).now_or_never().unwrap();
+
+    let mut low = Box::pin(limiter.acquire("a.test", 0));
+    let mut high = Box::pin(limiter.acquire("a.test", 5));
+    assert!((&mut low).now_or_never().is_none());
+    assert!((&mut high).now_or_never().is_none());
+
+    drop(first);
+    assert!((&mut low).now_or_never().is_none());
+    let _high = high.now_or_never().unwrap();
+  }
+
+  #[test]
+  fn limits_hosts_independently() {
+    let limiter = ConcurrencyLimiter::new(None, Some(1)).with_host_limit("wide.test", 2);
+    let _a = limiter.acquire("a.test", 0).now_or_never().unwrap();
+
+    let mut blocked = Box::pin(limiter.acquire("a.test", 0));
+    assert!((&mut blocked).now_or_never().is_none());
+
+    let _b = limiter.acquire("b.test", 0).now_or_never().unwrap();
+    let _wide = limiter.acquire("wide.test", 0).now_or_never().unwrap();
+    let _wider = limiter.acquire("wide.test", 0).now_or_never().unwrap();
+
+    let stats = limiter.stats();
+    assert_eq!(stats.in_flight, 4);
+    assert_eq!(
+      stats.hosts["a.test"],
+      HostConcurrency {
+        in_flight: 1,
+        queued: 1
+      }
+    );
+  }
+
+  #[test]
+  fn passes_over_hosts_at_their_limit() {
+    let limiter = ConcurrencyLimiter::new(Some(2), Some(1));
+    let first = limiter.acquire("a.test", 0).now_or_never().unwrap();
+
+    let mut urgent = Box::pin(limiter.acquire("a.test", 5));
+    assert!((&mut urgent).now_or_never().is_none());
+    // Queued behind a.test, but a.test can't take the free slot.
+    let _other = limiter.acquire("b.test", 0).now_or_never().unwrap();
+
+    drop(first);
+    let _urgent = urgent.now_or_never().unwrap();
+    assert_eq!(limiter.stats().queued, 0);
+  }
+
+  #[test]
+  fn dropping_a_queued_request_frees_its_place() {
+    let limiter = ConcurrencyLimiter::new(Some(1), None);
+    let first = limiter.acquire("a.test", 0).now_or_never().unwrap();
+
+    let mut abandoned = Box::pin(limiter.acquire("a.test", 0));
+    assert!((&mut abandoned).now_or_never().is_none());
+    drop(first);
+    // The slot was granted to the abandoned request, which hands it on when dropped.
+    drop(abandoned);
+
+    assert_eq!(limiter.stats().in_flight, 0);
+    assert!(limiter.acquire("a.test", 0).now_or_never().is_some());
+  }
+}
@@ -0,0 +1,720 @@
+//! Recording exchanges in HTTP Archive (HAR 1.2) format.
+
+use std::{
+  fs, io,
+  path::PathBuf,
+  sync::{Arc, Mutex},
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use http::{header, HeaderName, HeaderValue, StatusCode, Version};
+use serde_json::{json, Map, Value};
+
+use crate::Error;
+
+/// Records every exchange made through a client, in memory and optionally to a file.
+///
+/// Bodies are truncated as they are recorded, so a size limit also bounds the memory held.
+#[derive(Debug, Default)]
+pub struct HarRecorder {
+  max_body_size: Option<usize>,
+  path: Option<PathBuf>,
+  state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+  next_id: u64,
+  entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+  id: u64,
+  started: SystemTime,
+  /// The request, without its body.
+  request: HarRequest,
+  request_body: Option<Content>,
+  outcome: Outcome,
+  blocked: Duration,
+  wait: Duration,
+  receive: Option<Duration>,
+}
+
+#[derive(Debug)]
+enum Outcome {
+  Response {
+    version: Version,
+    status: StatusCode,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    server_ip: Option<String>,
+    content: Option<Content>,
+  },
+  Error(String),
+}
+
+/// A recorded body: as many bytes as the recorder keeps, and the size of the whole body.
+#[derive(Debug)]
+struct Content {
+  kept: Bytes,
+  size: usize,
+}
+
+/// The request side of an entry, captured before sending.
+#[derive(Debug, Clone)]
+pub(crate) struct HarRequest {
+  pub(super) method: String,
+  pub(super) url: String,
+  pub(super) headers: Vec<(HeaderName, HeaderValue)>,
+  pub(super) body: Option<Bytes>,
+  pub(super) body_streamed: bool,
+}
+
+/// A request about to be sent, waiting to be recorded with its outcome.
+pub(crate) struct HarCapture {
+  recorder: Arc<HarRecorder>,
+  request: HarRequest,
+  started: SystemTime,
+}
+
+/// Where a response stores its body once read.
+#[derive(Debug)]
+pub(crate) struct HarBody {
+  recorder: Arc<HarRecorder>,
+  id: u64,
+}
+
+impl HarRecorder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Keep at most `max` bytes of each request and response body.
+  pub fn with_max_body_size(mut self, max: usize) -> Self {
+    self.max_body_size = Some(max);
+    self
+  }
+
+  /// Write the archive to `path` when the recorder is dropped, or earlier with
+  /// [`flush`](Self::flush).
+  pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+    self.path = Some(path.into());
+    self
+  }
+
+  /// The archive as a HAR 1.2 document.
+  pub fn to_json(&self) -> Value {
+    let state = self.state.lock().unwrap();
+    let entries = state.entries.iter().map(Entry::to_json).collect();
+    document(entries)
+  }
+
+  /// The path given to [`with_path`](Self::with_path), if any.
+  pub fn path(&self) -> Option<&std::path::Path> {
+    self.path.as_deref()
+  }
+
+  /// Write the archive to `path`.
+  pub fn save(&self, path: impl AsRef<std::path::Path>) -> io::Result<()> {
+    let document = serde_json::to_vec_pretty(&self.to_json())?;
+    fs::write(path, document)
+  }
+
+  /// Write the archive to the path given to [`with_path`](Self::with_path), if any.
+  pub fn flush(&self) -> io::Result<()> {
+    match &self.path {
+      Some(path) => self.save(path),
+      None => Ok(()),
+    }
+  }
+
+  /// Forget every recorded exchange.
+  pub fn clear(&self) {
+    self.state.lock().unwrap().entries.clear();
+  }
+
+  pub fn len(&self) -> usize {
+    self.state.lock().unwrap().entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  fn push(&self, mut entry: Entry) -> u64 {
+    let mut state = self.state.lock().unwrap();
+    let id = state.next_id;
+    state.next_id += 1;
+    entry.id = id;
+    state.entries.push(entry);
+    id
+  }
+}
+
+impl Drop for HarRecorder {
+  /// A recorder is a debugging aid, so a failed final write is not reported.
+  fn drop(&mut self) {
+    let _ = self.flush();
+  }
+}
+
+impl HarRequest {
+  pub(crate) fn new(request: &wreq::Request, headers: Vec<(HeaderName, HeaderValue)>) -> Self {
+    let (body, body_streamed) = match request.body() {
+      None => (None, false),
+      Some(body) => match body.as_bytes() {
+        Some(bytes) => (Some(Bytes::copy_from_slice(bytes)), false),
+        None => (None, true),
+      },
+    };
+    Self {
+      method: request.method().to_string(),
+      url: request.uri().to_string(),
+      headers,
+      body,
+      body_streamed,
+    }
+  }
+
+  pub(super) fn method(&self) -> &str {
+    &self.method
+  }
+
+  pub(super) fn url(&self) -> &str {
+    &self.url
+  }
+
+  pub(super) fn headers(&self) -> &[(HeaderName, HeaderValue)] {
+    &self.headers
+  }
+
+  /// The request body, or `None` for an empty or streamed one.
+  pub(super) fn body(&self) -> Option<&Bytes> {
+    self.body.as_ref()
+  }
+
+  pub(super) fn is_streamed(&self) -> bool {
+    self.body_streamed
+  }
+}
+
+impl HarCapture {
+  pub(crate) fn new(recorder: Arc<HarRecorder>, request: HarRequest) -> Self {
+    Self {
+      recorder,
+      request,
+      started: SystemTime::now(),
+    }
+  }
+
+  /// Record the head of `response`; the body follows through the returned [`HarBody`].
+  pub(crate) fn record(
+    self,
+    response: &wreq::Response,
+    blocked: Duration,
+    wait: Duration,
+  ) -> HarBody {
+    let outcome = Outcome::Response {
+      version: response.version(),
+      status: response.status(),
+      headers: response
+        .headers()
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect(),
+      server_ip: response.remote_addr().map(|addr| addr.ip().to_string()),
+      content: None,
+    };
+    let (recorder, id) = self.finish(outcome, blocked, wait);
+    HarBody { recorder, id }
+  }
+
+  /// Record a request that failed without a response.
+  pub(crate) fn record_error(self, error: &Error, blocked: Duration, wait: Duration) {
+    self.finish(Outcome::Error(error.to_string()), blocked, wait);
+  }
+
+  fn finish(
+    mut self,
+    outcome: Outcome,
+    blocked: Duration,
+    wait: Duration,
+  ) -> (Arc<HarRecorder>, u64) {
+    let max_body_size = self.recorder.max_body_size;
+    let request_body = self
+      .request
+      .body
+      .take()
+      .map(|body| Content::new(&body, max_body_size));
+    let id = self.recorder.push(Entry {
+      id: 0,
+      started: self.started,
+      request: self.request,
+      request_body,
+      outcome,
+      blocked,
+      wait,
+      receive: None,
+    });
+    (self.recorder, id)
+  }
+}
+
+impl HarBody {
+  /// Store the response body read in `receive`.
+  pub(crate) fn record(&self, body: &Bytes, receive: Duration) {
+    let recorded = Content::new(body, self.recorder.max_body_size);
+    let mut state = self.recorder.state.lock().unwrap();
+    let Some(entry) = state
+      .entries
+      .iter_mut()
+      .rev()
+      .find(|entry| entry.id == self.id)
+    else {
+      return;
+    };
+    if let Outcome::Response { content, .. } = &mut entry.outcome {
+      *content = Some(recorded);
+    }
+    entry.receive = Some(receive);
+  }
+}
+
+impl Content {
+  /// Keep at most `max` bytes of `body`, copied so the rest can be freed.
+  fn new(body: &Bytes, max: Option<usize>) -> Self {
+    let kept = match max {
+      Some(max) if body.len() > max => Bytes::copy_from_slice(&body[..max]),
+      _ => body.clone(),
+    };
+    Self {
+      kept,
+      size: body.len(),
+    }
+  }
+
+  /// A body kept whole.
+  fn whole(body: Bytes) -> Self {
+    Self {
+      size: body.len(),
+      kept: body,
+    }
+  }
+}
+
+impl Entry {
+  fn to_json(&self) -> Value {
+    let blocked = millis(self.blocked);
+    let wait = millis(self.wait);
+    let receive = self.receive.map(millis).unwrap_or(0.0);
+
+    let version = match &self.outcome {
+      Outcome::Response { version, .. } => format!("{version:?}"),
+      Outcome::Error(_) => String::new(),
+    };
+
+    let mut request = json!({
+      "method": self.request.method,
+      "url": self.request.url,
+      "httpVersion": version,
+      "cookies": request_cookies(&self.request.headers),
+      "headers": headers_json(&self.request.headers),
+      "queryString": query_string(&self.request.url),
+      "headersSize": -1,
+      "bodySize": match (&self.request_body, self.request.body_streamed) {
+        (Some(body), _) => body.size as i64,
+        (None, true) => -1,
+        (None, false) => 0,
+      },
+    });
+    if let Some(body) = &self.request_body {
+      let mut post_data = content_json(body);
+      post_data.insert(
+        "mimeType".to_string(),
+        json!(header_value(&self.request.headers, &header::CONTENT_TYPE).unwrap_or_default()),
+      );
+      request["postData"] = Value::Object(post_data);
+    }
+
+    let mut entry = json!({
+      "startedDateTime": iso8601(self.started),
+      "time": blocked + wait + receive,
+      "request": request,
+      "cache": {},
+      "timings": {
+        "blocked": blocked,
+        "dns": -1,
+        "connect": -1,
+        "send": 0,
+        "wait": wait,
+        "receive": receive,
+        "ssl": -1,
+      },
+    });
+
+    entry["response"] = match &self.outcome {
+      Outcome::Response {
+        version,
+        status,
+        headers,
+        server_ip,
+        content: body,
+      } => {
+        if let Some(server_ip) = server_ip {
+          entry["serverIPAddress"] = json!(server_ip);
+        }
+        let mut content = match body {
+          Some(body) => content_json(body),
+          None => {
+            let mut content = Map::new();
+            content.insert("size".to_string(), json!(-1));
+            content.insert("comment".to_string(), json!("body not read"));
+            content
+          }
+        };
+        content.insert(
+          "mimeType".to_string(),
+          json!(header_value(headers, &header::CONTENT_TYPE).unwrap_or_default()),
+        );
+        json!({
+          "status": status.as_u16(),
+          "statusText": status.canonical_reason().unwrap_or_default(),
+          "httpVersion": format!("{version:?}"),
+          "cookies": response_cookies(headers),
+          "headers": headers_json(headers),
+          "content": content,
+          "redirectURL": header_value(headers, &header::LOCATION).unwrap_or_default(),
+          "headersSize": -1,
+          "bodySize": body.as_ref().map_or(-1, |body| body.size as i64),
+        })
+      }
+      Outcome::Error(error) => {
+        entry["_error"] = json!(error);
+        json!({
+          "status": 0,
+          "statusText": "",
+          "httpVersion": "",
+          "cookies": [],
+          "headers": [],
+          "content": { "size": 0, "mimeType": "" },
+          "redirectURL": "",
+          "headersSize": -1,
+          "bodySize": -1,
+        })
+      }
+    };
+
+    entry
+  }
+}
+
+/// A HAR 1.2 document holding `entries`.
+pub(super) fn document(entries: Vec<Value>) -> Value {
+  json!({
+    "log": {
+      "version": "1.2",
+      "creator": {
+        "name": "persona-http",
+        "version": env!("CARGO_PKG_VERSION"),
+      },
+      "entries": entries,
+    }
+  })
+}
+
+/// A complete exchange as a HAR entry, bodies kept whole.
+pub(super) fn exchange_json(
+  request: HarRequest,
+  response: &http::response::Parts,
+  body: Bytes,
+  wait: Duration,
+) -> Value {
+  let mut request = request;
+  let request_body = request.body.take().map(Content::whole);
+  let entry = Entry {
+    id: 0,
+    started: SystemTime::now() - wait,
+    request,
+    request_body,
+    outcome: Outcome::Response {
+      version: response.version,
+      status: response.status,
+      headers: response
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect(),
+      server_ip: None,
+      content: Some(Content::whole(body)),
+    },
+    blocked: Duration::ZERO,
+    wait,
+    receive: Some(Duration::ZERO),
+  };
+  entry.to_json()
+}
+
+/// The body stored in a HAR `content` or `postData` object.
+pub(super) fn content_bytes(content: &Value) -> Option<Bytes> {
+  let text = content.get("text")?.as_str()?;
+  match content.get("encoding").and_then(Value::as_str) {
+    Some("base64") => decode_base64(text).map(Bytes::from),
+    _ => Some(Bytes::copy_from_slice(text.as_bytes())),
+  }
+}
+
+fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+fn text(value: &HeaderValue) -> String {
+  String::from_utf8_lossy(value.as_bytes()).into_owned()
+}
+
+fn header_value(headers: &[(HeaderName, HeaderValue)], name: &HeaderName) -> Option<String> {
+  headers
+    .iter()
+    .find(|(header, _)| header == name)
+    .map(|(_, value)| text(value))
+}
+
+fn headers_json(headers: &[(HeaderName, HeaderValue)]) -> Value {
+  headers
+    .iter()
+    .map(|(name, value)| json!({ "name": name.as_str(), "value": text(value) }))
+    .collect()
+}
+
+fn request_cookies(headers: &[(HeaderName, HeaderValue)]) -> Value {
+  headers
+    .iter()
+    .filter(|(name, _)| name == header::COOKIE)
+    .flat_map(|(_, value)| {
+      text(value)
+        .split(';')
+        .filter_map(|pair| {
+          let (name, value) = pair.trim().split_once('=')?;
+          Some(json!({ "name": name, "value": value }))
+        })
+        .collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+fn response_cookies(headers: &[(HeaderName, HeaderValue)]) -> Value {
+  headers
+    .iter()
+    .filter(|(name, _)| name == header::SET_COOKIE)
+    .filter_map(|(_, value)| cookie::Cookie::parse(text(value)).ok())
+    .map(|cookie| {
+      let mut entry = json!({ "name": cookie.name(), "value": cookie.value() });
+      if let Some(path) = cookie.path() {
+        entry["path"] = json!(path);
+      }
+      if let Some(domain) = cookie.domain() {
+        entry["domain"] = json!(domain);
+      }
+      if let Some(expires) = cookie.expires_datetime() {
+        entry["expires"] = json!(iso8601(expires.into()));
+      }
+      if let Some(http_only) = cookie.http_only() {
+        entry["httpOnly"] = json!(http_only);
+      }
+      if let Some(secure) = cookie.secure() {
+        entry["secure"] = json!(secure);
+      }
+      entry
+    })
+    .collect()
+}
+
+fn query_string(url: &str) -> Value {
+  url::Url::parse(url)
+    .map(|url| {
+      url
+        .query_pairs()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+    })
+    .unwrap_or_else(|_| json!([]))
+}
+
+/// `size`, `text` and, for binary bodies, `encoding`, noting when the body was truncated.
+fn content_json(body: &Content) -> Map<String, Value> {
+  let mut content = Map::new();
+  content.insert("size".to_string(), json!(body.size));
+
+  let kept = &body.kept[..];
+  if kept.len() < body.size {
+    content.insert(
+      "comment".to_string(),
+      json!(format!("truncated to {} bytes", kept.len())),
+    );
+  }
+
+  match std::str::from_utf8(kept) {
+    Ok(text) => {
+      content.insert("text".to_string(), json!(text));
+    }
+    Err(_) => {
+      content.insert("text".to_string(), json!(base64(kept)));
+      content.insert("encoding".to_string(), json!("base64"));
+    }
+  }
+  content
+}
+
+pub(super) fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+      group | ((*byte as u32) << (16 - 8 * index))
+    });
+    for index in 0..4 {
+      if index <= chunk.len() {
+        encoded.push(ALPHABET[((group >> (18 - 6 * index)) & 0x3f) as usize] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
+
+pub(super) fn decode_base64(text: &str) -> Option<Vec<u8>> {
+  let sextet = |byte: u8| match byte {
+    b'A'..=b'Z' => Some(byte - b'A'),
+    b'a'..=b'z' => Some(byte - b'a' + 26),
+    b'0'..=b'9' => Some(byte - b'0' + 52),
+    b'+' | b'-' => Some(62),
+    b'/' | b'_' => Some(63),
+    _ => None,
+  };
+
+  let text = text.trim_end_matches('=').as_bytes();
+  let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+  for chunk in text.chunks(4) {
+    if chunk.len() == 1 {
+      return None;
+    }
+    let group = chunk
+      .iter()
+      .enumerate()
+      .try_fold(0u32, |group, (index, byte)| {
+        Some(group | ((sextet(*byte)? as u32) << (18 - 6 * index)))
+      })?;
+    for index in 0..chunk.len() - 1 {
+      decoded.push((group >> (16 - 8 * index)) as u8);
+    }
+  }
+  Some(decoded)
+}
+
+/// `time` as an ISO 8601 UTC timestamp with milliseconds.
+fn iso8601(time: SystemTime) -> String {
+  let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+  let seconds = since_epoch.as_secs();
+  let days = (seconds / 86_400) as i64;
+  let (hour, minute, second) = (seconds % 86_400 / 3600, seconds % 3600 / 60, seconds % 60);
+
+  // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`.
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let day_of_era = z.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+  format!(
+    "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{:03}Z",
+    since_epoch.subsec_millis()
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn formats_timestamps() {
+    assert_eq!(iso8601(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    assert_eq!(
+      iso8601(UNIX_EPOCH + Duration::from_millis(1_709_210_096_789)),
+      "2024-02-29T12:34:56.789Z"
+    );
+  }
+
+  #[test]
+  fn encodes_base64() {
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foo"), "Zm9v");
+    assert_eq!(base64(&[0xff, 0x00, 0x80, 0x7f]), "/wCAfw==");
+  }
+
+  #[test]
+  fn decodes_base64() {
+    for bytes in [&b""[..], b"f", b"fo", b"foo", &[0xff, 0x00, 0x80, 0x7f]] {
+      assert_eq!(decode_base64(&base64(bytes)).as_deref(), Some(bytes));
+    }
+    assert_eq!(decode_base64("Zm9v!"), None);
+  }
+
+  #[test]
+  fn truncates_bodies() {
+    let recorded = Content::new(&Bytes::from_static(b"hello world"), Some(5));
+    assert_eq!(recorded.kept, "hello");
+    let content = content_json(&recorded);
+    assert_eq!(content["size"], 11);
+    assert_eq!(content["text"], "hello");
+    assert_eq!(content["comment"], "truncated to 5 bytes");
+
+    let binary = content_json(&Content::new(&Bytes::from_static(&[0xff, 0xfe]), None));
+    assert_eq!(binary["encoding"], "base64");
+  }
+
+  #[test]
+  fn records_entries_in_order() {
+    let recorder = Arc::new(HarRecorder::new());
+    let request = || HarRequest {
+      method: "GET".to_string(),
+      url: "https://example.com/?q=1".to_string(),
+      headers: vec![(header::COOKIE, HeaderValue::from_static("a=1; b=2"))],
+      body: None,
+      body_streamed: false,
+    };
+
+    HarCapture::new(recorder.clone(), request()).record_error(
+      &Error::CircuitOpen("example.com".to_string()),
+      Duration::ZERO,
+      Duration::ZERO,
+    );
+    HarCapture::new(recorder.clone(), request()).record_error(
+      &Error::NonReplayableBody,
+      Duration::ZERO,
+      Duration::ZERO,
+    );
+
+    let har = recorder.to_json();
+    let entries = har["log"]["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["_error"], "circuit breaker open for example.com");
+    assert_eq!(entries[0]["request"]["queryString"][0]["name"], "q");
+    assert_eq!(entries[0]["request"]["cookies"][1]["value"], "2");
+    assert_eq!(entries[1]["response"]["status"], 0);
+
+    recorder.clear();
+    assert!(recorder.is_empty());
+  }
+}
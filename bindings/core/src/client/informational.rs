@@ -0,0 +1,91 @@
+//! Reporting `1xx` responses and holding request bodies back for `100 Continue`.
+
+use std::{
+  mem,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use tokio::sync::Notify;
+
+use crate::{body::Gated, Request};
+
+/// How long a body waits for `100 Continue` when the request doesn't say.
+pub const DEFAULT_EXPECT_CONTINUE: Duration = Duration::from_secs(1);
+
+/// Called with every `1xx` response received before the final one.
+pub type InformationalCallback = Arc<dyn Fn(InformationalResponse) + Send + Sync>;
+
+/// An interim response, such as `100 Continue` or `103 Early Hints`.
+#[derive(Debug, Clone)]
+pub struct InformationalResponse {
+  pub status: StatusCode,
+  pub headers: HeaderMap,
+}
+
+/// Collects the interim responses of a request and passes them to its callback.
+///
+/// Interim responses are only reported by HTTP/1.1 connections.
+#[derive(Default)]
+pub(super) struct Informational {
+  received: Arc<Mutex<Vec<InformationalResponse>>>,
+  callback: Option<InformationalCallback>,
+  expect_continue: Option<Duration>,
+}
+
+impl Informational {
+  pub(super) fn from_request(params: &mut Request) -> Self {
+    Self {
+      callback: params.on_informational.take(),
+      expect_continue: params.expect_continue.take(),
+      ..Self::default()
+    }
+  }
+
+  /// Hook into the connection of a built request.
+  ///
+  /// With `Expect: 100-continue`, the body is held back until the server answers `100 Continue`
+  /// or the wait runs out, as servers that don't know the header never answer it.
+  pub(super) fn attach(&self, request: &mut wreq::Request) {
+    let continued = self.expect_continue.and_then(|wait| {
+      let body = request.body_mut().take()?;
+      let continued = Arc::new(Notify::new());
+      let notified = continued.clone();
+      *request.body_mut() = Some(wreq::Body::wrap(Gated::new(body, async move {
+        let _ = tokio::time::timeout(wait, notified.notified_owned()).await;
+      })));
+      request
+        .headers_mut()
+        .insert(header::EXPECT, HeaderValue::from_static("100-continue"));
+      Some(continued)
+    });
+
+    let received = self.received.clone();
+    let callback = self.callback.clone();
+    let mut hooked = http::Request::new(());
+    wreq_proto::ext::on_informational(&mut hooked, move |response| {
+      if response.status() == StatusCode::CONTINUE {
+        if let Some(continued) = &continued {
+          continued.notify_one();
+        }
+      }
+      let response = InformationalResponse {
+        status: response.status(),
+        headers: response.headers().clone(),
+      };
+      received.lock().unwrap().push(response.clone());
+      if let Some(callback) = &callback {
+        callback(response);
+      }
+    });
+    request
+      .extensions_mut()
+      .extend(hooked.into_parts().0.extensions);
+  }
+
+  /// The interim responses received so far.
+  pub(super) fn take(&self) -> Vec<InformationalResponse> {
+    mem::take(&mut *self.received.lock().unwrap())
+  }
+}
@@ -0,0 +1,230 @@
+//! Hooks run around every request a client sends.
+
+use std::{future, sync::Arc};
+
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use http::{HeaderMap, Method, StatusCode, Uri, Version};
+
+use super::Client;
+use crate::{Error, Response};
+
+/// Times the hooks of a client may retry a single request.
+pub const MAX_HOOK_RETRIES: u32 = 5;
+
+/// Code run around every request a client sends, including retries and redirect hops followed
+/// one at a time.
+///
+/// Middleware runs in the order it was added to the client. The circuit breaker, concurrency and
+/// rate limits key on the URL as it was before `before_request`.
+pub trait Middleware: Send + Sync {
+  /// Inspect or rewrite a request before it is sent.
+  fn before_request<'a>(
+    &'a self,
+    request: &'a mut HookRequest,
+  ) -> BoxFuture<'a, Result<(), Error>> {
+    let _ = request;
+    Box::pin(future::ready(Ok(())))
+  }
+
+  /// Inspect a response, replace it, or have the request sent again.
+  fn after_response<'a>(
+    &'a self,
+    request: &'a HookRequest,
+    response: &'a Response,
+  ) -> BoxFuture<'a, Result<HookAction, Error>> {
+    let _ = (request, response);
+    Box::pin(future::ready(Ok(HookAction::Continue)))
+  }
+
+  /// Inspect a failed request, answer it with a response instead, or have it sent again.
+  fn on_error<'a>(
+    &'a self,
+    request: &'a HookRequest,
+    error: &'a Error,
+  ) -> BoxFuture<'a, Result<HookAction, Error>> {
+    let _ = (request, error);
+    Box::pin(future::ready(Ok(HookAction::Continue)))
+  }
+}
+
+/// A request as middleware sees it: resolved by the client and not yet sent.
+#[derive(Debug, Clone)]
+pub struct HookRequest {
+  pub method: Method,
+  /// The URL with the query merged in.
+  pub uri: Uri,
+  /// Headers set on the request. The client's default and emulation headers are added when it
+  /// is sent.
+  pub headers: HeaderMap,
+  /// The body, or `None` for an empty or streamed one. Setting it replaces a streamed body.
+  pub body: Option<Bytes>,
+}
+
+/// What to do once middleware has seen a response or error.
+#[derive(Debug)]
+pub enum HookAction {
+  /// Hand the response or error on to the next middleware.
+  Continue,
+  /// Use this response instead.
+  Replace(Response),
+  /// Send the request again, up to [`MAX_HOOK_RETRIES`] times. Requests with a body that can't
+  /// be replayed end with what they got.
+  Retry,
+}
+
+/// How a request ended once its middleware ran.
+pub(super) enum HookOutcome {
+  Done(Result<Response, Error>),
+  Retry(Result<Response, Error>),
+}
+
+impl HookRequest {
+  fn new(request: &wreq::Request) -> Self {
+    Self {
+      method: request.method().clone(),
+      uri: request.uri().clone(),
+      headers: request.headers().clone(),
+      body: request
+        .body()
+        .and_then(wreq::Body::as_bytes)
+        .map(Bytes::copy_from_slice),
+    }
+  }
+
+  /// A response to this request made up in place of the server's.
+  pub fn respond(&self, status: StatusCode, headers: HeaderMap, body: Bytes) -> Response {
+    Response::buffered(Version::HTTP_11, status, headers, self.uri.clone(), body)
+  }
+}
+
+impl Client {
+  /// Run the `before_request` hooks on `request`, returning what they left for the other hooks.
+  pub(super) async fn before_request(
+    &self,
+    request: &mut wreq::Request,
+  ) -> Result<Option<HookRequest>, Error> {
+    if self.middleware.is_empty() {
+      return Ok(None);
+    }
+
+    let mut hooked = HookRequest::new(request);
+    let streamed = request.body().is_some() && hooked.body.is_none();
+    for middleware in self.middleware.iter() {
+      middleware.before_request(&mut hooked).await?;
+    }
+
+    *request.method_mut() = hooked.method.clone();
+    *request.uri_mut() = hooked.uri.clone();
+    merge_headers(request.headers_mut(), &hooked.headers);
+    match &hooked.body {
+      Some(body) => *request.body_mut() = Some(wreq::Body::from(body.clone())),
+      None if !streamed => *request.body_mut() = None,
+      None => {}
+    }
+    Ok(Some(hooked))
+  }
+
+  /// Run the `after_response` or `on_error` hooks on the outcome of `request`.
+  pub(super) async fn after_request(
+    &self,
+    request: &HookRequest,
+    mut result: Result<Response, Error>,
+  ) -> HookOutcome {
+    for middleware in self.middleware.iter() {
+      let action = match &result {
+        Ok(response) => middleware.after_response(request, response).await,
+        Err(err) => middleware.on_error(request, err).await,
+      };
+      match action {
+        Ok(HookAction::Continue) => {}
+        Ok(HookAction::Replace(response)) => {
+          if let Ok(replaced) = &result {
+            replaced.close();
+          }
+          result = Ok(response);
+        }
+        Ok(HookAction::Retry) => return HookOutcome::Retry(result),
+        Err(err) => {
+          if let Ok(response) = &result {
+            response.close();
+          }
+          return HookOutcome::Done(Err(err));
+        }
+      }
+    }
+    HookOutcome::Done(result)
+  }
+}
+
+/// Bring `headers` in line with `hooked`, keeping the names a hook left in their place in the
+/// wire order and adding new ones after them.
+fn merge_headers(headers: &mut HeaderMap, hooked: &HeaderMap) {
+  let mut merged = HeaderMap::with_capacity(hooked.len());
+  for name in headers.keys().chain(hooked.keys()) {
+    if merged.contains_key(name) {
+      continue;
+    }
+    for value in hooked.get_all(name) {
+      merged.append(name.clone(), value.clone());
+    }
+  }
+  *headers = merged;
+}
+
+/// Middleware added to a client, shared by its clones.
+pub(super) type MiddlewareStack = Arc<[Arc<dyn Middleware>]>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn made_up_responses_answer_the_request() {
+    let request = HookRequest {
+      method: Method::GET,
+      uri: Uri::from_static("https://api.test/items"),
+      headers: HeaderMap::new(),
+      body: None,
+    };
+
+    let response = request.respond(
+      StatusCode::ACCEPTED,
+      HeaderMap::new(),
+      Bytes::from_static(b"queued"),
+    );
+    assert_eq!(response.status, StatusCode::ACCEPTED);
+    assert_eq!(response.uri, request.uri);
+  }
+
+  #[test]
+  fn merged_headers_keep_their_order() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-first", "1".parse().unwrap());
+    headers.insert("x-gone", "2".parse().unwrap());
+    headers.insert("x-changed", "3".parse().unwrap());
+    headers.insert("x-last", "4".parse().unwrap());
+
+    let mut hooked = headers.clone();
+    hooked.remove("x-gone");
+    hooked.insert("x-changed", "a".parse().unwrap());
+    hooked.append("x-changed", "b".parse().unwrap());
+    hooked.insert("x-added", "5".parse().unwrap());
+
+    merge_headers(&mut headers, &hooked);
+    let pairs: Vec<_> = headers
+      .iter()
+      .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+      .collect();
+    assert_eq!(
+      pairs,
+      [
+        ("x-first", "1"),
+        ("x-changed", "a"),
+        ("x-changed", "b"),
+        ("x-last", "4"),
+        ("x-added", "5"),
+      ]
+    );
+  }
+}
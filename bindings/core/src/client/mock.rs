@@ -0,0 +1,376 @@
+//! Canned responses and failures for requests matching a pattern, in place of the network.
+
+use std::{
+  convert::Infallible,
+  future::Future,
+  io,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use regex::{Regex, RegexBuilder};
+
+use super::har::HarRequest;
+use crate::Error;
+
+/// Routes requests to canned replies and keeps a log of every request it sees.
+///
+/// Routes are tried in the order they were added; a route with a hit limit stops matching once
+/// it is used up.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+  passthrough: bool,
+  state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+  routes: Vec<(MockRoute, usize)>,
+  calls: Vec<MockCall>,
+}
+
+/// A matcher, the reply it gives and how often.
+#[derive(Debug, Clone)]
+pub struct MockRoute {
+  pub matcher: MockMatcher,
+  pub reply: MockReply,
+  /// Times the route may match; unlimited when `None`.
+  pub times: Option<usize>,
+}
+
+/// What a request must look like to match a route. Unset parts match anything.
+#[derive(Debug, Clone, Default)]
+pub struct MockMatcher {
+  pub method: Option<Method>,
+  pub url: Option<UrlPattern>,
+  /// Headers the request must carry with exactly these values.
+  pub headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// A pattern matched against the whole request URL.
+#[derive(Debug, Clone)]
+pub struct UrlPattern(Regex);
+
+/// The response, or failure, a route answers with.
+#[derive(Debug, Clone)]
+pub struct MockReply {
+  pub status: StatusCode,
+  pub headers: HeaderMap,
+  pub body: Bytes,
+  /// Time to wait before answering.
+  pub delay: Option<Duration>,
+  /// Fail the request instead of answering.
+  pub failure: Option<MockFailure>,
+}
+
+/// A transport failure a route can inject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFailure {
+  Timeout,
+  ConnectionReset,
+  ConnectionRefused,
+  Tls,
+}
+
+/// A request seen by a [`MockTransport`].
+#[derive(Debug, Clone)]
+pub struct MockCall {
+  pub method: Method,
+  pub uri: Uri,
+  /// Headers as sent, including the client's defaults and emulation headers.
+  pub headers: HeaderMap,
+  /// The request body, or `None` for an empty or streamed one.
+  pub body: Option<Bytes>,
+  /// Index of the route that answered, or `None` when none matched.
+  pub route: Option<usize>,
+}
+
+/// A request about to be answered by a mock or the network.
+pub(crate) struct MockRequest {
+  transport: Arc<MockTransport>,
+  request: HarRequest,
+  /// The shorter of the request's total and read timeouts, which the inner client would apply.
+  timeout: Option<Duration>,
+}
+
+impl MockTransport {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Send requests no route matches over the network instead of failing them.
+  pub fn with_passthrough(mut self, passthrough: bool) -> Self {
+    self.passthrough = passthrough;
+    self
+  }
+
+  pub fn add(&self, route: MockRoute) {
+    self.state.lock().unwrap().routes.push((route, 0));
+  }
+
+  /// Requests seen so far, in the order they were sent.
+  pub fn calls(&self) -> Vec<MockCall> {
+    self.state.lock().unwrap().calls.clone()
+  }
+
+  pub fn clear_calls(&self) {
+    self.state.lock().unwrap().calls.clear();
+  }
+
+  /// Remove every route and forget every call.
+  pub fn reset(&self) {
+    let mut state = self.state.lock().unwrap();
+    state.routes.clear();
+    state.calls.clear();
+  }
+
+  /// Log `call` and return the reply of the first route matching it.
+  fn answer(&self, mut call: MockCall) -> Option<MockReply> {
+    let mut state = self.state.lock().unwrap();
+    let matched = state.routes.iter().position(|(route, hits)| {
+      route.times.is_none_or(|times| *hits < times) && route.matcher.matches(&call)
+    });
+    call.route = matched;
+    state.calls.push(call);
+
+    let (route, hits) = &mut state.routes[matched?];
+    *hits += 1;
+    Some(route.reply.clone())
+  }
+}
+
+impl MockMatcher {
+  fn matches(&self, call: &MockCall) -> bool {
+    self
+      .method
+      .as_ref()
+      .is_none_or(|method| *method == call.method)
+      && self
+        .url
+        .as_ref()
+        .is_none_or(|url| url.matches(&call.uri.to_string()))
+      && self
+        .headers
+        .iter()
+        .all(|(name, value)| call.headers.get_all(name).iter().any(|sent| sent == value))
+  }
+}
+
+impl UrlPattern {
+  /// A glob where `*` matches within a path segment, `**` matches across segments and `?`
+  /// matches one character.
+  pub fn glob(pattern: &str) -> Result<Self, Error> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+      match c {
+        '*' if chars.next_if_eq(&'*').is_some() => regex.push_str(".*"),
+        '*' => regex.push_str("[^/]*"),
+        '?' => regex.push('.'),
+        c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+      }
+    }
+    regex.push('$');
+    Self::regex(&regex, false)
+  }
+
+  /// A regular expression, which matches anywhere in the URL unless anchored.
+  pub fn regex(pattern: &str, case_insensitive: bool) -> Result<Self, Error> {
+    RegexBuilder::new(pattern)
+      .case_insensitive(case_insensitive)
+      .build()
+      .map(Self)
+      .map_err(|err| Error::Mock(format!("invalid URL pattern: {err}")))
+  }
+
+  pub fn matches(&self, url: &str) -> bool {
+    self.0.is_match(url)
+  }
+}
+
+impl Default for MockReply {
+  fn default() -> Self {
+    Self {
+      status: StatusCode::OK,
+      headers: HeaderMap::new(),
+      body: Bytes::new(),
+      delay: None,
+      failure: None,
+    }
+  }
+}
+
+impl MockReply {
+  fn to_response(&self) -> wreq::Response {
+    let mut response = http::Response::new(wreq::Body::from(self.body.clone()));
+    *response.status_mut() = self.status;
+    *response.headers_mut() = self.headers.clone();
+    wreq::Response::from(response)
+  }
+}
+
+impl MockFailure {
+  /// The error the failure surfaces as. A timeout comes once `timeout` has passed, as for a
+  /// server that never answers, or at once for requests without one.
+  async fn into_error(self, timeout: Option<Duration>) -> Error {
+    let io_error = |kind, message| io::Error::new(kind, message);
+    match self {
+      MockFailure::Timeout => {
+        let never = std::future::pending::<Infallible>();
+        let elapsed = tokio::time::timeout(timeout.unwrap_or_default(), never).await;
+        Error::Timeout(elapsed.unwrap_err())
+      }
+      MockFailure::ConnectionReset => Error::ConnectionReset(io_error(
+        io::ErrorKind::ConnectionReset,
+        "connection reset by peer",
+      )),
+      MockFailure::ConnectionRefused => Error::Connect(io_error(
+        io::ErrorKind::ConnectionRefused,
+        "connection refused",
+      )),
+      MockFailure::Tls => {
+        Error::Connect(io_error(io::ErrorKind::InvalidData, "TLS handshake failed"))
+      }
+    }
+  }
+}
+
+impl MockRequest {
+  pub(crate) fn new(
+    transport: Arc<MockTransport>,
+    request: HarRequest,
+    timeout: Option<Duration>,
+  ) -> Self {
+    Self {
+      transport,
+      request,
+      timeout,
+    }
+  }
+
+  /// Answer from the first matching route, falling back to `send` for a passthrough transport.
+  pub(crate) async fn send<F>(&self, send: impl FnOnce() -> F) -> Result<wreq::Response, Error>
+  where
+    F: Future<Output = Result<wreq::Response, Error>>,
+  {
+    let call = MockCall {
+      method: self.request.method().parse().unwrap_or_default(),
+      uri: self.request.url().parse().unwrap_or_default(),
+      headers: self.request.headers().iter().cloned().collect(),
+      body: self.request.body().cloned(),
+      route: None,
+    };
+
+    let Some(reply) = self.transport.answer(call) else {
+      if self.transport.passthrough {
+        return send().await;
+      }
+      return Err(Error::Mock(format!(
+        "no mock matches {} {}",
+        self.request.method(),
+        self.request.url()
+      )));
+    };
+
+    // A delay past the request's timeout ends the way a slow server would. Aborting the
+    // request ends it too, since the whole exchange runs under the request's signal.
+    let delay = reply.delay.unwrap_or_default();
+    match self.timeout {
+      Some(timeout) => tokio::time::timeout(timeout, tokio::time::sleep(delay)).await?,
+      None => tokio::time::sleep(delay).await,
+    }
+
+    match reply.failure {
+      Some(failure) => {
+        let remaining = self.timeout.map(|timeout| timeout.saturating_sub(delay));
+        Err(failure.into_error(remaining).await)
+      }
+      None => Ok(reply.to_response()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn call(method: Method, url: &str) -> MockCall {
+    MockCall {
+      method,
+      uri: url.parse().unwrap(),
+      headers: HeaderMap::new(),
+      body: None,
+      route: None,
+    }
+  }
+
+  #[test]
+  fn globs_match_within_segments() {
+    let pattern = UrlPattern::glob("https://api.test/users/*").unwrap();
+    assert!(pattern.matches("https://api.test/users/1"));
+    assert!(!pattern.matches("https://api.test/users/1/posts"));
+
+    let pattern = UrlPattern::glob("**/users/?").unwrap();
+    assert!(pattern.matches("https://api.test/v2/users/1"));
+    assert!(!pattern.matches("https://api.test/v2/users/12"));
+
+    assert!(UrlPattern::glob("https://api.test/a+b")
+      .unwrap()
+      .matches("https://api.test/a+b"));
+  }
+
+  #[test]
+  fn routes_match_in_order_until_used_up() {
+    let transport = MockTransport::new();
+    let reply = |status| MockReply {
+      status,
+      ..MockReply::default()
+    };
+    transport.add(MockRoute {
+      matcher: MockMatcher {
+        method: Some(Method::GET),
+        url: Some(UrlPattern::regex("/items$", false).unwrap()),
+        headers: Vec::new(),
+      },
+      reply: reply(StatusCode::SERVICE_UNAVAILABLE),
+      times: Some(1),
+    });
+    transport.add(MockRoute {
+      matcher: MockMatcher::default(),
+      reply: reply(StatusCode::OK),
+      times: None,
+    });
+
+    let url = "https://api.test/items";
+    let first = transport.answer(call(Method::GET, url)).unwrap();
+    let second = transport.answer(call(Method::GET, url)).unwrap();
+    assert_eq!(first.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(second.status, StatusCode::OK);
+
+    let calls = transport.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].route, Some(0));
+    assert_eq!(calls[1].route, Some(1));
+  }
+
+  #[test]
+  fn header_matchers_need_the_value() {
+    let matcher = MockMatcher {
+      headers: vec![(
+        http::header::AUTHORIZATION,
+        HeaderValue::from_static("Bearer token"),
+      )],
+      ..MockMatcher::default()
+    };
+    let mut sent = call(Method::GET, "https://api.test/");
+    assert!(!matcher.matches(&sent));
+
+    sent.headers.insert(
+      http::header::AUTHORIZATION,
+      HeaderValue::from_static("Bearer token"),
+    );
+    assert!(matcher.matches(&sent));
+  }
+}
@@ -0,0 +1,420 @@
+//! Client-side navigation: following `Refresh` headers and `<meta http-equiv="refresh">` tags.
+
+use std::{net::IpAddr, time::Duration};
+
+use futures_util::FutureExt;
+use http::{header, Uri};
+use url::Url;
+use wreq::{
+  header::{HeaderMap, HeaderValue, OrigHeaderMap},
+  Method, Proxy,
+};
+use wreq_util::EmulationOption;
+
+use super::{redirect::strip_credentials, send_with_retries, Client, DEFAULT_MAX_REDIRECTS};
+use crate::{
+  abort::AbortSignal,
+  response::{RedirectHop, RedirectKind},
+  Error, Request, Response,
+};
+
+/// Bytes of an HTML page searched for a `<meta http-equiv="refresh">` tag.
+const META_REFRESH_SCAN_LIMIT: usize = 64 * 1024;
+
+/// A refresh directive extracted from a `Refresh` header or meta tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refresh {
+  /// Delay in seconds announced by the page. Navigation does not wait for it.
+  pub delay: u64,
+  /// Raw target, possibly relative to the current document.
+  pub target: String,
+}
+
+/// Settings carried over from the original request to every navigation hop.
+pub(crate) struct Navigation {
+  max_redirects: usize,
+  emulation: Option<EmulationOption>,
+  proxy: Option<Proxy>,
+  local_address: Option<IpAddr>,
+  interface: Option<String>,
+  timeout: Option<Duration>,
+  read_timeout: Option<Duration>,
+  headers: Option<HeaderMap>,
+  orig_headers: Option<OrigHeaderMap>,
+  default_headers: Option<bool>,
+  cookies: Option<Vec<HeaderValue>>,
+  auth: Option<String>,
+  bearer_auth: Option<String>,
+  basic_auth: Option<(String, Option<String>)>,
+  signal: Option<AbortSignal>,
+  priority: Option<i32>,
+  queue_timeout: Option<Duration>,
+}
+
+impl Navigation {
+  /// Capture navigation settings from a request, if refresh following is enabled.
+  pub(crate) fn from_request(client: Option<&Client>, params: &mut Request) -> Option<Self> {
+    let enabled = params
+      .follow_refresh
+      .take()
+      .unwrap_or_else(|| client.is_some_and(|client| client.follow_refresh));
+
+    if !enabled || params.allow_redirects == Some(false) {
+      return None;
+    }
+
+    let max_redirects = params
+      .max_redirects
+      .or_else(|| client.map(|client| client.max_redirects))
+      .unwrap_or(DEFAULT_MAX_REDIRECTS);
+
+    Some(Navigation {
+      max_redirects,
+      emulation: params.emulation.clone(),
+      proxy: params.proxy.clone(),
+      local_address: params.local_address,
+      interface: params.interface.clone(),
+      timeout: params.timeout,
+      read_timeout: params.read_timeout,
+      headers: params.headers.clone(),
+      orig_headers: params.orig_headers.clone(),
+      default_headers: params.default_headers,
+      cookies: params.cookies.clone(),
+      auth: params.auth.clone(),
+      bearer_auth: params.bearer_auth.clone(),
+      basic_auth: params.basic_auth.clone(),
+      signal: params.signal.clone(),
+      priority: params.priority,
+      queue_timeout: params.queue_timeout,
+    })
+  }
+
+  /// Follow refresh directives starting from `response` until a page without one is reached.
+  ///
+  /// Every hop is sent as a request of its own, through the client's hooks, recorder, cassette,
+  /// mocks and limits.
+  pub(crate) async fn follow(
+    self,
+    client: Option<Client>,
+    mut response: Response,
+  ) -> Result<Response, Error> {
+    let mut history = Vec::new();
+    // Credentials never follow a navigation to another origin, nor any hop after it.
+    let mut left_origin = false;
+
+    loop {
+      history.extend_from_slice(response.history());
+
+      let Some((kind, target)) = refresh_target(&response).await? else {
+        break;
+      };
+
+      if history.len() >= self.max_redirects {
+        return Err(Error::TooManyRedirects(self.max_redirects));
+      }
+
+      left_origin |= !same_origin(&response.uri, &target);
+      history.push(RedirectHop::new(kind, &response, target.clone()));
+      // The page is left behind, which also frees its concurrency slot for the next hop.
+      response.close();
+
+      let remaining = self.max_redirects - history.len();
+      let hop = self.request(client.as_ref(), left_origin, remaining);
+      // Boxed because sending a hop goes back through the dispatch that follows refreshes.
+      response = send_with_retries(client.clone(), Method::GET, &target.to_string(), hop)
+        .boxed()
+        .await?;
+    }
+
+    response.set_history(history);
+    Ok(response)
+  }
+
+  fn request(&self, client: Option<&Client>, left_origin: bool, remaining: usize) -> Request {
+    let mut request = Request {
+      emulation: self.emulation.clone(),
+      proxy: self.proxy.clone(),
+      local_address: self.local_address,
+      interface: self.interface.clone(),
+      timeout: self.timeout,
+      read_timeout: self.read_timeout,
+      headers: self.headers.clone(),
+      orig_headers: self.orig_headers.clone(),
+      default_headers: self.default_headers,
+      cookies: self.cookies.clone(),
+      auth: self.auth.clone(),
+      bearer_auth: self.bearer_auth.clone(),
+      basic_auth: self.basic_auth.clone(),
+      max_redirects: Some(remaining),
+      // Refreshes on the next page are followed by this navigation, not by the hop.
+      follow_refresh: Some(false),
+      signal: self.signal.clone(),
+      priority: self.priority,
+      queue_timeout: self.queue_timeout,
+      ..Request::default()
+    };
+    if left_origin {
+      strip_credentials(&mut request, client);
+    }
+    request
+  }
+}
+
+/// Locate a refresh directive on a response, resolved against the response URI.
+///
+/// Self-referencing refreshes (periodic reloads) and non-HTTP targets are ignored.
+async fn refresh_target(response: &Response) -> Result<Option<(RedirectKind, Uri)>, Error> {
+  let header_refresh = response
+    .headers
+    .get(header::REFRESH)
+    .and_then(|value| value.to_str().ok())
+    .and_then(parse_refresh)
+    .map(|refresh| (RedirectKind::RefreshHeader, refresh));
+
+  let refresh = match header_refresh {
+    Some(refresh) => Some(refresh),
+    None if response.status.is_success() && is_html(&response.headers) => {
+      // Meta tags belong in the head, so only the start of the page is read and the rest stays
+      // streamable.
+      let head = response
+        .peek(META_REFRESH_SCAN_LIMIT, |read| {
+          read
+            .to_ascii_lowercase()
+            .windows(7)
+            .any(|tag| tag == b"</head>")
+        })
+        .await?;
+      find_meta_refresh(&String::from_utf8_lossy(&head))
+        .map(|refresh| (RedirectKind::MetaRefresh, refresh))
+    }
+    None => None,
+  };
+
+  Ok(refresh.and_then(|(kind, refresh)| {
+    resolve(&response.uri, &refresh.target)
+      .filter(|target| *target != response.uri)
+      .map(|target| (kind, target))
+  }))
+}
+
+fn is_html(headers: &HeaderMap) -> bool {
+  headers
+    .get(header::CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .is_some_and(|value| value.to_ascii_lowercase().contains("html"))
+}
+
+pub(super) fn resolve(base: &Uri, target: &str) -> Option<Uri> {
+  let joined = Url::parse(&base.to_string()).ok()?.join(target).ok()?;
+  match joined.scheme() {
+    "http" | "https" => joined.as_str().parse().ok(),
+    _ => None,
+  }
+}
+
+pub(super) fn same_origin(a: &Uri, b: &Uri) -> bool {
+  a.scheme() == b.scheme()
+    && a.host().map(str::to_ascii_lowercase) == b.host().map(str::to_ascii_lowercase)
+    && a.port_u16() == b.port_u16()
+}
+
+/// Parse a refresh value such as `5; url=/next` or `0;URL='https://example.com/'`.
+///
+/// Returns `None` when the value has no target, i.e. it only reloads the current page.
+pub fn parse_refresh(value: &str) -> Option<Refresh> {
+  let value = value.trim();
+  let split = value.find([';', ',']).unwrap_or(value.len());
+  let (delay, rest) = value.split_at(split);
+
+  let delay = delay.trim();
+  if delay.is_empty() || !delay.chars().all(|c| c.is_ascii_digit() || c == '.') {
+    return None;
+  }
+  let delay = delay
+    .split('.')
+    .next()
+    .and_then(|whole| whole.parse::<u64>().ok())
+    .unwrap_or(0);
+
+  let rest = rest.get(1..).unwrap_or_default().trim_start();
+  let rest = match rest.get(..3) {
+    Some(prefix) if prefix.eq_ignore_ascii_case("url") => rest[3..]
+      .trim_start()
+      .strip_prefix('=')
+      .map(str::trim_start)
+      .unwrap_or(rest),
+    _ => rest,
+  };
+
+  let target = match rest.chars().next() {
+    Some(quote @ ('"' | '\'')) => {
+      let inner = &rest[1..];
+      inner.split(quote).next().unwrap_or_default()
+    }
+    _ => rest.trim_end(),
+  };
+
+  if target.is_empty() {
+    return None;
+  }
+
+  Some(Refresh {
+    delay,
+    target: decode_entities(target),
+  })
+}
+
+/// Find the first `<meta http-equiv="refresh">` directive in an HTML document.
+pub fn find_meta_refresh(html: &str) -> Option<Refresh> {
+  let lower = html.to_ascii_lowercase();
+  let mut offset = 0;
+
+  while let Some(pos) = lower[offset..].find("<meta") {
+    let start = offset + pos + "<meta".len();
+    let end = lower[start..]
+      .find('>')
+      .map_or(lower.len(), |end| start + end);
+    let attributes = parse_attributes(&html[start..end]);
+
+    let is_refresh = attributes
+      .iter()
+      .any(|(name, value)| name == "http-equiv" && value.trim().eq_ignore_ascii_case("refresh"));
+    if is_refresh {
+      let refresh = attributes
+        .iter()
+        .find(|(name, _)| name == "content")
+        .and_then(|(_, content)| parse_refresh(content));
+      if refresh.is_some() {
+        return refresh;
+      }
+    }
+
+    offset = end;
+  }
+
+  None
+}
+
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+  let bytes = tag.as_bytes();
+  let len = bytes.len();
+  let mut attributes = Vec::new();
+  let mut i = 0;
+
+  while i < len {
+    while i < len && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+      i += 1;
+    }
+
+    let name_start = i;
+    while i < len && !bytes[i].is_ascii_whitespace() && bytes[i] != b'=' && bytes[i] != b'/' {
+      i += 1;
+    }
+    if i == name_start {
+      break;
+    }
+    let name = tag[name_start..i].to_ascii_lowercase();
+
+    while i < len && bytes[i].is_ascii_whitespace() {
+      i += 1;
+    }
+
+    let mut value = String::new();
+    if i < len && bytes[i] == b'=' {
+      i += 1;
+      while i < len && bytes[i].is_ascii_whitespace() {
+        i += 1;
+      }
+
+      if i < len && (bytes[i] == b'"' || bytes[i] == b'\'') {
+        let quote = bytes[i];
+        i += 1;
+        let value_start = i;
+        while i < len && bytes[i] != quote {
+          i += 1;
+        }
+        value = tag[value_start..i].to_string();
+        i += 1;
+      } else {
+        let value_start = i;
+        while i < len && !bytes[i].is_ascii_whitespace() {
+          i += 1;
+        }
+        value = tag[value_start..i].to_string();
+      }
+    }
+
+    attributes.push((name, value));
+  }
+
+  attributes
+}
+
+fn decode_entities(value: &str) -> String {
+  value
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_refresh_header_values() {
+    let cases = [
+      (
+        "0; url=https://example.com/next",
+        0,
+        "https://example.com/next",
+      ),
+      ("5;URL='/login'", 5, "/login"),
+      ("3, url = \"relative?a=1\"", 3, "relative?a=1"),
+      ("1; /bare", 1, "/bare"),
+      ("2.5;url=/frac", 2, "/frac"),
+    ];
+
+    for (input, delay, target) in cases {
+      let refresh = parse_refresh(input).unwrap();
+      assert_eq!(refresh.delay, delay, "{input}");
+      assert_eq!(refresh.target, target, "{input}");
+    }
+  }
+
+  #[test]
+  fn ignores_reload_only_refresh() {
+    assert_eq!(parse_refresh("30"), None);
+    assert_eq!(parse_refresh("10;"), None);
+    assert_eq!(parse_refresh("soon; url=/x"), None);
+  }
+
+  #[test]
+  fn finds_meta_refresh_in_document() {
+    let html = r#"<html><head>
+      <meta charset="utf-8">
+      <META HTTP-EQUIV="Refresh" CONTENT="0; URL=/next?a=1&amp;b=2">
+    </head></html>"#;
+
+    let refresh = find_meta_refresh(html).unwrap();
+    assert_eq!(refresh.target, "/next?a=1&b=2");
+  }
+
+  #[test]
+  fn skips_meta_tags_without_refresh() {
+    let html = r#"<meta name="description" content="0; url=/nope"><p>hello</p>"#;
+    assert_eq!(find_meta_refresh(html), None);
+  }
+
+  #[test]
+  fn resolves_relative_targets() {
+    let base: Uri = "https://example.com/a/b".parse().unwrap();
+    assert_eq!(
+      resolve(&base, "c").unwrap().to_string(),
+      "https://example.com/a/c"
+    );
+    assert_eq!(resolve(&base, "javascript:alert(1)"), None);
+  }
+}
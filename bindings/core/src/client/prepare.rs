@@ -0,0 +1,310 @@
+//! Resolving a request without sending it.
+
+use std::mem;
+
+use bytes::Bytes;
+use http::{
+  header::{Entry, HeaderName},
+  HeaderMap, HeaderValue, Method, Uri,
+};
+use wreq::{header::OrigHeaderMap, EmulationFactory};
+use wreq_util::EmulationOption;
+
+use super::{build_request, dispatch, Client, Outgoing};
+use crate::{Error, Request, Response};
+
+/// Headers the client adds to every request: those of its emulation preset, its user agent and
+/// its default headers, plus the order they go on the wire in.
+#[derive(Debug, Default)]
+pub(super) struct DefaultHeaders {
+  pub(super) headers: HeaderMap,
+  pub(super) orig_headers: Option<OrigHeaderMap>,
+}
+
+impl DefaultHeaders {
+  pub(super) fn new(
+    emulation: Option<EmulationOption>,
+    user_agent: Option<&str>,
+    headers: Option<&HeaderMap>,
+    orig_headers: Option<&OrigHeaderMap>,
+  ) -> Result<Self, Error> {
+    let (mut defaults, emulation_order) = match emulation {
+      Some(emulation) => {
+        let (headers, orig_headers) = emulation_headers(emulation);
+        (headers, Some(orig_headers))
+      }
+      None => (HeaderMap::new(), None),
+    };
+
+    if let Some(user_agent) = user_agent {
+      defaults.insert(http::header::USER_AGENT, HeaderValue::from_str(user_agent)?);
+    }
+
+    if let Some(headers) = headers {
+      defaults.extend(headers.clone());
+    }
+
+    Ok(Self {
+      headers: defaults,
+      orig_headers: orig_headers.cloned().or(emulation_order),
+    })
+  }
+}
+
+/// What a prepared request will send as its body.
+#[derive(Debug, Clone)]
+pub enum PreparedBody {
+  Empty,
+  /// A body known up front, such as JSON, a form or a buffer.
+  Buffered(Bytes),
+  /// A body produced while sending, such as a stream or multipart form.
+  Streaming,
+}
+
+/// A request resolved up to the point of sending.
+///
+/// Sending goes through the client's circuit breaker, concurrency and rate limits like any other
+/// request, but makes a single attempt with redirects followed by the inner client. Requests that
+/// a retry policy or a redirect policy restricting hops would apply to fail to send instead. The
+/// request goes out exactly as resolved, so hooks, the HTTP cache and single-flight coalescing,
+/// which may rewrite, answer or share a request, are skipped as well.
+pub struct PreparedRequest {
+  client: Client,
+  request: wreq::Request,
+  headers: Vec<(HeaderName, HeaderValue)>,
+  outgoing: Outgoing,
+  /// Why the request can't be sent, if it can't.
+  unsupported: Option<&'static str>,
+}
+
+impl PreparedRequest {
+  pub fn method(&self) -> &Method {
+    self.request.method()
+  }
+
+  /// The request URL with its query merged in.
+  pub fn uri(&self) -> &Uri {
+    self.request.uri()
+  }
+
+  /// Headers in wire order, including the client's defaults and emulation headers.
+  ///
+  /// Headers added by the connection itself, like `Host`, `Content-Length` and cookies from a
+  /// cookie store, are not included.
+  pub fn headers(&self) -> &[(HeaderName, HeaderValue)] {
+    &self.headers
+  }
+
+  pub fn body(&self) -> PreparedBody {
+    match self.request.body() {
+      None => PreparedBody::Empty,
+      Some(body) => match body.as_bytes() {
+        Some(bytes) => PreparedBody::Buffered(Bytes::copy_from_slice(bytes)),
+        None => PreparedBody::Streaming,
+      },
+    }
+  }
+
+  pub async fn send(self) -> Result<Response, Error> {
+    let Self {
+      client,
+      mut request,
+      outgoing,
+      unsupported,
+      ..
+    } = self;
+    if let Some(reason) = unsupported {
+      return Err(Error::Unsupported(reason.to_string()));
+    }
+    outgoing.informational.attach(&mut request);
+    let inner = client.inner().clone();
+    dispatch(Some(client), outgoing, move || inner.execute(request)).await
+  }
+}
+
+impl Client {
+  /// Resolve a request the way [`execute_request`](super::execute_request) would, without
+  /// sending it.
+  pub fn prepare(
+    &self,
+    method: Method,
+    url: &str,
+    params: Request,
+  ) -> Result<PreparedRequest, Error> {
+    let unsupported = self.unsupported(&method, &params);
+    let layers = HeaderLayers::capture(&params);
+    let read_timeout = params.read_timeout;
+    let (builder, mut outgoing) = build_request(Some(self), method, url, params, false)?;
+    let request = builder.build().map_err(Error::Library)?;
+    let headers = layers.resolve(self, &request);
+
+    self.capture(&mut outgoing, &request, headers.clone(), read_timeout);
+    Ok(PreparedRequest {
+      client: self.clone(),
+      request,
+      headers,
+      outgoing,
+      unsupported,
+    })
+  }
+
+  /// Why a prepared request with `params` can't be sent the way
+  /// [`execute_request`](super::execute_request) would send it, if it can't.
+  fn unsupported(&self, method: &Method, params: &Request) -> Option<&'static str> {
+    let retry = params.retry.as_ref().or(self.retry.as_ref());
+    if retry.is_some_and(|policy| policy.allows(method)) {
+      return Some("prepared requests are sent once, but a retry policy applies");
+    }
+    let allow_redirects = params.allow_redirects.unwrap_or(self.allow_redirects);
+    let redirect = params.redirect.as_ref().or(self.redirect.as_ref());
+    if allow_redirects && redirect.is_some_and(|policy| policy.restricts()) {
+      return Some(
+        "prepared requests can't check redirects hop by hop, but a redirect policy applies",
+      );
+    }
+    None
+  }
+}
+
+/// The request settings that add headers beside the request's own: its emulation, whether the
+/// client's defaults apply, and the wire order.
+pub(super) struct HeaderLayers {
+  emulation: Option<(HeaderMap, OrigHeaderMap)>,
+  use_defaults: bool,
+  orig_headers: Option<OrigHeaderMap>,
+}
+
+impl HeaderLayers {
+  /// Capture the layers of `params` before it is turned into a request.
+  pub(super) fn capture(params: &Request) -> Self {
+    Self {
+      emulation: params.emulation.clone().map(emulation_headers),
+      use_defaults: params.default_headers.unwrap_or(true),
+      orig_headers: params.orig_headers.clone(),
+    }
+  }
+
+  /// The headers of `request` merged with the layers and `client`'s defaults, in wire order.
+  pub(super) fn resolve(
+    &self,
+    client: &Client,
+    request: &wreq::Request,
+  ) -> Vec<(HeaderName, HeaderValue)> {
+    let mut layers = Vec::new();
+    let mut order = self.orig_headers.as_ref();
+    if let Some((headers, orig_headers)) = &self.emulation {
+      layers.push(headers);
+      order = order.or(Some(orig_headers));
+    }
+    if self.use_defaults {
+      layers.push(&client.default_headers.headers);
+      order = order.or(client.default_headers.orig_headers.as_ref());
+    }
+
+    resolve_headers(request.headers(), &layers, order)
+  }
+}
+
+fn emulation_headers(emulation: EmulationOption) -> (HeaderMap, OrigHeaderMap) {
+  let mut emulation = emulation.emulation();
+  (
+    mem::take(emulation.headers_mut()),
+    mem::take(emulation.orig_headers_mut()),
+  )
+}
+
+/// Merge `headers` with `layers` of defaults, earlier ones winning per name, then order them:
+/// names listed in `order` first, the rest as they came.
+fn resolve_headers(
+  headers: &HeaderMap,
+  layers: &[&HeaderMap],
+  order: Option<&OrigHeaderMap>,
+) -> Vec<(HeaderName, HeaderValue)> {
+  let mut merged = headers.clone();
+  for layer in layers {
+    for name in layer.keys() {
+      if let Entry::Vacant(entry) = merged.entry(name) {
+        let mut values = layer.get_all(name).iter().cloned();
+        if let Some(first) = values.next() {
+          entry.insert(first);
+          merged.extend(values.map(|value| (name.clone(), value)));
+        }
+      }
+    }
+  }
+
+  let mut resolved = Vec::with_capacity(merged.len());
+  for (name, _) in order.into_iter().flat_map(OrigHeaderMap::iter) {
+    if let Entry::Occupied(entry) = merged.entry(name) {
+      let (name, values) = entry.remove_entry_mult();
+      resolved.extend(values.map(|value| (name.clone(), value)));
+    }
+  }
+
+  let mut current = None;
+  for (name, value) in merged {
+    if let Some(name) = name {
+      current = Some(name);
+    }
+    if let Some(name) = &current {
+      resolved.push((name.clone(), value));
+    }
+  }
+  resolved
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn map(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+      map.append(*name, HeaderValue::from_static(value));
+    }
+    map
+  }
+
+  fn names(resolved: &[(HeaderName, HeaderValue)]) -> Vec<&str> {
+    resolved.iter().map(|(name, _)| name.as_str()).collect()
+  }
+
+  #[test]
+  fn request_headers_win_over_defaults() {
+    let resolved = resolve_headers(
+      &map(&[("accept", "application/json")]),
+      &[&map(&[("accept", "*/*"), ("user-agent", "preset")])],
+      None,
+    );
+
+    assert_eq!(names(&resolved), ["accept", "user-agent"]);
+    assert_eq!(resolved[0].1, "application/json");
+  }
+
+  #[test]
+  fn keeps_every_value_of_a_default() {
+    let resolved = resolve_headers(
+      &HeaderMap::new(),
+      &[&map(&[("x-tag", "a"), ("x-tag", "b")])],
+      None,
+    );
+
+    assert_eq!(names(&resolved), ["x-tag", "x-tag"]);
+    assert_eq!(resolved[1].1, "b");
+  }
+
+  #[test]
+  fn listed_names_come_first() {
+    let mut order = OrigHeaderMap::new();
+    order.insert("user-agent");
+    order.insert("accept");
+
+    let resolved = resolve_headers(
+      &map(&[("x-custom", "1"), ("accept", "*/*")]),
+      &[&map(&[("user-agent", "preset")])],
+      Some(&order),
+    );
+
+    assert_eq!(names(&resolved), ["user-agent", "accept", "x-custom"]);
+  }
+}
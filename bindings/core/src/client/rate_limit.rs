@@ -0,0 +1,288 @@
+//! Pacing requests with per-host token buckets.
+
+use std::{
+  collections::HashMap,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use super::retry::random_fraction;
+
+/// How often buckets that have refilled are dropped.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A token bucket refilled with `requests` tokens every `per`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+  pub requests: u32,
+  pub per: Duration,
+  /// Requests that may be sent back to back after an idle period.
+  pub burst: u32,
+}
+
+impl RateLimit {
+  /// A limit of `requests` per `per` without bursts.
+  pub fn new(requests: u32, per: Duration) -> Self {
+    Self {
+      requests,
+      per,
+      burst: 1,
+    }
+  }
+
+  fn tokens_per_second(&self) -> f64 {
+    self.requests as f64 / self.per.as_secs_f64()
+  }
+}
+
+#[derive(Debug)]
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+  /// When the bucket is back to a full burst, and so no different from a new one.
+  full_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+  by_key: HashMap<String, Bucket>,
+  next_sweep: Option<Instant>,
+}
+
+impl Buckets {
+  /// Forget buckets that have refilled, so keys seen once do not stay around forever.
+  fn sweep(&mut self, now: Instant) {
+    if self.next_sweep.is_some_and(|next| now < next) {
+      return;
+    }
+    self.by_key.retain(|_, bucket| bucket.full_at > now);
+    self.next_sweep = Some(now + SWEEP_INTERVAL);
+  }
+}
+
+/// Paces requests sharing a key, usually the URL host.
+///
+/// Each key gets its own bucket, using the override registered for that key or the default
+/// limit. Keys without any limit are only subject to the randomized delay.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+  default: Option<RateLimit>,
+  overrides: HashMap<String, RateLimit>,
+  min_delay: Duration,
+  max_delay: Duration,
+  buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+  pub fn new(default: Option<RateLimit>) -> Self {
+    Self {
+      default,
+      ..Self::default()
+    }
+  }
+
+  /// Use `limit` for `key` instead of the default limit.
+  pub fn with_override(mut self, key: impl Into<String>, limit: RateLimit) -> Self {
+    self
+      .overrides
+      .insert(key.into().to_ascii_lowercase(), limit);
+    self
+  }
+
+  /// Wait a random extra delay between `min` and `max` before every request.
+  pub fn with_delay(mut self, min: Duration, max: Duration) -> Self {
+    self.min_delay = min;
+    self.max_delay = max.max(min);
+    self
+  }
+
+  /// Wait until a request for `key` may be sent.
+  ///
+  /// A request dropped while waiting gives its token back, so aborted and timed out requests
+  /// don't hold up the ones after them.
+  pub async fn acquire(&self, key: &str) {
+    let wait = self.reserve(key, Instant::now()) + self.random_delay();
+    if !wait.is_zero() {
+      let mut reservation = Reservation {
+        limiter: self,
+        key,
+        waiting: true,
+      };
+      tokio::time::sleep(wait).await;
+      reservation.waiting = false;
+    }
+  }
+
+  /// Take a token for `key` and return how long to wait for it.
+  ///
+  /// Tokens are handed out even when the bucket is empty, so concurrent callers queue up behind
+  /// each other instead of all waking at the same instant.
+  fn reserve(&self, key: &str, now: Instant) -> Duration {
+    let key = key.to_ascii_lowercase();
+    let Some((rate, burst)) = self.bucket_size(&key) else {
+      return Duration::ZERO;
+    };
+    let mut buckets = self.buckets.lock().unwrap();
+    buckets.sweep(now);
+    let bucket = buckets.by_key.entry(key).or_insert(Bucket {
+      tokens: burst,
+      updated: now,
+      full_at: now,
+    });
+
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * rate).min(burst) - 1.0;
+    bucket.updated = now;
+    bucket.full_at = now + Duration::from_secs_f64((burst - bucket.tokens) / rate);
+
+    if bucket.tokens >= 0.0 {
+      Duration::ZERO
+    } else {
+      Duration::from_secs_f64(-bucket.tokens / rate)
+    }
+  }
+
+  /// Give back a token taken for `key` by [`reserve`](Self::reserve).
+  fn refund(&self, key: &str) {
+    let key = key.to_ascii_lowercase();
+    let Some((rate, burst)) = self.bucket_size(&key) else {
+      return;
+    };
+    if let Some(bucket) = self.buckets.lock().unwrap().by_key.get_mut(&key) {
+      bucket.tokens = (bucket.tokens + 1.0).min(burst);
+      bucket.full_at = bucket.updated + Duration::from_secs_f64((burst - bucket.tokens) / rate);
+    }
+  }
+
+  /// Tokens per second and burst of the bucket for a lowercased `key`, or `None` when requests
+  /// for it are not limited.
+  fn bucket_size(&self, key: &str) -> Option<(f64, f64)> {
+    let limit = self.overrides.get(key).or(self.default.as_ref())?;
+    let rate = limit.tokens_per_second();
+    if limit.requests == 0 || !rate.is_finite() {
+      return None;
+    }
+    Some((rate, limit.burst.max(1) as f64))
+  }
+
+  fn random_delay(&self) -> Duration {
+    self.min_delay + (self.max_delay - self.min_delay).mul_f64(random_fraction())
+  }
+}
+
+/// A token taken by a request still waiting for its turn.
+struct Reservation<'a> {
+  limiter: &'a RateLimiter,
+  key: &'a str,
+  waiting: bool,
+}
+
+impl Drop for Reservation<'_> {
+  fn drop(&mut self) {
+    if self.waiting {
+      self.limiter.refund(self.key);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn paces_requests_per_key() {
+    let limiter = RateLimiter::new(Some(RateLimit::new(2, Duration::from_secs(1))));
+    let start = Instant::now();
+
+    assert_eq!(limiter.reserve("a.test", start), Duration::ZERO);
+    assert_eq!(limiter.reserve("a.test", start), Duration::from_millis(500));
+    assert_eq!(limiter.reserve("a.test", start), Duration::from_secs(1));
+    // Other hosts have their own bucket.
+    assert_eq!(limiter.reserve("B.test", start), Duration::ZERO);
+
+    let later = start + Duration::from_secs(5);
+    assert_eq!(limiter.reserve("a.test", later), Duration::ZERO);
+  }
+
+  #[test]
+  fn allows_bursts_after_idle_periods() {
+    let limit = RateLimit {
+      burst: 3,
+      ..RateLimit::new(1, Duration::from_secs(1))
+    };
+    let limiter = RateLimiter::new(Some(limit));
+    let start = Instant::now();
+
+    for _ in 0..3 {
+      assert_eq!(limiter.reserve("a.test", start), Duration::ZERO);
+    }
+    assert_eq!(limiter.reserve("a.test", start), Duration::from_secs(1));
+  }
+
+  #[test]
+  fn overrides_take_precedence() {
+    let limiter =
+      RateLimiter::new(None).with_override("Slow.test", RateLimit::new(1, Duration::from_secs(10)));
+    let start = Instant::now();
+
+    assert_eq!(limiter.reserve("fast.test", start), Duration::ZERO);
+    assert_eq!(limiter.reserve("fast.test", start), Duration::ZERO);
+    assert_eq!(limiter.reserve("slow.test", start), Duration::ZERO);
+    assert_eq!(limiter.reserve("slow.test", start), Duration::from_secs(10));
+  }
+
+  #[test]
+  fn evicts_refilled_buckets() {
+    let limiter = RateLimiter::new(Some(RateLimit::new(1, Duration::from_secs(1))));
+    let start = Instant::now();
+
+    limiter.reserve("a.test", start);
+    let before_sweep = start + SWEEP_INTERVAL - Duration::from_secs(1);
+    limiter.reserve("b.test", before_sweep);
+    limiter.reserve("b.test", before_sweep);
+    assert_eq!(limiter.buckets.lock().unwrap().by_key.len(), 2);
+
+    // a.test refilled long ago; b.test still owes a token.
+    limiter.reserve(
+      "c.test",
+      start + SWEEP_INTERVAL + Duration::from_millis(500),
+    );
+    let buckets = limiter.buckets.lock().unwrap();
+    let mut keys = buckets
+      .by_key
+      .keys()
+      .map(String::as_str)
+      .collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, ["b.test", "c.test"]);
+  }
+
+  #[test]
+  fn refunds_requests_dropped_while_waiting() {
+    let limiter = RateLimiter::new(Some(RateLimit::new(1, Duration::from_secs(1))));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .unwrap();
+
+    runtime.block_on(async {
+      limiter.acquire("a.test").await;
+      let waited = tokio::time::timeout(Duration::from_millis(10), limiter.acquire("a.test"));
+      assert!(waited.await.is_err());
+    });
+
+    // Only the first request's token is still owed.
+    let wait = limiter.reserve("a.test", Instant::now());
+    assert!(wait > Duration::from_millis(500) && wait <= Duration::from_secs(1));
+  }
+
+  #[test]
+  fn random_delay_stays_within_bounds() {
+    let limiter =
+      RateLimiter::new(None).with_delay(Duration::from_millis(10), Duration::from_millis(20));
+    for _ in 0..100 {
+      let delay = limiter.random_delay();
+      assert!(delay >= Duration::from_millis(10) && delay <= Duration::from_millis(20));
+    }
+  }
+}
@@ -0,0 +1,290 @@
+//! Following HTTP redirects hop by hop under a configurable policy.
+
+use std::{fmt, sync::Arc};
+
+use futures_util::future::BoxFuture;
+use http::{header, HeaderMap, Method, StatusCode, Uri};
+
+use super::{
+  navigation::{resolve, same_origin},
+  send_request, Client, DEFAULT_MAX_REDIRECTS,
+};
+use crate::{
+  response::{RedirectHop, RedirectKind},
+  Error, Request, Response,
+};
+
+/// Decides each redirect hop; see [`RedirectPolicy::callback`].
+pub type RedirectCallback =
+  Arc<dyn Fn(RedirectStep) -> BoxFuture<'static, Result<RedirectAction, Error>> + Send + Sync>;
+
+/// How HTTP redirects are followed when more control than on/off is needed.
+///
+/// Hops are sent one at a time so every one of them can be checked and rewritten. Bodies are
+/// only resent for redirects that keep the method, which requires a replayable body.
+#[derive(Clone)]
+pub struct RedirectPolicy {
+  /// Stop at redirects to another scheme, host or port.
+  pub same_origin_only: bool,
+  /// Stop at redirects from `https` to `http`.
+  pub deny_downgrade: bool,
+  /// Drop `Authorization`, `Proxy-Authorization` and `Cookie` headers on cross-origin hops,
+  /// including those among the client's default headers.
+  pub strip_sensitive_headers: bool,
+  /// Switch `POST` to a body-less `GET` on 301 and 302, as browsers do. 303 always switches.
+  pub rewrite_post: bool,
+  /// Called for every hop that passed the checks above.
+  pub callback: Option<RedirectCallback>,
+}
+
+impl Default for RedirectPolicy {
+  fn default() -> Self {
+    Self {
+      same_origin_only: false,
+      deny_downgrade: false,
+      strip_sensitive_headers: true,
+      rewrite_post: true,
+      callback: None,
+    }
+  }
+}
+
+impl fmt::Debug for RedirectPolicy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("RedirectPolicy")
+      .field("same_origin_only", &self.same_origin_only)
+      .field("deny_downgrade", &self.deny_downgrade)
+      .field("strip_sensitive_headers", &self.strip_sensitive_headers)
+      .field("rewrite_post", &self.rewrite_post)
+      .field("callback", &self.callback.is_some())
+      .finish()
+  }
+}
+
+/// A redirect about to be followed.
+#[derive(Debug, Clone)]
+pub struct RedirectStep {
+  /// 1-based number of the hop.
+  pub hop: usize,
+  pub status: StatusCode,
+  pub from: Uri,
+  pub to: Uri,
+  /// Method of the next request.
+  pub method: Method,
+  /// Headers of the next request, after sensitive ones were stripped.
+  pub headers: HeaderMap,
+}
+
+/// What to do with a [`RedirectStep`].
+#[derive(Debug, Clone)]
+pub enum RedirectAction {
+  Follow,
+  /// Return the redirect response as is.
+  Stop,
+  /// Follow with the given changes; unset fields keep the step's values.
+  ///
+  /// A new URL goes through the same origin and downgrade checks as the `Location` it replaces,
+  /// and sensitive headers are stripped when it is cross-origin, including any given here. A
+  /// method that carries no body, such as `GET`, drops the body of the redirected request.
+  Modify {
+    url: Option<Uri>,
+    method: Option<Method>,
+    headers: Option<HeaderMap>,
+  },
+}
+
+/// Send `params`, following redirects one hop at a time under `policy`.
+pub(super) async fn follow_redirects(
+  client: Option<Client>,
+  mut method: Method,
+  url: &str,
+  mut params: Request,
+  policy: &RedirectPolicy,
+) -> Result<Response, Error> {
+  let max_redirects = params
+    .max_redirects
+    .take()
+    .or_else(|| client.as_ref().map(|client| client.max_redirects))
+    .unwrap_or(DEFAULT_MAX_REDIRECTS);
+  let mut url = url.to_string();
+  let mut history = Vec::new();
+
+  loop {
+    let template = params.clone_without_body();
+    let replay = params.try_clone();
+    let has_body = params.body.is_some() || params.multipart.is_some();
+
+    let response = send_request(client.clone(), method.clone(), &url, params, true).await?;
+    history.extend_from_slice(response.history());
+
+    let Some(target) = location(&response) else {
+      break finish(response, history);
+    };
+    let previous = response.uri.clone();
+    if !policy.allows(&previous, &target) {
+      break finish(response, history);
+    }
+
+    if history.len() >= max_redirects {
+      response.close();
+      return Err(Error::TooManyRedirects(max_redirects));
+    }
+
+    let status = response.status;
+    let rewrite = match status {
+      StatusCode::SEE_OTHER => method != Method::HEAD,
+      StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => {
+        policy.rewrite_post && method == Method::POST
+      }
+      _ => false,
+    };
+
+    let mut next = if rewrite {
+      method = Method::GET;
+      let mut next = template;
+      drop_body(&mut next);
+      next
+    } else if has_body {
+      match replay {
+        Some(replay) => replay,
+        None => {
+          response.close();
+          return Err(Error::NonReplayableBody);
+        }
+      }
+    } else {
+      template
+    };
+
+    // The query is already part of the URL the server redirected from.
+    next.query = None;
+
+    if policy.strip_sensitive_headers && !same_origin(&previous, &target) {
+      strip_credentials(&mut next, client.as_ref());
+    }
+
+    let mut target = target;
+    if let Some(callback) = &policy.callback {
+      let step = RedirectStep {
+        hop: history.len() + 1,
+        status,
+        from: previous,
+        to: target.clone(),
+        method: method.clone(),
+        headers: next.headers.clone().unwrap_or_default(),
+      };
+      match callback(step).await? {
+        RedirectAction::Follow => {}
+        RedirectAction::Stop => break finish(response, history),
+        RedirectAction::Modify {
+          url,
+          method: new_method,
+          headers,
+        } => {
+          if let Some(url) = url {
+            if !policy.allows(&previous, &url) {
+              break finish(response, history);
+            }
+            target = url;
+          }
+          if let Some(new_method) = new_method {
+            if matches!(new_method, Method::GET | Method::HEAD) {
+              drop_body(&mut next);
+            }
+            method = new_method;
+          }
+          if let Some(headers) = headers {
+            next.headers = Some(headers);
+          }
+          if policy.strip_sensitive_headers && !same_origin(&previous, &target) {
+            strip_credentials(&mut next, client.as_ref());
+          }
+        }
+      }
+    }
+
+    response.close();
+    history.push(RedirectHop::new(
+      RedirectKind::Http,
+      &response,
+      target.clone(),
+    ));
+    url = target.to_string();
+    params = next;
+  }
+}
+
+impl RedirectPolicy {
+  /// Whether following hops needs more than the inner client's own redirect handling, which
+  /// rewrites `POST` like browsers do and strips credentials on cross-origin hops.
+  pub(super) fn restricts(&self) -> bool {
+    self.same_origin_only || self.deny_downgrade || !self.rewrite_post || self.callback.is_some()
+  }
+
+  /// Whether a hop from `from` to `to` passes the origin and downgrade checks.
+  fn allows(&self, from: &Uri, to: &Uri) -> bool {
+    let cross_origin = !same_origin(from, to);
+    let downgrade = from.scheme_str() == Some("https") && to.scheme_str() == Some("http");
+    !(cross_origin && self.same_origin_only) && !(downgrade && self.deny_downgrade)
+  }
+}
+
+fn finish(mut response: Response, history: Vec<RedirectHop>) -> Result<Response, Error> {
+  response.set_history(history);
+  Ok(response)
+}
+
+/// The resolved `Location` of a redirect response.
+fn location(response: &Response) -> Option<Uri> {
+  if !response.status.is_redirection() {
+    return None;
+  }
+  let location = response.headers.get(header::LOCATION)?.to_str().ok()?;
+  resolve(&response.uri, location)
+}
+
+/// Turn `request` into one without a body, as for a `GET`.
+fn drop_body(request: &mut Request) {
+  request.body = None;
+  request.json = None;
+  request.form = None;
+  request.multipart = None;
+  request.trailers = None;
+  if let Some(headers) = request.headers.as_mut() {
+    headers.remove(header::CONTENT_TYPE);
+    headers.remove(header::CONTENT_LENGTH);
+  }
+}
+
+/// Drop the credentials of a request about to go to another origin.
+///
+/// Each hop is a request of its own, so the inner client would add `client`'s default headers
+/// back. Those are turned off for the request, and all but the sensitive ones are carried over
+/// on the request itself.
+pub(super) fn strip_credentials(request: &mut Request, client: Option<&Client>) {
+  let headers = request.headers.get_or_insert_with(HeaderMap::new);
+  if request.default_headers != Some(false) {
+    if let Some(client) = client {
+      let defaults = &client.default_headers;
+      for name in defaults.headers.keys() {
+        if !headers.contains_key(name) {
+          for value in defaults.headers.get_all(name) {
+            headers.append(name.clone(), value.clone());
+          }
+        }
+      }
+      if request.orig_headers.is_none() {
+        request.orig_headers = defaults.orig_headers.clone();
+      }
+    }
+    request.default_headers = Some(false);
+  }
+
+  headers.remove(header::AUTHORIZATION);
+  headers.remove(header::PROXY_AUTHORIZATION);
+  headers.remove(header::COOKIE);
+  request.auth = None;
+  request.bearer_auth = None;
+  request.basic_auth = None;
+  request.cookies = None;
+}
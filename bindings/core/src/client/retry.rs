@@ -0,0 +1,213 @@
+//! Retrying failed requests with exponential backoff.
+
+use std::{
+  collections::hash_map::RandomState,
+  hash::{BuildHasher, Hasher},
+  time::{Duration, SystemTime},
+};
+
+use http::{header::RETRY_AFTER, HeaderMap, Method, StatusCode};
+
+use crate::{Error, Response};
+
+/// When and how often a request is retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+  /// Total attempts, including the first one.
+  pub max_attempts: u32,
+  /// Retry when the connection could not be established.
+  pub retry_connect_errors: bool,
+  /// Retry when the request timed out.
+  pub retry_timeouts: bool,
+  /// Retry responses with these status codes.
+  pub statuses: Vec<StatusCode>,
+  /// Only retry idempotent methods (`GET`, `HEAD`, `OPTIONS`, `PUT`, `DELETE`, `TRACE`).
+  pub idempotent_only: bool,
+  /// Delay before the first retry; doubled (by `multiplier`) for each further one.
+  pub base_delay: Duration,
+  pub multiplier: f64,
+  pub max_delay: Duration,
+  /// Fraction of each backoff delay that is randomized, from `0.0` to `1.0`.
+  pub jitter: f64,
+  /// Wait as long as a `Retry-After` header asks, up to `max_retry_after`.
+  pub respect_retry_after: bool,
+  /// Give up instead of waiting when `Retry-After` asks for longer than this.
+  pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self {
+      max_attempts: 3,
+      retry_connect_errors: true,
+      retry_timeouts: true,
+      statuses: vec![
+        StatusCode::REQUEST_TIMEOUT,
+        StatusCode::TOO_MANY_REQUESTS,
+        StatusCode::BAD_GATEWAY,
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::GATEWAY_TIMEOUT,
+      ],
+      idempotent_only: true,
+      base_delay: Duration::from_millis(100),
+      multiplier: 2.0,
+      max_delay: Duration::from_secs(10),
+      jitter: 0.5,
+      respect_retry_after: true,
+      max_retry_after: Duration::from_secs(60),
+    }
+  }
+}
+
+/// A failed attempt that was retried.
+#[derive(Debug, Clone)]
+pub struct RetryAttempt {
+  /// 1-based attempt number.
+  pub attempt: u32,
+  /// Status of the response, when one was received.
+  pub status: Option<StatusCode>,
+  /// Description of the error, when no response was received.
+  pub error: Option<String>,
+  /// Time waited before the next attempt.
+  pub delay: Duration,
+}
+
+impl RetryPolicy {
+  /// Whether requests with `method` may be retried at all.
+  pub fn allows(&self, method: &Method) -> bool {
+    self.max_attempts > 1 && (!self.idempotent_only || is_idempotent(method))
+  }
+
+  /// The delay before retrying after `attempt` produced `result`, or `None` to stop.
+  pub(crate) fn retry_delay(
+    &self,
+    attempt: u32,
+    result: &Result<Response, Error>,
+  ) -> Option<Duration> {
+    match result {
+      Ok(response) => {
+        if !self.statuses.contains(&response.status) {
+          return None;
+        }
+        match retry_after(&response.headers).filter(|_| self.respect_retry_after) {
+          Some(delay) if delay > self.max_retry_after => None,
+          Some(delay) => Some(delay),
+          None => Some(self.backoff(attempt)),
+        }
+      }
+      Err(Error::Library(err)) => {
+        let retry = (self.retry_connect_errors && err.is_connect())
+          || (self.retry_timeouts && err.is_timeout());
+        retry.then(|| self.backoff(attempt))
+      }
+      Err(Error::Timeout(_)) if self.retry_timeouts => Some(self.backoff(attempt)),
+      Err(Error::Connect(_) | Error::ConnectionReset(_)) if self.retry_connect_errors => {
+        Some(self.backoff(attempt))
+      }
+      Err(_) => None,
+    }
+  }
+
+  /// Exponential backoff for the retry following `attempt`, with jitter applied.
+  pub fn backoff(&self, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+    let delay = self
+      .base_delay
+      .mul_f64(self.multiplier.max(1.0).powi(exponent).min(u32::MAX as f64))
+      .min(self.max_delay);
+    let jitter = self.jitter.clamp(0.0, 1.0);
+    delay.mul_f64(1.0 - jitter * random_fraction())
+  }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+  matches!(
+    *method,
+    Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+  )
+}
+
+/// Parse `Retry-After` as delay-seconds or an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+  let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+  if let Ok(seconds) = value.parse::<u64>() {
+    return Some(Duration::from_secs(seconds));
+  }
+  let date = httpdate::parse_http_date(value).ok()?;
+  Some(
+    date
+      .duration_since(SystemTime::now())
+      .unwrap_or(Duration::ZERO),
+  )
+}
+
+/// A uniformly distributed value in `[0, 1)`, good enough for jitter.
+pub(super) fn random_fraction() -> f64 {
+  let mut hasher = RandomState::new().build_hasher();
+  hasher.write_u128(
+    SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map(|elapsed| elapsed.as_nanos())
+      .unwrap_or_default(),
+  );
+  (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use http::HeaderValue;
+
+  #[test]
+  fn backoff_grows_and_is_capped() {
+    let policy = RetryPolicy {
+      jitter: 0.0,
+      max_delay: Duration::from_millis(350),
+      ..RetryPolicy::default()
+    };
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(3), Duration::from_millis(350));
+  }
+
+  #[test]
+  fn jitter_stays_within_bounds() {
+    let policy = RetryPolicy {
+      jitter: 1.0,
+      ..RetryPolicy::default()
+    };
+    for _ in 0..100 {
+      assert!(policy.backoff(1) <= Duration::from_millis(100));
+    }
+  }
+
+  #[test]
+  fn parses_retry_after() {
+    let mut headers = HeaderMap::new();
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+
+    headers.insert(
+      RETRY_AFTER,
+      HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+    assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+    assert_eq!(retry_after(&headers), None);
+  }
+
+  #[test]
+  fn only_idempotent_methods_by_default() {
+    let policy = RetryPolicy::default();
+    assert!(policy.allows(&Method::GET));
+    assert!(policy.allows(&Method::PUT));
+    assert!(!policy.allows(&Method::POST));
+
+    let policy = RetryPolicy {
+      idempotent_only: false,
+      ..RetryPolicy::default()
+    };
+    assert!(policy.allows(&Method::POST));
+  }
+}
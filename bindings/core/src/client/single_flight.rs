@@ -0,0 +1,476 @@
+//! Sharing one response between identical requests in flight at the same time.
+
+use std::{
+  collections::HashMap,
+  fmt::Write,
+  future::Future,
+  sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use http::{header, HeaderMap, Method, StatusCode, Uri, Version};
+use tokio::sync::watch;
+
+use crate::{
+  abort::{run_abortable, AbortSignal},
+  Error, RedirectHop, Request, Response,
+};
+
+/// Largest body, in bytes, a flight shares by default.
+pub const DEFAULT_MAX_SHARED_BODY: usize = 10 * 1024 * 1024;
+
+/// Coalesces identical `GET` and `HEAD` requests so that only one goes on the wire while the
+/// others wait for its response.
+///
+/// Every caller gets its own [`Response`] over the same buffered body. When the request on the
+/// wire fails, or its body is too large to buffer, each waiting caller sends its own.
+#[derive(Debug)]
+pub struct SingleFlight {
+  flights: Mutex<HashMap<String, watch::Receiver<Option<Landed>>>>,
+  max_body_size: usize,
+}
+
+/// How a flight ended: the response to share, or `None` when there is none.
+type Landed = Option<Arc<SharedResponse>>;
+
+#[derive(Debug)]
+struct SharedResponse {
+  version: Version,
+  status: StatusCode,
+  headers: HeaderMap,
+  uri: Uri,
+  history: Vec<RedirectHop>,
+  body: Bytes,
+}
+
+/// Ends a flight when its request finishes or is dropped.
+struct Landing<'a> {
+  flights: &'a SingleFlight,
+  key: &'a str,
+}
+
+impl Default for SingleFlight {
+  fn default() -> Self {
+    Self {
+      flights: Mutex::default(),
+      max_body_size: DEFAULT_MAX_SHARED_BODY,
+    }
+  }
+}
+
+impl SingleFlight {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Largest body, in bytes, to buffer and share. Larger responses, such as downloads, are
+  /// streamed to the caller that sent the request. Defaults to [`DEFAULT_MAX_SHARED_BODY`].
+  pub fn with_max_body_size(mut self, max: usize) -> Self {
+    self.max_body_size = max;
+    self
+  }
+
+  /// Number of distinct requests in flight.
+  pub fn len(&self) -> usize {
+    self.flights.lock().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Send the request under `key` with `send`, or wait for the identical one already in flight.
+  pub(crate) async fn run<F>(
+    &self,
+    key: String,
+    signal: Option<AbortSignal>,
+    send: impl FnOnce() -> F,
+  ) -> Result<Response, Error>
+  where
+    F: Future<Output = Result<Response, Error>>,
+  {
+    let joined = {
+      let mut flights = self.flights.lock().unwrap();
+      match flights.get(&key) {
+        Some(landed) => Err(landed.clone()),
+        None => {
+          let (tx, rx) = watch::channel(None);
+          flights.insert(key.clone(), rx);
+          Ok(tx)
+        }
+      }
+    };
+
+    let tx = match joined {
+      Ok(tx) => tx,
+      Err(mut landed) => {
+        let shared = run_abortable(signal.as_ref(), async {
+          // A closed channel means the request was dropped before it finished.
+          Ok(match landed.wait_for(Option::is_some).await {
+            Ok(landed) => landed.clone().flatten(),
+            Err(_) => None,
+          })
+        })
+        .await?;
+        return match shared {
+          Some(shared) => Ok(shared.to_response()),
+          None => send().await,
+        };
+      }
+    };
+
+    let landing = Landing {
+      flights: self,
+      key: &key,
+    };
+    let result = match send().await {
+      Ok(response) => match self.shared_body(&response).await {
+        Ok(body) => Ok((response, body)),
+        Err(err) => Err(err),
+      },
+      Err(err) => Err(err),
+    };
+    // Requests sent from now on start a new flight.
+    drop(landing);
+
+    match result {
+      Ok((response, None)) => {
+        let _ = tx.send(Some(None));
+        Ok(response)
+      }
+      Ok((response, Some(body))) => {
+        let _ = tx.send(Some(Some(Arc::new(SharedResponse {
+          version: response.version,
+          status: response.status,
+          headers: response.headers.clone(),
+          uri: response.uri.clone(),
+          history: response.history().to_vec(),
+          body,
+        }))));
+        Ok(response)
+      }
+      Err(err) => {
+        let _ = tx.send(Some(None));
+        Err(err)
+      }
+    }
+  }
+
+  /// The whole body of `response`, or `None` when it is larger than the flight shares. The body
+  /// stays readable either way.
+  async fn shared_body(&self, response: &Response) -> Result<Option<Bytes>, Error> {
+    let declared = response
+      .headers
+      .get(header::CONTENT_LENGTH)
+      .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > self.max_body_size as u64) {
+      return Ok(None);
+    }
+    // Reading one byte past the limit tells a body that fits from one to stream on.
+    let limit = self.max_body_size.saturating_add(1);
+    let body = response.peek(limit, |_| false).await?;
+    Ok((body.len() <= self.max_body_size).then_some(body))
+  }
+}
+
+impl Drop for Landing<'_> {
+  fn drop(&mut self) {
+    self.flights.flights.lock().unwrap().remove(self.key);
+  }
+}
+
+impl SharedResponse {
+  fn to_response(&self) -> Response {
+    let mut response = Response::buffered(
+      self.version,
+      self.status,
+      self.headers.clone(),
+      self.uri.clone(),
+      self.body.clone(),
+    );
+    response.set_history(self.history.clone());
+    response
+  }
+}
+
+/// The key identical requests share, or `None` for requests that are never coalesced: methods
+/// other than `GET` and `HEAD`, requests with a body or an informational callback, and requests
+/// that change how they are sent or how long a caller is willing to wait.
+///
+/// Every field of `params` is either part of the key or refuses coalescing, except the abort
+/// signal, which each waiting caller honors on its own.
+pub(crate) fn flight_key(method: &Method, url: &str, params: &Request) -> Option<String> {
+  let Request {
+    emulation,
+    proxy,
+    local_address,
+    interface,
+    timeout,
+    read_timeout,
+    version,
+    headers,
+    orig_headers,
+    default_headers,
+    cookies,
+    allow_redirects,
+    max_redirects,
+    follow_refresh,
+    redirect,
+    detailed_history,
+    gzip,
+    brotli,
+    deflate,
+    zstd,
+    auth,
+    bearer_auth,
+    basic_auth,
+    query,
+    form,
+    space_encoding,
+    json,
+    body,
+    trailers,
+    multipart,
+    on_informational,
+    expect_continue,
+    signal: _,
+    retry,
+    rate_limit_key,
+    priority,
+    queue_timeout,
+  } = params;
+
+  if !matches!(*method, Method::GET | Method::HEAD)
+    || form.is_some()
+    || json.is_some()
+    || body.is_some()
+    || trailers.is_some()
+    || multipart.is_some()
+    || on_informational.is_some()
+    || expect_continue.is_some()
+    || emulation.is_some()
+    || proxy.is_some()
+    || local_address.is_some()
+    || interface.is_some()
+    || version.is_some()
+    || orig_headers.is_some()
+    || redirect.is_some()
+    || timeout.is_some()
+    || read_timeout.is_some()
+    || retry.is_some()
+    || rate_limit_key.is_some()
+    || priority.is_some()
+    || queue_timeout.is_some()
+  {
+    return None;
+  }
+
+  // Every field goes in after its length and every list after its count, so text moved from one
+  // field to the next never makes two requests share a key.
+  let mut key = String::new();
+  let mut push = |field: &[u8]| {
+    let field = field.escape_ascii().to_string();
+    let _ = write!(key, "{}:{field}", field.len());
+  };
+  push(method.as_str().as_bytes());
+  push(url.as_bytes());
+
+  let query = query.as_deref().unwrap_or_default();
+  push(query.len().to_string().as_bytes());
+  for (name, value) in query {
+    push(name.as_bytes());
+    push(value.as_bytes());
+  }
+
+  // Header order does not change the response, so names are sorted.
+  let mut sent = headers
+    .iter()
+    .flatten()
+    .map(|(name, value)| (name.as_str(), value.as_bytes()))
+    .collect::<Vec<_>>();
+  sent.sort();
+  push(sent.len().to_string().as_bytes());
+  for (name, value) in sent {
+    push(name.as_bytes());
+    push(value);
+  }
+
+  let cookies = cookies.as_deref().unwrap_or_default();
+  push(cookies.len().to_string().as_bytes());
+  for cookie in cookies {
+    push(cookie.as_bytes());
+  }
+
+  let settings = format!(
+    "{auth:?} {bearer_auth:?} {basic_auth:?} {default_headers:?} {allow_redirects:?} \
+     {max_redirects:?} {follow_refresh:?} {detailed_history:?} {gzip:?} {brotli:?} {deflate:?} \
+     {zstd:?} {space_encoding:?}"
+  );
+  push(settings.as_bytes());
+  Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+  };
+
+  use futures_util::future;
+  use http::HeaderValue;
+
+  use super::*;
+
+  #[test]
+  fn keys_identical_safe_requests_alike() {
+    let url = "https://api.test/items";
+    let with_headers = |pairs: &[(&'static str, &'static str)]| Request {
+      headers: Some(
+        pairs
+          .iter()
+          .map(|(name, value)| {
+            (
+              http::HeaderName::from_static(name),
+              HeaderValue::from_static(value),
+            )
+          })
+          .collect(),
+      ),
+      ..Request::default()
+    };
+
+    let a = with_headers(&[("accept", "text/html"), ("x-tenant", "a")]);
+    let b = with_headers(&[("x-tenant", "a"), ("accept", "text/html")]);
+    let c = with_headers(&[("accept", "text/html"), ("x-tenant", "b")]);
+    assert_eq!(
+      flight_key(&Method::GET, url, &a),
+      flight_key(&Method::GET, url, &b)
+    );
+    assert_ne!(
+      flight_key(&Method::GET, url, &a),
+      flight_key(&Method::GET, url, &c)
+    );
+    assert_ne!(
+      flight_key(&Method::GET, url, &a),
+      flight_key(&Method::HEAD, url, &a)
+    );
+
+    assert!(flight_key(&Method::POST, url, &Request::default()).is_none());
+    let with_body = Request {
+      body: Some(wreq::Body::from("payload")),
+      ..Request::default()
+    };
+    assert!(flight_key(&Method::GET, url, &with_body).is_none());
+  }
+
+  #[test]
+  fn keys_cannot_be_forged_across_fields() {
+    let url = "https://api.test/items";
+    let with_query = |pairs: &[(&str, &str)]| Request {
+      query: Some(
+        pairs
+          .iter()
+          .map(|(name, value)| (name.to_string(), value.to_string()))
+          .collect(),
+      ),
+      ..Request::default()
+    };
+
+    let one = with_query(&[("a", "1\n?b=2")]);
+    let two = with_query(&[("a", "1"), ("b", "2")]);
+    assert_ne!(
+      flight_key(&Method::GET, url, &one),
+      flight_key(&Method::GET, url, &two)
+    );
+    assert_ne!(
+      flight_key(
+        &Method::GET,
+        "https://api.test/items\n?a=1",
+        &Request::default()
+      ),
+      flight_key(&Method::GET, url, &with_query(&[("a", "1")]))
+    );
+  }
+
+  #[test]
+  fn keys_requests_by_how_they_follow() {
+    let url = "https://api.test/items";
+    let plain = flight_key(&Method::GET, url, &Request::default());
+    let no_redirects = Request {
+      allow_redirects: Some(false),
+      ..Request::default()
+    };
+    let refresh = Request {
+      follow_refresh: Some(true),
+      ..Request::default()
+    };
+    assert_ne!(plain, flight_key(&Method::GET, url, &no_redirects));
+    assert_ne!(plain, flight_key(&Method::GET, url, &refresh));
+    assert_ne!(
+      flight_key(&Method::GET, url, &no_redirects),
+      flight_key(&Method::GET, url, &refresh)
+    );
+
+    let timed = Request {
+      timeout: Some(Duration::from_secs(1)),
+      ..Request::default()
+    };
+    assert!(flight_key(&Method::GET, url, &timed).is_none());
+  }
+
+  #[test]
+  fn concurrent_callers_share_one_response() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .unwrap();
+    let flights = SingleFlight::new();
+    let sent = AtomicUsize::new(0);
+    let send = || async {
+      sent.fetch_add(1, Ordering::SeqCst);
+      tokio::time::sleep(Duration::from_millis(10)).await;
+      let response = http::Response::new(wreq::Body::from("shared"));
+      Ok(Response::new(wreq::Response::from(response)))
+    };
+
+    runtime.block_on(async {
+      let (first, second) = future::join(
+        flights.run("key".to_string(), None, send),
+        flights.run("key".to_string(), None, send),
+      )
+      .await;
+      assert_eq!(first.unwrap().bytes().await.unwrap(), "shared");
+      assert_eq!(second.unwrap().bytes().await.unwrap(), "shared");
+    });
+    assert_eq!(sent.load(Ordering::SeqCst), 1);
+    assert!(flights.is_empty());
+  }
+
+  #[test]
+  fn bodies_too_large_to_share_are_sent_again() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .unwrap();
+    let flights = SingleFlight::new().with_max_body_size(4);
+    let sent = AtomicUsize::new(0);
+    let send = || async {
+      sent.fetch_add(1, Ordering::SeqCst);
+      tokio::time::sleep(Duration::from_millis(10)).await;
+      let response = http::Response::new(wreq::Body::from("too large"));
+      Ok(Response::new(wreq::Response::from(response)))
+    };
+
+    runtime.block_on(async {
+      let (first, second) = future::join(
+        flights.run("key".to_string(), None, send),
+        flights.run("key".to_string(), None, send),
+      )
+      .await;
+      assert_eq!(first.unwrap().bytes().await.unwrap(), "too large");
+      assert_eq!(second.unwrap().bytes().await.unwrap(), "too large");
+    });
+    assert_eq!(sent.load(Ordering::SeqCst), 2);
+  }
+}
@@ -0,0 +1,1011 @@
+//! Converting between curl command lines and requests.
+
+use std::{fmt, fs, path::Path, time::Duration};
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Version};
+use url::form_urlencoded;
+use wreq::{
+  header::OrigHeaderMap,
+  multipart::{Form, Part},
+  Proxy,
+};
+
+use crate::{
+  body::ProducerBody,
+  client::{PreparedBody, PreparedRequest},
+  params::append_query,
+  Error, Request,
+};
+
+/// A request described by a curl command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CurlCommand {
+  pub method: Method,
+  pub url: String,
+  /// Headers in the order given, including those from `-A` and `-e`.
+  pub headers: Vec<(String, String)>,
+  pub body: CurlBody,
+  /// `-b` cookies, as a `Cookie` header value.
+  pub cookies: Option<String>,
+  /// `-u` credentials.
+  pub user: Option<(String, Option<String>)>,
+  pub proxy: Option<String>,
+  /// `--compressed`: ask for and decode compressed responses.
+  pub compressed: bool,
+  /// `-k`: skip TLS certificate verification, which only a client can turn off.
+  pub insecure: bool,
+  /// `--http2`.
+  pub http2: bool,
+  /// `-m`.
+  pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CurlBody {
+  #[default]
+  None,
+  /// `-d` and its variants, joined with `&` as curl does.
+  Data(Bytes),
+  /// `-F` parts.
+  Form(Vec<CurlFormPart>),
+  /// A body only known while sending, rendered as `--data-binary @-`.
+  Stdin,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurlFormPart {
+  Text {
+    name: String,
+    value: String,
+  },
+  /// `name=@path`, with optional `;type=` and `;filename=`. A path of `-` stands for contents
+  /// only known while sending.
+  File {
+    name: String,
+    path: String,
+    content_type: Option<String>,
+    filename: Option<String>,
+  },
+}
+
+/// Options without a value that change nothing about the request.
+const IGNORED_FLAGS: &[&str] = &[
+  "-s",
+  "--silent",
+  "-S",
+  "--show-error",
+  "-L",
+  "--location",
+  "-v",
+  "--verbose",
+  "-i",
+  "--include",
+  "-g",
+  "--globoff",
+  "-N",
+  "--no-buffer",
+  "-f",
+  "--fail",
+  "--http1.1",
+];
+
+/// Options taking a value that change nothing about the request.
+const IGNORED_OPTIONS: &[&str] = &["-o", "--output", "-w", "--write-out"];
+
+/// Short options taking a value, which may be attached as in `-XPOST`.
+const SHORT_WITH_VALUE: &str = "XHdFbuxAemow";
+
+impl CurlCommand {
+  /// Parse a curl command line as copied from browser devtools, in bash quoting.
+  ///
+  /// Files named by `-d @path` and its variants are read here, so this blocks.
+  pub fn parse(command: &str) -> Result<Self, Error> {
+    let mut words = split_words(command)?.into_iter();
+    match words.next().as_deref() {
+      Some(program) if program == b"curl" || program.ends_with(b"/curl") => {}
+      _ => return Err(Error::Curl("command does not start with curl".to_string())),
+    }
+
+    let mut parsed = CurlCommand::default();
+    let mut method = None;
+    let mut head = false;
+    let mut get = false;
+    let mut url = None;
+    let mut data: Vec<Vec<u8>> = Vec::new();
+    let mut form = Vec::new();
+    let mut removed = Vec::new();
+
+    let mut args = expand_short_options(words).into_iter();
+    while let Some(arg) = args.next() {
+      let arg = text(arg)?;
+      // Values stay bytes, as bodies need not be UTF-8.
+      let mut value = |name: &str| {
+        args
+          .next()
+          .ok_or_else(|| Error::Curl(format!("{name} needs a value")))
+      };
+
+      match arg.as_str() {
+        "-X" | "--request" => method = Some(text(value(&arg)?)?),
+        "-H" | "--header" => {
+          let header = text(value(&arg)?)?;
+          if let Some(name) = header.strip_suffix(';') {
+            parsed
+              .headers
+              .push((name.trim().to_string(), String::new()));
+          } else if let Some((name, value)) = header.split_once(':') {
+            // `Name:` with nothing after it removes a header curl would add.
+            if value.trim().is_empty() {
+              removed.push(name.trim().to_string());
+            } else {
+              parsed
+                .headers
+                .push((name.trim().to_string(), value.trim().to_string()));
+            }
+          } else {
+            return Err(Error::Curl(format!("invalid header {header:?}")));
+          }
+        }
+        "-A" | "--user-agent" => parsed
+          .headers
+          .push(("User-Agent".to_string(), text(value(&arg)?)?)),
+        "-e" | "--referer" => parsed
+          .headers
+          .push(("Referer".to_string(), text(value(&arg)?)?)),
+        "-d" | "--data" | "--data-ascii" => {
+          let value = value(&arg)?;
+          data.push(match value.strip_prefix(b"@") {
+            Some(path) => read_data_file(path)?
+              .into_iter()
+              .filter(|byte| !matches!(byte, b'\r' | b'\n'))
+              .collect(),
+            None => value,
+          });
+        }
+        "--data-binary" => {
+          let value = value(&arg)?;
+          data.push(match value.strip_prefix(b"@") {
+            Some(path) => read_data_file(path)?,
+            None => value,
+          });
+        }
+        "--data-raw" => data.push(value(&arg)?),
+        "--data-urlencode" => data.push(url_encode_data(&value(&arg)?)?.into_bytes()),
+        "--json" => {
+          let value = value(&arg)?;
+          data.push(match value.strip_prefix(b"@") {
+            Some(path) => read_data_file(path)?,
+            None => value,
+          });
+          for (name, default) in [
+            ("Content-Type", "application/json"),
+            ("Accept", "application/json"),
+          ] {
+            if !has_header(&parsed.headers, name) {
+              parsed.headers.push((name.to_string(), default.to_string()));
+            }
+          }
+        }
+        "-F" | "--form" => form.push(parse_form_part(&text(value(&arg)?)?)?),
+        "--form-string" => {
+          let value = text(value(&arg)?)?;
+          let (name, value) = value
+            .split_once('=')
+            .ok_or_else(|| Error::Curl(format!("invalid form field {value:?}")))?;
+          form.push(CurlFormPart::Text {
+            name: name.to_string(),
+            value: value.to_string(),
+          });
+        }
+        "-b" | "--cookie" => {
+          let value = text(value(&arg)?)?;
+          if !value.contains('=') {
+            return Err(Error::Curl(format!(
+              "reading cookies from a file ({value:?}) is not supported"
+            )));
+          }
+          parsed.cookies = Some(value);
+        }
+        "-u" | "--user" => {
+          let value = text(value(&arg)?)?;
+          parsed.user = Some(match value.split_once(':') {
+            Some((user, password)) => (user.to_string(), Some(password.to_string())),
+            None => (value, None),
+          });
+        }
+        "-x" | "--proxy" => {
+          let value = text(value(&arg)?)?;
+          parsed.proxy = Some(if value.contains("://") {
+            value
+          } else {
+            format!("http://{value}")
+          });
+        }
+        "-m" | "--max-time" => {
+          let value = text(value(&arg)?)?;
+          let timeout = value
+            .parse::<f64>()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .ok_or_else(|| Error::Curl(format!("invalid --max-time {value:?}")))?;
+          // Like curl, zero means no limit.
+          parsed.timeout = (!timeout.is_zero()).then_some(timeout);
+        }
+        "--connect-timeout" => {
+          return Err(Error::Curl(
+            "--connect-timeout is a setting of the client's connections, not of a request"
+              .to_string(),
+          ))
+        }
+        "--url" => url = Some(text(value(&arg)?)?),
+        "-I" | "--head" => head = true,
+        "-G" | "--get" => get = true,
+        "--compressed" => parsed.compressed = true,
+        "-k" | "--insecure" => parsed.insecure = true,
+        "--http2" => parsed.http2 = true,
+        flag if IGNORED_FLAGS.contains(&flag) => {}
+        option if IGNORED_OPTIONS.contains(&option) => {
+          value(option)?;
+        }
+        option if option.starts_with('-') && option.len() > 1 => {
+          return Err(Error::Curl(format!("unsupported option {option}")));
+        }
+        _ if url.is_none() => url = Some(arg.clone()),
+        _ => return Err(Error::Curl(format!("unexpected argument {arg:?}"))),
+      }
+    }
+
+    let url = url.ok_or_else(|| Error::Curl("command has no URL".to_string()))?;
+    if !data.is_empty() && !form.is_empty() {
+      return Err(Error::Curl("-d and -F cannot be combined".to_string()));
+    }
+
+    let data = (!data.is_empty()).then(|| data.join(&b'&'));
+    parsed.url = match (&data, get) {
+      (Some(data), true) => append_query(
+        &url,
+        std::str::from_utf8(data)
+          .map_err(|_| Error::Curl("query data is not valid UTF-8".to_string()))?,
+      ),
+      _ => url,
+    };
+    parsed.body = match data {
+      Some(data) if !get => {
+        let content_type = "content-type";
+        if !has_header(&parsed.headers, content_type)
+          && !removed
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(content_type))
+        {
+          parsed.headers.push((
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+          ));
+        }
+        CurlBody::Data(data.into())
+      }
+      _ if !form.is_empty() => CurlBody::Form(form),
+      _ => CurlBody::None,
+    };
+
+    parsed.method = match method {
+      Some(method) => Method::from_bytes(method.as_bytes())
+        .map_err(|_| Error::Curl(format!("invalid method {method:?}")))?,
+      None if head => Method::HEAD,
+      None if matches!(parsed.body, CurlBody::None) => Method::GET,
+      None => Method::POST,
+    };
+
+    Ok(parsed)
+  }
+
+  /// Describe a prepared request; see [`PreparedRequest::headers`] for what it includes.
+  pub fn from_prepared(prepared: &PreparedRequest) -> Self {
+    let headers: Vec<(String, String)> = prepared
+      .headers()
+      .iter()
+      .map(|(name, value)| {
+        (
+          name.to_string(),
+          String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        )
+      })
+      .collect();
+    let compressed = has_header(&headers, "accept-encoding");
+
+    CurlCommand {
+      method: prepared.method().clone(),
+      url: prepared.uri().to_string(),
+      headers,
+      body: match prepared.body() {
+        PreparedBody::Empty => CurlBody::None,
+        PreparedBody::Buffered(bytes) => CurlBody::Data(bytes),
+        PreparedBody::Streaming => CurlBody::Stdin,
+      },
+      compressed,
+      ..CurlCommand::default()
+    }
+  }
+
+  /// Describe the body as `-F` parts instead, for a prepared multipart form whose parts only the
+  /// caller knows. curl picks its own boundary, so the form's `Content-Type` is dropped.
+  pub fn set_form(&mut self, parts: Vec<CurlFormPart>) {
+    self
+      .headers
+      .retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+    self.body = CurlBody::Form(parts);
+  }
+
+  /// The method, URL and request to send; `-k` has to be applied to the client separately.
+  ///
+  /// Files named by `-F name=@path` are opened here, and read while sending.
+  pub fn into_request(self) -> Result<(Method, String, Request), Error> {
+    let mut request = Request::default();
+
+    let mut headers = HeaderMap::new();
+    let mut orig_headers = OrigHeaderMap::new();
+    for (name, value) in self.headers {
+      headers.append(
+        HeaderName::from_bytes(name.as_bytes())?,
+        HeaderValue::from_str(&value)?,
+      );
+      orig_headers.insert(name);
+    }
+    if !headers.is_empty() {
+      request.headers = Some(headers);
+      request.orig_headers = Some(orig_headers);
+    }
+
+    match self.body {
+      CurlBody::None => {}
+      CurlBody::Data(data) => request.body = Some(data.into()),
+      CurlBody::Form(parts) => request.multipart = Some(build_form(parts)?),
+      CurlBody::Stdin => {
+        return Err(Error::Curl(
+          "a body read from stdin cannot be imported".to_string(),
+        ))
+      }
+    }
+
+    if let Some(cookies) = self.cookies {
+      request.cookies = Some(vec![HeaderValue::from_str(&cookies)?]);
+    }
+
+    request.basic_auth = self.user;
+
+    if let Some(proxy) = self.proxy {
+      request.proxy = Some(Proxy::all(&proxy).map_err(Error::Library)?);
+    }
+
+    if self.compressed {
+      request.gzip = Some(true);
+      request.brotli = Some(true);
+      request.deflate = Some(true);
+      request.zstd = Some(true);
+    }
+
+    if self.http2 {
+      request.version = Some(Version::HTTP_2);
+    }
+
+    request.timeout = self.timeout;
+
+    Ok((self.method, self.url, request))
+  }
+}
+
+impl fmt::Display for CurlCommand {
+  /// A bash command line that [`CurlCommand::parse`] reads back into the same command.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "curl {}", quote(self.url.as_bytes()))?;
+
+    let has_body = !matches!(self.body, CurlBody::None);
+    let implied = match (&self.method, has_body) {
+      (&Method::GET, false) | (&Method::POST, true) => true,
+      (&Method::HEAD, false) => {
+        f.write_str(" -I")?;
+        true
+      }
+      _ => false,
+    };
+    if !implied {
+      write!(f, " -X {}", quote(self.method.as_str().as_bytes()))?;
+    }
+
+    for (name, value) in &self.headers {
+      let header = if value.is_empty() {
+        format!("{name};")
+      } else {
+        format!("{name}: {value}")
+      };
+      write!(f, " -H {}", quote(header.as_bytes()))?;
+    }
+
+    match &self.body {
+      CurlBody::None => {}
+      CurlBody::Data(data) => {
+        // Otherwise curl would label the body as a form.
+        if !has_header(&self.headers, "content-type") {
+          f.write_str(" -H 'Content-Type:'")?;
+        }
+        write!(f, " --data-raw {}", quote(data))?
+      }
+      CurlBody::Form(parts) => {
+        for part in parts {
+          match part {
+            CurlFormPart::Text { name, value } => write!(
+              f,
+              " --form-string {}",
+              quote(format!("{name}={value}").as_bytes())
+            )?,
+            CurlFormPart::File {
+              name,
+              path,
+              content_type,
+              filename,
+            } => {
+              let mut spec = format!("{name}=@{path}");
+              if let Some(content_type) = content_type {
+                spec.push_str(&format!(";type={content_type}"));
+              }
+              if let Some(filename) = filename {
+                spec.push_str(&format!(";filename={filename}"));
+              }
+              write!(f, " -F {}", quote(spec.as_bytes()))?;
+            }
+          }
+        }
+      }
+      CurlBody::Stdin => f.write_str(" --data-binary @-")?,
+    }
+
+    if let Some(cookies) = &self.cookies {
+      write!(f, " -b {}", quote(cookies.as_bytes()))?;
+    }
+
+    if let Some((user, password)) = &self.user {
+      let credentials = match password {
+        Some(password) => format!("{user}:{password}"),
+        None => user.clone(),
+      };
+      write!(f, " -u {}", quote(credentials.as_bytes()))?;
+    }
+
+    if let Some(proxy) = &self.proxy {
+      write!(f, " -x {}", quote(proxy.as_bytes()))?;
+    }
+
+    if let Some(timeout) = self.timeout {
+      write!(f, " -m {}", timeout.as_secs_f64())?;
+    }
+
+    if self.compressed {
+      f.write_str(" --compressed")?;
+    }
+
+    if self.insecure {
+      f.write_str(" -k")?;
+    }
+
+    if self.http2 {
+      f.write_str(" --http2")?;
+    }
+
+    Ok(())
+  }
+}
+
+fn has_header(headers: &[(String, String)], name: &str) -> bool {
+  headers
+    .iter()
+    .any(|(header, _)| header.eq_ignore_ascii_case(name))
+}
+
+fn read_data_file(path: &[u8]) -> Result<Vec<u8>, Error> {
+  if path == b"-" {
+    return Err(Error::Curl(
+      "reading data from stdin is not supported".to_string(),
+    ));
+  }
+  Ok(fs::read(text(path.to_vec())?)?)
+}
+
+/// `--data-urlencode` forms: `content`, `=content`, `name=content`, `@file` and `name@file`.
+fn url_encode_data(value: &[u8]) -> Result<String, Error> {
+  let encode = |content: &[u8]| form_urlencoded::byte_serialize(content).collect::<String>();
+  let split = |separator: u8| {
+    let at = value.iter().position(|&byte| byte == separator)?;
+    Some((&value[..at], &value[at + 1..]))
+  };
+  let named = |name: &[u8], content: String| -> Result<String, Error> {
+    Ok(match name {
+      b"" => content,
+      name => format!("{}={content}", text(name.to_vec())?),
+    })
+  };
+  if let Some((name, content)) = split(b'=') {
+    return named(name, encode(content));
+  }
+  match split(b'@') {
+    Some((name, path)) => named(name, encode(&read_data_file(path)?)),
+    None => Ok(encode(value)),
+  }
+}
+
+/// A word that has to be text, such as an option, URL or header.
+fn text(word: Vec<u8>) -> Result<String, Error> {
+  String::from_utf8(word).map_err(|err| {
+    Error::Curl(format!(
+      "{:?} is not valid UTF-8",
+      String::from_utf8_lossy(err.as_bytes())
+    ))
+  })
+}
+
+fn parse_form_part(spec: &str) -> Result<CurlFormPart, Error> {
+  let (name, value) = spec
+    .split_once('=')
+    .ok_or_else(|| Error::Curl(format!("invalid form field {spec:?}")))?;
+
+  let Some(file) = value.strip_prefix('@') else {
+    return Ok(CurlFormPart::Text {
+      name: name.to_string(),
+      value: value.to_string(),
+    });
+  };
+
+  let mut attributes = file.split(';');
+  let path = attributes.next().unwrap_or_default().to_string();
+  let mut content_type = None;
+  let mut filename = None;
+  for attribute in attributes {
+    match attribute.split_once('=') {
+      Some(("type", value)) => content_type = Some(value.to_string()),
+      Some(("filename", value)) => filename = Some(value.to_string()),
+      _ => {
+        return Err(Error::Curl(format!(
+          "unsupported form attribute {attribute:?}"
+        )))
+      }
+    }
+  }
+
+  Ok(CurlFormPart::File {
+    name: name.to_string(),
+    path,
+    content_type,
+    filename,
+  })
+}
+
+fn build_form(parts: Vec<CurlFormPart>) -> Result<Form, Error> {
+  parts.into_iter().try_fold(Form::new(), |form, part| {
+    Ok(match part {
+      CurlFormPart::Text { name, value } => form.text(name, value),
+      CurlFormPart::File {
+        name,
+        path,
+        content_type,
+        filename,
+      } => {
+        if path == "-" {
+          return Err(Error::Curl(
+            "reading a form part from stdin is not supported".to_string(),
+          ));
+        }
+        let size = fs::metadata(&path)?.len();
+        let default_filename = Path::new(&path)
+          .file_name()
+          .map(|name| name.to_string_lossy().into_owned());
+        let body = wreq::Body::wrap(ProducerBody::file(path, Some(size)));
+        let mut part = Part::stream_with_length(body, size);
+        if let Some(filename) = filename.or(default_filename) {
+          part = part.file_name(filename);
+        }
+        if let Some(content_type) = content_type {
+          part = part.mime_str(&content_type).map_err(Error::Library)?;
+        }
+        form.part(name, part)
+      }
+    })
+  })
+}
+
+/// Expand grouped short flags (`-sSL`) and attached values (`-XPOST`) into separate words.
+fn expand_short_options(words: impl Iterator<Item = Vec<u8>>) -> Vec<Vec<u8>> {
+  let short_with_value = |flag: &u8| SHORT_WITH_VALUE.as_bytes().contains(flag);
+  let mut expanded = Vec::new();
+  let mut words = words.peekable();
+  while let Some(word) = words.next() {
+    let is_short_group = word.len() > 2 && word.starts_with(b"-") && !word.starts_with(b"--");
+    if !is_short_group {
+      let takes_value = word.len() == 2 && word[0] == b'-' && short_with_value(&word[1])
+        || (word.starts_with(b"--") && !is_flag(&word));
+      expanded.push(word);
+      // Values are taken as they are, even when they start with a dash.
+      if takes_value {
+        if let Some(value) = words.next() {
+          expanded.push(value);
+        }
+      }
+      continue;
+    }
+
+    for (index, &flag) in word.iter().enumerate().skip(1) {
+      if short_with_value(&flag) {
+        expanded.push(vec![b'-', flag]);
+        let rest = &word[index + 1..];
+        if rest.is_empty() {
+          if let Some(value) = words.next() {
+            expanded.push(value);
+          }
+        } else {
+          expanded.push(rest.to_vec());
+        }
+        break;
+      }
+      expanded.push(vec![b'-', flag]);
+    }
+  }
+  expanded
+}
+
+/// Long options that take no value.
+fn is_flag(option: &[u8]) -> bool {
+  IGNORED_FLAGS.iter().any(|flag| flag.as_bytes() == option)
+    || matches!(
+      option,
+      b"--head" | b"--get" | b"--compressed" | b"--insecure" | b"--http2"
+    )
+}
+
+/// Split a bash command line into words, handling quotes, `$'…'` strings and line
+/// continuations. Words are bytes, as `$'…'` escapes can spell out any byte.
+fn split_words(command: &str) -> Result<Vec<Vec<u8>>, Error> {
+  let mut words = Vec::new();
+  let mut word = Vec::new();
+  let mut in_word = false;
+  let mut chars = command.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      c if c.is_whitespace() => {
+        if in_word {
+          words.push(std::mem::take(&mut word));
+          in_word = false;
+        }
+      }
+      '\\' => match chars.next() {
+        Some('\n') => {}
+        Some('\r') if chars.peek() == Some(&'\n') => {
+          chars.next();
+        }
+        Some(escaped) => {
+          push_char(&mut word, escaped);
+          in_word = true;
+        }
+        None => {}
+      },
+      '\'' => {
+        in_word = true;
+        loop {
+          match chars.next() {
+            Some('\'') => break,
+            Some(c) => push_char(&mut word, c),
+            None => return Err(unterminated()),
+          }
+        }
+      }
+      '"' => {
+        in_word = true;
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+              Some(escaped @ ('$' | '`' | '"' | '\\')) => word.push(escaped as u8),
+              Some('\n') => {}
+              Some(other) => {
+                word.push(b'\\');
+                push_char(&mut word, other);
+              }
+              None => return Err(unterminated()),
+            },
+            Some(c) => push_char(&mut word, c),
+            None => return Err(unterminated()),
+          }
+        }
+      }
+      '$' if chars.peek() == Some(&'\'') => {
+        chars.next();
+        in_word = true;
+        read_ansi_c(&mut chars, &mut word)?;
+      }
+      c => {
+        push_char(&mut word, c);
+        in_word = true;
+      }
+    }
+  }
+
+  if in_word {
+    words.push(word);
+  }
+  Ok(words)
+}
+
+fn push_char(word: &mut Vec<u8>, c: char) {
+  word.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+fn unterminated() -> Error {
+  Error::Curl("unterminated quote".to_string())
+}
+
+/// Read the rest of a `$'…'` string, decoding its backslash escapes.
+fn read_ansi_c(
+  chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+  word: &mut Vec<u8>,
+) -> Result<(), Error> {
+  let hex = |chars: &mut std::iter::Peekable<std::str::Chars<'_>>, max: usize| {
+    let mut value = 0u32;
+    let mut digits = 0;
+    while digits < max {
+      match chars.peek().and_then(|c| c.to_digit(16)) {
+        Some(digit) => {
+          value = value * 16 + digit;
+          chars.next();
+          digits += 1;
+        }
+        None => break,
+      }
+    }
+    (digits > 0).then_some(value)
+  };
+
+  loop {
+    match chars.next() {
+      Some('\'') => break,
+      Some('\\') => match chars.next() {
+        Some('n') => word.push(b'\n'),
+        Some('r') => word.push(b'\r'),
+        Some('t') => word.push(b'\t'),
+        Some('0') => word.push(0),
+        Some('x') => match hex(chars, 2) {
+          Some(value) => word.push(value as u8),
+          None => word.extend_from_slice(b"\\x"),
+        },
+        Some(kind @ ('u' | 'U')) => {
+          let max = if kind == 'u' { 4 } else { 8 };
+          match hex(chars, max).and_then(char::from_u32) {
+            Some(c) => push_char(word, c),
+            None => return Err(Error::Curl("invalid unicode escape".to_string())),
+          }
+        }
+        Some(escaped @ ('\\' | '\'' | '"' | '?')) => word.push(escaped as u8),
+        Some(other) => {
+          word.push(b'\\');
+          push_char(word, other);
+        }
+        None => return Err(unterminated()),
+      },
+      Some(c) => push_char(word, c),
+      None => return Err(unterminated()),
+    }
+  }
+
+  Ok(())
+}
+
+/// Quote a word for bash: plain single quotes when possible, `$'…'` otherwise.
+fn quote(bytes: &[u8]) -> String {
+  if let Ok(text) = std::str::from_utf8(bytes) {
+    if !text.contains('\'') && !text.chars().any(char::is_control) {
+      return format!("'{text}'");
+    }
+  }
+
+  let mut quoted = String::from("$'");
+  for chunk in bytes.utf8_chunks() {
+    for c in chunk.valid().chars() {
+      match c {
+        '\\' => quoted.push_str("\\\\"),
+        '\'' => quoted.push_str("\\'"),
+        '\n' => quoted.push_str("\\n"),
+        '\r' => quoted.push_str("\\r"),
+        '\t' => quoted.push_str("\\t"),
+        c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+        c => quoted.push(c),
+      }
+    }
+    for byte in chunk.invalid() {
+      quoted.push_str(&format!("\\x{byte:02x}"));
+    }
+  }
+  quoted.push('\'');
+  quoted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_a_devtools_command() {
+    let command = CurlCommand::parse(
+      "curl 'https://example.com/api?x=1' \\\n  -H 'accept: application/json' \\\n  -H $'x-note: it\\'s' \\\n  -b 'session=abc; theme=dark' \\\n  --data-raw '{\"a\":1}' \\\n  --compressed",
+    )
+    .unwrap();
+
+    assert_eq!(command.method, Method::POST);
+    assert_eq!(command.url, "https://example.com/api?x=1");
+    assert_eq!(
+      command.headers,
+      [
+        ("accept".to_string(), "application/json".to_string()),
+        ("x-note".to_string(), "it's".to_string()),
+        (
+          "Content-Type".to_string(),
+          "application/x-www-form-urlencoded".to_string()
+        ),
+      ]
+    );
+    assert_eq!(command.cookies.as_deref(), Some("session=abc; theme=dark"));
+    assert_eq!(
+      command.body,
+      CurlBody::Data(Bytes::from_static(b"{\"a\":1}"))
+    );
+    assert!(command.compressed);
+  }
+
+  #[test]
+  fn parses_grouped_and_attached_short_options() {
+    let command = CurlCommand::parse(
+      "curl -sSLk -XPUT -uuser:pass -x proxy.test:8080 --http2 https://example.com",
+    )
+    .unwrap();
+
+    assert_eq!(command.method, Method::PUT);
+    assert!(command.insecure);
+    assert!(command.http2);
+    assert_eq!(
+      command.user,
+      Some(("user".to_string(), Some("pass".to_string())))
+    );
+    assert_eq!(command.proxy.as_deref(), Some("http://proxy.test:8080"));
+  }
+
+  #[test]
+  fn joins_data_and_moves_it_to_the_query_with_get() {
+    let command =
+      CurlCommand::parse("curl -G https://example.com -d a=1 --data-urlencode 'q=a b'").unwrap();
+
+    assert_eq!(command.method, Method::GET);
+    assert_eq!(command.url, "https://example.com?a=1&q=a+b");
+    assert_eq!(command.body, CurlBody::None);
+  }
+
+  #[test]
+  fn parses_form_parts() {
+    let command = CurlCommand::parse(
+      "curl https://example.com -F name=value -F 'file=@/tmp/a.txt;type=text/plain'",
+    )
+    .unwrap();
+
+    assert_eq!(
+      command.body,
+      CurlBody::Form(vec![
+        CurlFormPart::Text {
+          name: "name".to_string(),
+          value: "value".to_string(),
+        },
+        CurlFormPart::File {
+          name: "file".to_string(),
+          path: "/tmp/a.txt".to_string(),
+          content_type: Some("text/plain".to_string()),
+          filename: None,
+        },
+      ])
+    );
+  }
+
+  #[test]
+  fn rejects_out_of_range_timeouts() {
+    for timeout in ["-1", "nan", "1e30"] {
+      assert!(matches!(
+        CurlCommand::parse(&format!("curl -m {timeout} https://example.com")),
+        Err(Error::Curl(_))
+      ));
+    }
+  }
+
+  #[test]
+  fn zero_max_time_means_no_limit() {
+    let parsed = CurlCommand::parse("curl -m 0 https://example.com").unwrap();
+    assert_eq!(parsed.timeout, None);
+  }
+
+  #[test]
+  fn renders_forms_as_parts() {
+    let mut command = CurlCommand {
+      method: Method::POST,
+      url: "https://example.com".to_string(),
+      headers: vec![(
+        "content-type".to_string(),
+        "multipart/form-data; boundary=x".to_string(),
+      )],
+      body: CurlBody::Stdin,
+      ..CurlCommand::default()
+    };
+    command.set_form(vec![
+      CurlFormPart::Text {
+        name: "name".to_string(),
+        value: "value".to_string(),
+      },
+      CurlFormPart::File {
+        name: "upload".to_string(),
+        path: "-".to_string(),
+        content_type: None,
+        filename: Some("a.bin".to_string()),
+      },
+    ]);
+
+    let rendered = command.to_string();
+    assert_eq!(
+      rendered,
+      "curl 'https://example.com' --form-string 'name=value' -F 'upload=@-;filename=a.bin'"
+    );
+    assert_eq!(CurlCommand::parse(&rendered).unwrap(), command);
+  }
+
+  #[test]
+  fn rejects_unknown_options() {
+    assert!(matches!(
+      CurlCommand::parse("curl --trace out.txt https://example.com"),
+      Err(Error::Curl(_))
+    ));
+    assert!(matches!(
+      CurlCommand::parse("curl --connect-timeout 5 https://example.com"),
+      Err(Error::Curl(_))
+    ));
+    assert!(CurlCommand::parse("wget https://example.com").is_err());
+  }
+
+  #[test]
+  fn renders_what_it_parses() {
+    let command = CurlCommand {
+      method: Method::PATCH,
+      url: "https://example.com/a b".to_string(),
+      headers: vec![
+        ("x-quote".to_string(), "it's".to_string()),
+        ("x-empty".to_string(), String::new()),
+      ],
+      body: CurlBody::Data(Bytes::from_static(b"line\nnext")),
+      cookies: Some("a=1".to_string()),
+      user: Some(("user".to_string(), None)),
+      proxy: Some("http://proxy.test".to_string()),
+      compressed: true,
+      insecure: true,
+      http2: true,
+      timeout: Some(Duration::from_millis(1500)),
+    };
+
+    let rendered = command.to_string();
+    assert!(rendered.starts_with("curl 'https://example.com/a b' -X 'PATCH'"));
+    assert_eq!(CurlCommand::parse(&rendered).unwrap(), command);
+  }
+
+  #[test]
+  fn renders_binary_bodies_that_parse_back() {
+    let command = CurlCommand {
+      method: Method::POST,
+      url: "https://example.com".to_string(),
+      body: CurlBody::Data(Bytes::from_static(b"\x00\xff\xfe'\xc3\xa9\n")),
+      ..CurlCommand::default()
+    };
+
+    let rendered = command.to_string();
+    assert!(rendered.contains(r"\xff\xfe"));
+    assert_eq!(CurlCommand::parse(&rendered).unwrap(), command);
+  }
+}
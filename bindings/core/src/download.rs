@@ -0,0 +1,372 @@
+//! Streaming response bodies to files, resuming interrupted downloads.
+
+use std::{
+  ffi::OsString,
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use tokio::{
+  fs::{self, File, OpenOptions},
+  io::AsyncWriteExt,
+};
+use wreq::Method;
+
+use crate::{execute_request, Client, Error, Request, Response};
+
+/// Least time between two progress updates. The update for the last byte is always sent.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bytes of a body written so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+  /// Bytes in the file, including those kept from an earlier, interrupted download.
+  pub downloaded: u64,
+  /// Size of the whole file, when the server announced it.
+  pub total: Option<u64>,
+}
+
+/// Called as chunks are written to disk, at most once per [`PROGRESS_INTERVAL`] and once the
+/// whole body is written.
+pub type ProgressCallback = Arc<dyn Fn(DownloadProgress) + Send + Sync>;
+
+/// A file written by [`download`].
+#[derive(Debug)]
+pub struct Download {
+  /// The response the file was written from; its body has been consumed.
+  pub response: Response,
+  pub path: PathBuf,
+  /// Size of the file in bytes.
+  pub size: u64,
+  /// Whether the download continued a partial file instead of starting over.
+  pub resumed: bool,
+}
+
+impl Response {
+  /// Stream the body to `path`, replacing the file only once the whole body is written.
+  ///
+  /// The body is written to `<path>.part` first and checked against the announced length. Returns
+  /// the number of bytes written.
+  pub async fn save_to(
+    &self,
+    path: impl AsRef<Path>,
+    progress: Option<ProgressCallback>,
+  ) -> Result<u64, Error> {
+    let path = path.as_ref();
+    let part = suffixed(path, ".part");
+    let total = expected_length(self);
+    let result = async {
+      let mut file = File::create(&part).await?;
+      let size = write_body(self, &mut file, 0, total, progress.as_ref()).await?;
+      verify_length(size, total)?;
+      fs::rename(&part, path).await?;
+      Ok(size)
+    }
+    .await;
+    if result.is_err() {
+      let _ = fs::remove_file(&part).await;
+    }
+    result
+  }
+}
+
+/// Download `url` to `path`, continuing an earlier download that was interrupted.
+///
+/// The body is written to `<path>.part` and moved to `path` once its length checks out. When a
+/// download fails part way, the partial file stays behind along with the response's `ETag` or
+/// `Last-Modified`, and the next download of the same path asks for the rest with `Range` and
+/// `If-Range`. Servers that ignore the range, or whose file changed, send it whole.
+///
+/// Once the server accepts the request, and before any of the body is written:
+///
+/// - the validator is saved to `<path>.part.validator`, or an earlier one is deleted when the
+///   response has none, so a `200` without a strong `ETag` or `Last-Modified` can't be resumed;
+/// - `<path>.part` is created, or truncated when a whole body replaces an earlier partial file.
+///   It is only appended to when the server answers `206` at the file's current length.
+///
+/// The partial file becomes `path`, and the validator is deleted, once the download completes.
+pub async fn download(
+  client: Option<Client>,
+  url: &str,
+  path: impl Into<PathBuf>,
+  mut params: Request,
+  progress: Option<ProgressCallback>,
+) -> Result<Download, Error> {
+  let path = path.into();
+  let part = suffixed(&path, ".part");
+  let validator_path = suffixed(&path, ".part.validator");
+
+  // Ranges count encoded bytes, so ask for the body as stored.
+  let headers = params.headers.get_or_insert_with(HeaderMap::new);
+  headers
+    .entry(header::ACCEPT_ENCODING)
+    .or_insert(HeaderValue::from_static("identity"));
+
+  let mut resume = partial_download(&part, &validator_path).await;
+  let mut restart = None;
+  if let Some((offset, validator)) = &resume {
+    restart = params.try_clone();
+    let headers = params.headers.get_or_insert_with(HeaderMap::new);
+    headers.insert(
+      header::RANGE,
+      HeaderValue::try_from(format!("bytes={offset}-"))?,
+    );
+    headers.insert(header::IF_RANGE, validator.clone());
+  }
+
+  let mut response = execute_request(client.clone(), Method::GET, url, params).await?;
+  if response.status == StatusCode::RANGE_NOT_SATISFIABLE {
+    if let Some(restart) = restart {
+      // The partial file is longer than the server's; start over.
+      response.close();
+      resume = None;
+      response = execute_request(client, Method::GET, url, restart).await?;
+    }
+  }
+  if !response.status.is_success() {
+    response.close();
+    return Err(Error::Download(format!(
+      "server answered {} for {url}",
+      response.status
+    )));
+  }
+
+  let offset = match (response.status, resume) {
+    (StatusCode::PARTIAL_CONTENT, Some((offset, _)))
+      if content_range(&response.headers).is_some_and(|(start, _)| start == offset) =>
+    {
+      offset
+    }
+    (StatusCode::PARTIAL_CONTENT, _) => {
+      response.close();
+      return Err(Error::Download(format!(
+        "server sent an unexpected range for {url}"
+      )));
+    }
+    _ => 0,
+  };
+
+  // Keep what identifies this version of the file, so an interrupted download can continue.
+  match strong_validator(&response.headers) {
+    Some(validator) => fs::write(&validator_path, validator.as_bytes()).await?,
+    None => {
+      let _ = fs::remove_file(&validator_path).await;
+    }
+  }
+
+  let total = expected_length(&response);
+  let mut file = if offset > 0 {
+    OpenOptions::new().append(true).open(&part).await?
+  } else {
+    File::create(&part).await?
+  };
+  let size = write_body(&response, &mut file, offset, total, progress.as_ref()).await?;
+  drop(file);
+  verify_length(size, total)?;
+  fs::rename(&part, &path).await?;
+  let _ = fs::remove_file(&validator_path).await;
+
+  Ok(Download {
+    response,
+    path,
+    size,
+    resumed: offset > 0,
+  })
+}
+
+/// Stream the body into `file`, which already holds `written` bytes.
+async fn write_body(
+  response: &Response,
+  file: &mut File,
+  mut written: u64,
+  total: Option<u64>,
+  progress: Option<&ProgressCallback>,
+) -> Result<u64, Error> {
+  let mut body = response.response_for_stream().await?;
+  let mut reported: Option<(Instant, u64)> = None;
+  while let Some(chunk) = body.chunk().await.map_err(Error::Library)? {
+    file.write_all(&chunk).await?;
+    written += chunk.len() as u64;
+    if let Some(progress) = progress {
+      if reported.is_none_or(|(at, _)| at.elapsed() >= PROGRESS_INTERVAL) {
+        progress(DownloadProgress {
+          downloaded: written,
+          total,
+        });
+        reported = Some((Instant::now(), written));
+      }
+    }
+  }
+  file.sync_all().await?;
+  if let Some(progress) = progress {
+    if reported.is_none_or(|(_, downloaded)| downloaded != written) {
+      progress(DownloadProgress {
+        downloaded: written,
+        total,
+      });
+    }
+  }
+  Ok(written)
+}
+
+fn verify_length(size: u64, total: Option<u64>) -> Result<(), Error> {
+  match total {
+    Some(total) if total != size => Err(Error::Download(format!(
+      "expected {total} bytes but received {size}"
+    ))),
+    _ => Ok(()),
+  }
+}
+
+/// Length of a partial file that can be resumed, and the validator it was downloaded under.
+async fn partial_download(part: &Path, validator_path: &Path) -> Option<(u64, HeaderValue)> {
+  let length = fs::metadata(part).await.ok()?.len();
+  let validator = fs::read(validator_path).await.ok()?;
+  let validator = HeaderValue::from_bytes(&validator).ok()?;
+  (length > 0).then_some((length, validator))
+}
+
+/// A validator `If-Range` accepts: a strong `ETag`, or else `Last-Modified`.
+fn strong_validator(headers: &HeaderMap) -> Option<&HeaderValue> {
+  headers
+    .get(header::ETAG)
+    .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+    .or_else(|| headers.get(header::LAST_MODIFIED))
+}
+
+/// Size of the whole file, from `Content-Range` or `Content-Length`. Unknown for encoded bodies,
+/// whose length on the wire differs.
+fn expected_length(response: &Response) -> Option<u64> {
+  if response.status == StatusCode::PARTIAL_CONTENT {
+    return content_range(&response.headers).and_then(|(_, total)| total);
+  }
+  let encoded = response
+    .headers
+    .get(header::CONTENT_ENCODING)
+    .is_some_and(|encoding| !encoding.as_bytes().eq_ignore_ascii_case(b"identity"));
+  if encoded {
+    return None;
+  }
+  response
+    .headers
+    .get(header::CONTENT_LENGTH)
+    .and_then(|length| length.to_str().ok()?.parse().ok())
+}
+
+/// First byte and complete length of a `Content-Range: bytes <first>-<last>/<length>`.
+fn content_range(headers: &HeaderMap) -> Option<(u64, Option<u64>)> {
+  let range = headers.get(header::CONTENT_RANGE)?.to_str().ok()?;
+  let (range, total) = range.trim().strip_prefix("bytes ")?.split_once('/')?;
+  let (first, _) = range.split_once('-')?;
+  let total = match total {
+    "*" => None,
+    total => Some(total.parse().ok()?),
+  };
+  Some((first.parse().ok()?, total))
+}
+
+/// `path` with `suffix` appended to its file name.
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+  let mut name = OsString::from(path.as_os_str());
+  name.push(suffix);
+  PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::client::{ClientBuilder, MockMatcher, MockReply, MockRoute, MockTransport};
+
+  #[test]
+  fn parses_content_range() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      header::CONTENT_RANGE,
+      HeaderValue::from_static("bytes 100-199/1000"),
+    );
+    assert_eq!(content_range(&headers), Some((100, Some(1000))));
+    headers.insert(
+      header::CONTENT_RANGE,
+      HeaderValue::from_static("bytes 0-9/*"),
+    );
+    assert_eq!(content_range(&headers), Some((0, None)));
+    headers.insert(
+      header::CONTENT_RANGE,
+      HeaderValue::from_static("bytes */1000"),
+    );
+    assert_eq!(content_range(&headers), None);
+  }
+
+  #[test]
+  fn resumes_partial_file() {
+    let dir = std::env::temp_dir().join(format!("nitai-download-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("file.bin");
+    std::fs::write(suffixed(&path, ".part"), "abc").unwrap();
+    std::fs::write(suffixed(&path, ".part.validator"), "\"v1\"").unwrap();
+
+    let mock = Arc::new(MockTransport::new());
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      header::CONTENT_RANGE,
+      HeaderValue::from_static("bytes 3-5/6"),
+    );
+    headers.insert(header::ETAG, HeaderValue::from_static("\"v1\""));
+    mock.add(MockRoute {
+      matcher: MockMatcher {
+        headers: vec![
+          (header::RANGE, HeaderValue::from_static("bytes=3-")),
+          (header::IF_RANGE, HeaderValue::from_static("\"v1\"")),
+        ],
+        ..MockMatcher::default()
+      },
+      reply: MockReply {
+        status: StatusCode::PARTIAL_CONTENT,
+        headers,
+        body: "def".into(),
+        ..MockReply::default()
+      },
+      times: None,
+    });
+    let client = ClientBuilder {
+      mock: Some(mock),
+      ..ClientBuilder::default()
+    }
+    .build()
+    .unwrap();
+
+    let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let updates = reported.clone();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .unwrap();
+    let download = runtime
+      .block_on(download(
+        Some(client),
+        "https://files.test/file.bin",
+        &path,
+        Request::default(),
+        Some(Arc::new(move |progress| {
+          updates.lock().unwrap().push(progress)
+        })),
+      ))
+      .unwrap();
+
+    assert!(download.resumed);
+    assert_eq!(
+      *reported.lock().unwrap(),
+      [DownloadProgress {
+        downloaded: 6,
+        total: Some(6),
+      }]
+    );
+    assert_eq!(download.size, 6);
+    assert_eq!(std::fs::read(&path).unwrap(), b"abcdef");
+    assert!(!suffixed(&path, ".part").exists());
+    assert!(!suffixed(&path, ".part.validator").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -10,9 +10,22 @@ pub enum Error {
   StopIteration,
   StopAsyncIteration,
   WebSocketDisconnected,
+  TooManyRedirects(usize),
+  NonReplayableBody,
+  Aborted(String),
+  QueueTimeout(std::time::Duration),
+  CircuitOpen(String),
+  Callback(String),
+  Curl(String),
+  Cassette(String),
+  Mock(String),
+  Download(String),
+  Unsupported(String),
   InvalidHeaderName(header::InvalidHeaderName),
   InvalidHeaderValue(header::InvalidHeaderValue),
   Timeout(tokio::time::error::Elapsed),
+  Connect(std::io::Error),
+  ConnectionReset(std::io::Error),
   Builder(http::Error),
   IO(std::io::Error),
   Decode(ParseError),
@@ -26,9 +39,22 @@ impl fmt::Display for Error {
       Error::StopIteration => write!(f, "iterator exhausted"),
       Error::StopAsyncIteration => write!(f, "async iterator exhausted"),
       Error::WebSocketDisconnected => write!(f, "websocket disconnected"),
+      Error::TooManyRedirects(max) => write!(f, "too many redirects (limit {max})"),
+      Error::NonReplayableBody => write!(f, "request body cannot be replayed"),
+      Error::Aborted(reason) => write!(f, "request aborted: {reason}"),
+      Error::QueueTimeout(waited) => write!(f, "no request slot freed up within {waited:?}"),
+      Error::CircuitOpen(host) => write!(f, "circuit breaker open for {host}"),
+      Error::Callback(message) => write!(f, "callback error: {message}"),
+      Error::Curl(message) => write!(f, "invalid curl command: {message}"),
+      Error::Cassette(message) => write!(f, "cassette error: {message}"),
+      Error::Mock(message) => write!(f, "mock error: {message}"),
+      Error::Download(message) => write!(f, "download error: {message}"),
+      Error::Unsupported(message) => write!(f, "unsupported: {message}"),
       Error::InvalidHeaderName(err) => write!(f, "invalid header name: {err:?}"),
       Error::InvalidHeaderValue(err) => write!(f, "invalid header value: {err:?}"),
       Error::Timeout(err) => write!(f, "timeout: {err:?}"),
+      Error::Connect(err) => write!(f, "connection failed: {err}"),
+      Error::ConnectionReset(err) => write!(f, "connection reset: {err}"),
       Error::Builder(err) => write!(f, "builder error: {err:?}"),
       Error::IO(err) => write!(f, "io error: {err}"),
       Error::Decode(err) => write!(f, "decode error: {err}"),
@@ -1,14 +1,33 @@
+pub mod abort;
+pub mod batch;
+pub mod body;
 pub mod client;
+pub mod curl;
+pub mod download;
 pub mod error;
+pub mod link;
+pub mod pagination;
+pub mod params;
 pub mod request;
 pub mod response;
 pub mod websocket;
 
+pub use abort::{AbortHandle, AbortSignal};
+pub use batch::{Batch, BatchRequest, BatchResult};
 pub use client::{
-  execute_request, execute_websocket_request, Client, ClientBuilder, HickoryDnsResolver,
-  TlsVerification,
+  execute_request, execute_websocket_request, CacheStatus, Cassette, CassetteMode, CircuitBreaker,
+  CircuitBreakerPolicy, CircuitState, CircuitStatus, Client, ClientBuilder, ConcurrencyLimiter,
+  ConcurrencyStats, HarRecorder, HickoryDnsResolver, HookAction, HookRequest, HttpCache,
+  InformationalCallback, InformationalResponse, Middleware, MockTransport, PreparedBody,
+  PreparedRequest, RateLimit, RateLimiter, RedirectAction, RedirectPolicy, RedirectStep,
+  RetryAttempt, RetryPolicy, SingleFlight, TlsVerification,
 };
+pub use curl::{CurlBody, CurlCommand, CurlFormPart};
+pub use download::{download, Download, DownloadProgress, ProgressCallback, PROGRESS_INTERVAL};
 pub use error::Error;
+pub use link::{parse_link_header, Link};
+pub use pagination::{NextPage, Paginator};
+pub use params::{encode_params, ArrayFormat, ObjectFormat, ParamsEncoding, SpaceEncoding};
 pub use request::{Request, WebSocketRequest};
-pub use response::{Response, ResponseBody};
+pub use response::{RedirectHop, RedirectKind, Response, ResponseBody};
 pub use websocket::{Message, WebSocket};
@@ -0,0 +1,180 @@
+//! RFC 8288 `Link` header parsing.
+
+/// A single target from a `Link` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+  /// Target URI, resolved against the response URI when parsed through [`crate::Response::links`].
+  pub uri: String,
+  /// Lowercased relation types, e.g. `["next"]` or `["prev", "first"]`.
+  pub rel: Vec<String>,
+  /// Remaining target attributes in header order, with lowercased names.
+  pub params: Vec<(String, String)>,
+}
+
+impl Link {
+  /// Whether this link carries the given relation type (case-insensitive).
+  pub fn has_rel(&self, rel: &str) -> bool {
+    self.rel.iter().any(|value| value.eq_ignore_ascii_case(rel))
+  }
+}
+
+/// Parse a `Link` header value into its individual links.
+///
+/// Malformed entries are skipped rather than failing the whole header.
+pub fn parse_link_header(value: &str) -> Vec<Link> {
+  let bytes = value.as_bytes();
+  let len = bytes.len();
+  let mut links = Vec::new();
+  let mut i = 0;
+
+  while i < len {
+    while i < len && (bytes[i].is_ascii_whitespace() || bytes[i] == b',') {
+      i += 1;
+    }
+    if i >= len {
+      break;
+    }
+
+    if bytes[i] != b'<' {
+      while i < len && bytes[i] != b',' {
+        i += 1;
+      }
+      continue;
+    }
+
+    let start = i + 1;
+    let Some(end) = value[start..].find('>') else {
+      break;
+    };
+    i = start + end + 1;
+
+    let mut link = Link {
+      uri: value[start..start + end].trim().to_string(),
+      rel: Vec::new(),
+      params: Vec::new(),
+    };
+
+    loop {
+      while i < len && bytes[i].is_ascii_whitespace() {
+        i += 1;
+      }
+      if i >= len || bytes[i] == b',' {
+        break;
+      }
+      if bytes[i] != b';' {
+        while i < len && bytes[i] != b',' {
+          i += 1;
+        }
+        break;
+      }
+      i += 1;
+
+      while i < len && bytes[i].is_ascii_whitespace() {
+        i += 1;
+      }
+      let name_start = i;
+      while i < len && !matches!(bytes[i], b'=' | b';' | b',') && !bytes[i].is_ascii_whitespace() {
+        i += 1;
+      }
+      let name = value[name_start..i].to_ascii_lowercase();
+
+      while i < len && bytes[i].is_ascii_whitespace() {
+        i += 1;
+      }
+
+      let mut param = String::new();
+      if i < len && bytes[i] == b'=' {
+        i += 1;
+        while i < len && bytes[i].is_ascii_whitespace() {
+          i += 1;
+        }
+
+        if i < len && bytes[i] == b'"' {
+          i += 1;
+          while let Some(c) = value[i..].chars().next() {
+            i += c.len_utf8();
+            match c {
+              '"' => break,
+              '\\' => {
+                if let Some(escaped) = value[i..].chars().next() {
+                  i += escaped.len_utf8();
+                  param.push(escaped);
+                }
+              }
+              c => param.push(c),
+            }
+          }
+        } else {
+          let value_start = i;
+          while i < len && !matches!(bytes[i], b';' | b',') && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+          }
+          param = value[value_start..i].to_string();
+        }
+      }
+
+      if name.is_empty() {
+        continue;
+      }
+
+      // Only the first `rel` parameter counts (RFC 8288, section 3.3).
+      if name == "rel" {
+        if link.rel.is_empty() {
+          link.rel = param
+            .split_ascii_whitespace()
+            .map(str::to_ascii_lowercase)
+            .collect();
+        }
+      } else {
+        link.params.push((name, param));
+      }
+    }
+
+    links.push(link);
+  }
+
+  links
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_github_style_pagination() {
+    let header = r#"<https://api.example.com/items?page=2>; rel="next", <https://api.example.com/items?page=5>; rel="last""#;
+    let links = parse_link_header(header);
+
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].uri, "https://api.example.com/items?page=2");
+    assert!(links[0].has_rel("next"));
+    assert!(links[1].has_rel("LAST"));
+  }
+
+  #[test]
+  fn parses_multiple_relations_and_params() {
+    let header =
+      r#"</a,b>; rel="prev first"; title="Say \"hi\", ok"; type=text/html, </c>;rel=next"#;
+    let links = parse_link_header(header);
+
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].uri, "/a,b");
+    assert_eq!(links[0].rel, vec!["prev", "first"]);
+    assert_eq!(
+      links[0].params,
+      vec![
+        ("title".to_string(), "Say \"hi\", ok".to_string()),
+        ("type".to_string(), "text/html".to_string()),
+      ]
+    );
+    assert_eq!(links[1].uri, "/c");
+    assert!(links[1].has_rel("next"));
+  }
+
+  #[test]
+  fn skips_malformed_entries() {
+    let links = parse_link_header(r#"garbage; rel=next, <https://ok.example/>; rel=next"#);
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].uri, "https://ok.example/");
+  }
+}
@@ -0,0 +1,189 @@
+//! Pagination driven by `Link: <...>; rel="next"` headers or caller-provided cursors.
+
+use std::collections::HashSet;
+
+use http::Uri;
+use url::Url;
+use wreq::Method;
+
+use crate::{execute_request, Client, Error, Request, Response};
+
+/// Where the next page lives, as decided after a page has been fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NextPage {
+  /// An absolute URL, or one relative to the current page.
+  Url(String),
+  /// Query parameters merged over the original request's query. Parameters it already has keep
+  /// their place, so each page's URL is built the same way.
+  Query(Vec<(String, String)>),
+  /// No further pages.
+  Done,
+}
+
+struct PageTarget {
+  url: String,
+  query: Option<Vec<(String, String)>>,
+}
+
+/// Sequentially fetches pages, replaying the same request parameters for each one.
+pub struct Paginator {
+  client: Option<Client>,
+  method: Method,
+  url: String,
+  template: Request,
+  next: Option<PageTarget>,
+  pages: usize,
+  max_pages: Option<usize>,
+  visited: HashSet<String>,
+}
+
+impl Paginator {
+  pub fn new(
+    client: Option<Client>,
+    method: Method,
+    url: String,
+    template: Request,
+    max_pages: Option<usize>,
+  ) -> Self {
+    let next = Some(PageTarget {
+      url: url.clone(),
+      query: template.query.clone(),
+    });
+
+    Self {
+      client,
+      method,
+      url,
+      template,
+      next,
+      pages: 0,
+      max_pages,
+      visited: HashSet::new(),
+    }
+  }
+
+  /// Number of pages fetched so far.
+  pub fn pages(&self) -> usize {
+    self.pages
+  }
+
+  /// Fetch the next page, or `None` once pagination is exhausted.
+  ///
+  /// After each page the next target defaults to the page's `rel="next"` link; call
+  /// [`Paginator::set_next`] to override it.
+  pub async fn fetch(&mut self) -> Result<Option<Response>, Error> {
+    if self.max_pages.is_some_and(|max| self.pages >= max) {
+      self.next = None;
+    }
+
+    let Some(target) = self.next.take() else {
+      return Ok(None);
+    };
+
+    // Stop instead of looping when a page points back at one already fetched.
+    if !self
+      .visited
+      .insert(format!("{}#{:?}", target.url, target.query))
+    {
+      return Ok(None);
+    }
+
+    let mut params = self.template.try_clone().ok_or(Error::NonReplayableBody)?;
+    params.query = target.query;
+
+    let response = execute_request(
+      self.client.clone(),
+      self.method.clone(),
+      &target.url,
+      params,
+    )
+    .await?;
+    self.pages += 1;
+
+    let next = response
+      .links()
+      .into_iter()
+      .find(|link| link.has_rel("next"))
+      .map_or(NextPage::Done, |link| NextPage::Url(link.uri));
+    self.set_next(next, &response.uri);
+
+    Ok(Some(response))
+  }
+
+  /// Override the target of the next [`Paginator::fetch`], relative to the page at `current`.
+  pub fn set_next(&mut self, next: NextPage, current: &Uri) {
+    self.next = match next {
+      NextPage::Url(url) => Url::parse(&current.to_string())
+        .and_then(|base| base.join(&url))
+        .map(|url| PageTarget {
+          url: url.into(),
+          query: None,
+        })
+        .ok(),
+      NextPage::Query(overrides) => {
+        let mut query = self.template.query.clone().unwrap_or_default();
+        for (name, value) in overrides {
+          match query.iter().position(|(key, _)| *key == name) {
+            Some(first) => {
+              query[first].1 = value;
+              let mut index = 0;
+              query.retain(|(key, _)| {
+                index += 1;
+                index <= first + 1 || *key != name
+              });
+            }
+            None => query.push((name, value)),
+          }
+        }
+        Some(PageTarget {
+          url: self.url.clone(),
+          query: Some(query),
+        })
+      }
+      NextPage::Done => None,
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+      .iter()
+      .map(|(name, value)| (name.to_string(), value.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn cursor_queries_keep_the_template_order() {
+    let template = Request {
+      query: Some(pairs(&[
+        ("page", "1"),
+        ("tag", "a"),
+        ("limit", "10"),
+        ("tag", "b"),
+      ])),
+      ..Request::default()
+    };
+    let url = "https://api.test/items".to_string();
+    let mut paginator = Paginator::new(None, Method::GET, url, template, None);
+
+    let current = Uri::from_static("https://api.test/items?page=1");
+    paginator.set_next(
+      NextPage::Query(pairs(&[("tag", "c"), ("after", "x"), ("page", "2")])),
+      &current,
+    );
+    let query = paginator.next.and_then(|next| next.query);
+    assert_eq!(
+      query,
+      Some(pairs(&[
+        ("page", "2"),
+        ("tag", "c"),
+        ("limit", "10"),
+        ("after", "x"),
+      ]))
+    );
+  }
+}
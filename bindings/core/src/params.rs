@@ -0,0 +1,238 @@
+//! Encoding of query strings and `application/x-www-form-urlencoded` bodies.
+
+use serde_json::Value;
+use url::form_urlencoded;
+
+/// How array values are expanded into keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayFormat {
+  /// `a=1&a=2`
+  #[default]
+  Repeat,
+  /// `a[]=1&a[]=2`
+  Brackets,
+  /// `a[0]=1&a[1]=2`
+  Indices,
+  /// `a=1,2`; arrays holding objects or arrays fall back to indices.
+  Comma,
+}
+
+/// How nested object keys are joined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObjectFormat {
+  /// `a[b]=1`
+  #[default]
+  Brackets,
+  /// `a.b=1`
+  Dots,
+}
+
+/// How spaces are written once parameters are percent-encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpaceEncoding {
+  /// `a+b`, as browsers submit forms.
+  #[default]
+  Plus,
+  /// `a%20b`, as RFC 3986 query components expect.
+  Percent,
+}
+
+/// Rules for flattening nested values into key/value pairs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParamsEncoding {
+  pub array_format: ArrayFormat,
+  pub object_format: ObjectFormat,
+}
+
+impl ParamsEncoding {
+  /// Flatten an object, or a list of `[key, value]` pairs, into ordered key/value pairs.
+  ///
+  /// Values may be nested objects and arrays; `null` values are skipped. Returns `None` when
+  /// `value` has neither shape.
+  pub fn flatten(&self, value: &Value) -> Option<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    match value {
+      Value::Object(map) => {
+        for (key, value) in map {
+          self.push(key.clone(), value, &mut pairs);
+        }
+      }
+      Value::Array(items) => {
+        for item in items {
+          let [Value::String(key), value] = item.as_array()?.as_slice() else {
+            return None;
+          };
+          self.push(key.clone(), value, &mut pairs);
+        }
+      }
+      _ => return None,
+    }
+    Some(pairs)
+  }
+
+  fn push(&self, key: String, value: &Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+      Value::Null => {}
+      Value::Object(map) => {
+        for (name, value) in map {
+          let key = match self.object_format {
+            ObjectFormat::Brackets => format!("{key}[{name}]"),
+            ObjectFormat::Dots => format!("{key}.{name}"),
+          };
+          self.push(key, value, pairs);
+        }
+      }
+      Value::Array(items) => {
+        if self.array_format == ArrayFormat::Comma && items.iter().all(is_scalar) {
+          if !items.is_empty() {
+            let joined = items
+              .iter()
+              .filter_map(scalar)
+              .collect::<Vec<_>>()
+              .join(",");
+            pairs.push((key, joined));
+          }
+          return;
+        }
+
+        for (index, item) in items.iter().enumerate() {
+          let key = match self.array_format {
+            ArrayFormat::Repeat => key.clone(),
+            ArrayFormat::Brackets => format!("{key}[]"),
+            ArrayFormat::Indices | ArrayFormat::Comma => format!("{key}[{index}]"),
+          };
+          self.push(key, item, pairs);
+        }
+      }
+      scalar_value => {
+        if let Some(value) = scalar(scalar_value) {
+          pairs.push((key, value));
+        }
+      }
+    }
+  }
+}
+
+/// Percent-encode pairs as `application/x-www-form-urlencoded`, keeping their order.
+pub fn encode_params(pairs: &[(String, String)], space: SpaceEncoding) -> String {
+  let encoded = form_urlencoded::Serializer::new(String::new())
+    .extend_pairs(pairs)
+    .finish();
+
+  match space {
+    SpaceEncoding::Plus => encoded,
+    // A literal `+` is already escaped as `%2B`, so every remaining `+` is a space.
+    SpaceEncoding::Percent => encoded.replace('+', "%20"),
+  }
+}
+
+/// Append an encoded query to `url`, after any existing query and before the fragment.
+pub(crate) fn append_query(url: &str, query: &str) -> String {
+  if query.is_empty() {
+    return url.to_string();
+  }
+
+  let (base, fragment) = match url.find('#') {
+    Some(index) => url.split_at(index),
+    None => (url, ""),
+  };
+  let separator = match base.find('?') {
+    None => "?",
+    Some(_) if base.ends_with('?') || base.ends_with('&') => "",
+    Some(_) => "&",
+  };
+  format!("{base}{separator}{query}{fragment}")
+}
+
+fn is_scalar(value: &Value) -> bool {
+  !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn scalar(value: &Value) -> Option<String> {
+  match value {
+    Value::String(value) => Some(value.clone()),
+    Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn encode(value: Value, array_format: ArrayFormat, object_format: ObjectFormat) -> String {
+    let encoding = ParamsEncoding {
+      array_format,
+      object_format,
+    };
+    encode_params(&encoding.flatten(&value).unwrap(), SpaceEncoding::Plus)
+  }
+
+  #[test]
+  fn keeps_pair_order() {
+    let value = json!([["z", "1"], ["a", 2], ["m", true]]);
+    assert_eq!(
+      encode(value, ArrayFormat::Repeat, ObjectFormat::Brackets),
+      "z=1&a=2&m=true"
+    );
+  }
+
+  #[test]
+  fn expands_arrays() {
+    let value = json!({ "a": ["1", "2"] });
+    assert_eq!(
+      encode(value.clone(), ArrayFormat::Repeat, ObjectFormat::Brackets),
+      "a=1&a=2"
+    );
+    assert_eq!(
+      encode(value.clone(), ArrayFormat::Brackets, ObjectFormat::Brackets),
+      "a%5B%5D=1&a%5B%5D=2"
+    );
+    assert_eq!(
+      encode(value.clone(), ArrayFormat::Indices, ObjectFormat::Brackets),
+      "a%5B0%5D=1&a%5B1%5D=2"
+    );
+    assert_eq!(
+      encode(value, ArrayFormat::Comma, ObjectFormat::Brackets),
+      "a=1%2C2"
+    );
+  }
+
+  #[test]
+  fn expands_nested_objects() {
+    let value = json!({ "user": { "name": "x", "tags": [{ "id": 1 }] }, "skip": null });
+    assert_eq!(
+      encode(value.clone(), ArrayFormat::Comma, ObjectFormat::Dots),
+      "user.name=x&user.tags%5B0%5D.id=1"
+    );
+    assert_eq!(
+      encode(value, ArrayFormat::Brackets, ObjectFormat::Brackets),
+      "user%5Bname%5D=x&user%5Btags%5D%5B%5D%5Bid%5D=1"
+    );
+  }
+
+  #[test]
+  fn rejects_other_shapes() {
+    let encoding = ParamsEncoding::default();
+    assert!(encoding.flatten(&json!("a=1")).is_none());
+    assert!(encoding.flatten(&json!([["a"]])).is_none());
+  }
+
+  #[test]
+  fn encodes_spaces() {
+    let pairs = vec![("q".to_string(), "a b+c".to_string())];
+    assert_eq!(encode_params(&pairs, SpaceEncoding::Plus), "q=a+b%2Bc");
+    assert_eq!(encode_params(&pairs, SpaceEncoding::Percent), "q=a%20b%2Bc");
+  }
+
+  #[test]
+  fn appends_query_before_fragment() {
+    assert_eq!(append_query("http://h/p", "a=1"), "http://h/p?a=1");
+    assert_eq!(
+      append_query("http://h/p?x=0#top", "a=1"),
+      "http://h/p?x=0&a=1#top"
+    );
+    assert_eq!(append_query("http://h/p?", "a=1"), "http://h/p?a=1");
+  }
+}
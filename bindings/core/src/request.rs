@@ -1,5 +1,6 @@
 use std::{net::IpAddr, time::Duration};
 
+use bytes::Bytes;
 use wreq::{
   header::{HeaderMap, HeaderValue, OrigHeaderMap},
   multipart::Form,
@@ -7,6 +8,12 @@ use wreq::{
 };
 use wreq_util::EmulationOption;
 
+use crate::{
+  abort::AbortSignal,
+  client::{InformationalCallback, RedirectPolicy, RetryPolicy},
+  params::SpaceEncoding,
+};
+
 /// The parameters for an HTTP request.
 #[derive(Default)]
 #[non_exhaustive]
@@ -24,6 +31,11 @@ pub struct Request {
   pub cookies: Option<Vec<HeaderValue>>,
   pub allow_redirects: Option<bool>,
   pub max_redirects: Option<usize>,
+  pub follow_refresh: Option<bool>,
+  /// Follow HTTP redirects hop by hop under this policy instead of the client's.
+  pub redirect: Option<RedirectPolicy>,
+  /// Record headers, address and timing for every redirect hop.
+  pub detailed_history: Option<bool>,
   pub gzip: Option<bool>,
   pub brotli: Option<bool>,
   pub deflate: Option<bool>,
@@ -33,9 +45,24 @@ pub struct Request {
   pub basic_auth: Option<(String, Option<String>)>,
   pub query: Option<Vec<(String, String)>>,
   pub form: Option<Vec<(String, String)>>,
+  pub space_encoding: Option<SpaceEncoding>,
   pub json: Option<serde_json::Value>,
   pub body: Option<wreq::Body>,
+  pub trailers: Option<HeaderMap>,
   pub multipart: Option<Form>,
+  /// Called with every `1xx` response received before the final one.
+  pub on_informational: Option<InformationalCallback>,
+  /// Send `Expect: 100-continue` and hold the body back until the server answers or this long
+  /// has passed.
+  pub expect_continue: Option<Duration>,
+  pub signal: Option<AbortSignal>,
+  pub retry: Option<RetryPolicy>,
+  /// Rate limit bucket for the request; defaults to the URL host.
+  pub rate_limit_key: Option<String>,
+  /// Queue position among requests waiting for a slot; higher values go first.
+  pub priority: Option<i32>,
+  /// How long to wait for a slot, overriding the client's queue timeout.
+  pub queue_timeout: Option<Duration>,
 }
 
 impl Request {
@@ -54,6 +81,9 @@ impl Request {
       cookies,
       allow_redirects,
       max_redirects,
+      follow_refresh,
+      redirect,
+      detailed_history,
       gzip,
       brotli,
       deflate,
@@ -63,9 +93,18 @@ impl Request {
       basic_auth,
       query,
       form,
+      space_encoding,
       json,
       body,
+      trailers,
       multipart,
+      on_informational,
+      expect_continue,
+      signal,
+      retry,
+      rate_limit_key,
+      priority,
+      queue_timeout,
     } = self;
 
     emulation.is_none()
@@ -81,6 +120,9 @@ impl Request {
       && cookies.is_none()
       && allow_redirects.is_none()
       && max_redirects.is_none()
+      && follow_refresh.is_none()
+      && redirect.is_none()
+      && detailed_history.is_none()
       && gzip.is_none()
       && brotli.is_none()
       && deflate.is_none()
@@ -90,9 +132,80 @@ impl Request {
       && basic_auth.is_none()
       && query.is_none()
       && form.is_none()
+      && space_encoding.is_none()
       && json.is_none()
       && body.is_none()
+      && trailers.is_none()
       && multipart.is_none()
+      && on_informational.is_none()
+      && expect_continue.is_none()
+      && signal.is_none()
+      && retry.is_none()
+      && rate_limit_key.is_none()
+      && priority.is_none()
+      && queue_timeout.is_none()
+  }
+
+  /// Duplicate the request parameters so the same request can be sent again.
+  ///
+  /// Returns `None` when the body cannot be replayed (streams and multipart forms).
+  pub fn try_clone(&self) -> Option<Request> {
+    if self.multipart.is_some() {
+      return None;
+    }
+
+    let body = match &self.body {
+      Some(body) => Some(wreq::Body::from(Bytes::copy_from_slice(body.as_bytes()?))),
+      None => None,
+    };
+
+    Some(Request {
+      body,
+      ..self.clone_without_body()
+    })
+  }
+
+  /// Duplicate everything except a raw `body` or `multipart` form.
+  pub(crate) fn clone_without_body(&self) -> Request {
+    Request {
+      emulation: self.emulation.clone(),
+      proxy: self.proxy.clone(),
+      local_address: self.local_address,
+      interface: self.interface.clone(),
+      timeout: self.timeout,
+      read_timeout: self.read_timeout,
+      version: self.version,
+      headers: self.headers.clone(),
+      orig_headers: self.orig_headers.clone(),
+      default_headers: self.default_headers,
+      cookies: self.cookies.clone(),
+      allow_redirects: self.allow_redirects,
+      max_redirects: self.max_redirects,
+      follow_refresh: self.follow_refresh,
+      redirect: self.redirect.clone(),
+      detailed_history: self.detailed_history,
+      gzip: self.gzip,
+      brotli: self.brotli,
+      deflate: self.deflate,
+      zstd: self.zstd,
+      auth: self.auth.clone(),
+      bearer_auth: self.bearer_auth.clone(),
+      basic_auth: self.basic_auth.clone(),
+      query: self.query.clone(),
+      form: self.form.clone(),
+      space_encoding: self.space_encoding,
+      json: self.json.clone(),
+      body: None,
+      trailers: self.trailers.clone(),
+      multipart: None,
+      on_informational: self.on_informational.clone(),
+      expect_continue: self.expect_continue,
+      signal: self.signal.clone(),
+      retry: self.retry.clone(),
+      rate_limit_key: self.rate_limit_key.clone(),
+      priority: self.priority,
+      queue_timeout: self.queue_timeout,
+    }
   }
 }
 
@@ -120,6 +233,7 @@ pub struct WebSocketRequest {
   pub max_frame_size: Option<usize>,
   pub max_message_size: Option<usize>,
   pub accept_unmasked_frames: Option<bool>,
+  pub signal: Option<AbortSignal>,
 }
 
 impl WebSocketRequest {
@@ -145,6 +259,7 @@ impl WebSocketRequest {
       max_frame_size,
       max_message_size,
       accept_unmasked_frames,
+      signal,
     } = self;
 
     emulation.is_none()
@@ -167,6 +282,7 @@ impl WebSocketRequest {
       && max_frame_size.is_none()
       && max_message_size.is_none()
       && accept_unmasked_frames.is_none()
+      && signal.is_none()
   }
 }
 
@@ -187,6 +303,17 @@ mod tests {
     assert!(!request.is_empty());
   }
 
+  #[test]
+  fn request_try_clone_replays_bytes_body() {
+    let mut request = Request::default();
+    request.body = Some(wreq::Body::from("payload"));
+    request.query = Some(vec![("a".into(), "1".into())]);
+
+    let cloned = request.try_clone().unwrap();
+    assert_eq!(cloned.body.unwrap().as_bytes(), Some(&b"payload"[..]));
+    assert_eq!(cloned.query, request.query);
+  }
+
   #[test]
   fn websocket_request_is_empty_by_default() {
     let request = WebSocketRequest::default();
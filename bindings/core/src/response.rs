@@ -1,13 +1,23 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+  net::SocketAddr,
+  sync::{Arc, Mutex, OnceLock},
+  time::{Duration, Instant},
+};
 
 use arc_swap::ArcSwapOption;
-use bytes::Bytes;
-use futures_util::TryFutureExt;
+use bytes::{Bytes, BytesMut};
 use http::{response::Response as HttpResponse, Extensions, StatusCode, Uri, Version};
 use http_body_util::BodyExt;
-use wreq::{self, Extension};
+use wreq::{self, header::HeaderMap, Extension};
 
+use crate::abort::{run_abortable, AbortSignal, AbortableBody};
+use crate::body::Prefixed;
+use crate::client::{
+  concurrency::PermitBody, har::HarBody, CacheStatus, ConcurrencyPermit, InformationalResponse,
+  RetryAttempt,
+};
 use crate::error::Error;
+use crate::link::{parse_link_header, Link};
 
 /// Represents the state of the HTTP response body.
 #[derive(Debug)]
@@ -18,6 +28,59 @@ pub enum ResponseBody {
   Reusable(Bytes),
 }
 
+/// How a redirect hop was triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+  /// A 3xx response with a `Location` header.
+  Http,
+  /// A `Refresh` response header.
+  RefreshHeader,
+  /// A `<meta http-equiv="refresh">` tag in an HTML body.
+  MetaRefresh,
+}
+
+/// A single hop in a redirect chain.
+#[derive(Debug, Clone)]
+pub struct RedirectHop {
+  pub kind: RedirectKind,
+  pub status: StatusCode,
+  pub uri: Uri,
+  pub previous: Uri,
+  /// Headers of the response that redirected.
+  ///
+  /// Empty for redirects followed inside a single request, which only report status and URIs.
+  pub headers: HeaderMap,
+  pub remote_addr: Option<SocketAddr>,
+  /// Time until the redirecting response's headers arrived.
+  pub elapsed: Option<Duration>,
+}
+
+impl RedirectHop {
+  /// A hop from `response` to `uri`.
+  pub(crate) fn new(kind: RedirectKind, response: &Response, uri: Uri) -> Self {
+    RedirectHop {
+      kind,
+      status: response.status,
+      uri,
+      previous: response.uri.clone(),
+      headers: response.headers.clone(),
+      remote_addr: response.remote_addr,
+      elapsed: response.elapsed,
+    }
+  }
+
+  /// Cookies set by the redirecting response; malformed `Set-Cookie` values are skipped.
+  pub fn cookies(&self) -> Vec<cookie::Cookie<'static>> {
+    self
+      .headers
+      .get_all(http::header::SET_COOKIE)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .filter_map(|value| cookie::Cookie::parse(value.to_string()).ok())
+      .collect()
+  }
+}
+
 /// A binding-agnostic HTTP response wrapper.
 #[derive(Debug)]
 pub struct Response {
@@ -29,6 +92,15 @@ pub struct Response {
   pub remote_addr: Option<SocketAddr>,
   pub uri: Uri,
   pub extensions: Extensions,
+  history: Vec<RedirectHop>,
+  attempts: Vec<RetryAttempt>,
+  informational: Vec<InformationalResponse>,
+  trailers: OnceLock<wreq::header::HeaderMap>,
+  signal: Mutex<Option<AbortSignal>>,
+  elapsed: Option<Duration>,
+  permit: Mutex<Option<ConcurrencyPermit>>,
+  har: Option<HarBody>,
+  cache_status: Option<CacheStatus>,
   body: ArcSwapOption<ResponseBody>,
 }
 
@@ -41,6 +113,24 @@ impl Response {
     let remote_addr = response.remote_addr();
     let response = HttpResponse::from(response);
     let (parts, body) = response.into_parts();
+    let history = parts
+      .extensions
+      .get::<Extension<Vec<wreq::redirect::History>>>()
+      .map(|Extension(history)| {
+        history
+          .iter()
+          .map(|hop| RedirectHop {
+            kind: RedirectKind::Http,
+            status: hop.status(),
+            uri: hop.uri().clone(),
+            previous: hop.previous().clone(),
+            headers: HeaderMap::new(),
+            remote_addr: None,
+            elapsed: None,
+          })
+          .collect()
+      })
+      .unwrap_or_default();
 
     Response {
       uri,
@@ -51,10 +141,36 @@ impl Response {
       version: parts.version,
       status: parts.status,
       headers: parts.headers,
+      history,
+      attempts: Vec::new(),
+      informational: Vec::new(),
+      trailers: OnceLock::new(),
+      signal: Mutex::new(None),
+      elapsed: None,
+      permit: Mutex::new(None),
+      har: None,
+      cache_status: None,
       body: ArcSwapOption::from_pointee(ResponseBody::Streamable(body)),
     }
   }
 
+  /// A response over a body already in memory, for responses that don't come from the network.
+  pub(crate) fn buffered(
+    version: Version,
+    status: StatusCode,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+  ) -> Self {
+    let mut response = HttpResponse::new(wreq::Body::from(body));
+    *response.version_mut() = version;
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    let mut response = Response::new(wreq::Response::from(response));
+    response.uri = uri;
+    response
+  }
+
   /// Attempt to reuse the response body, yielding a fresh [`wreq::Response`].
   async fn reuse_response(&self, stream: bool) -> Result<wreq::Response, Error> {
     let build_response = |body: wreq::Body| -> wreq::Response {
@@ -70,12 +186,31 @@ impl Response {
       match Arc::try_unwrap(arc) {
         Ok(ResponseBody::Streamable(body)) => {
           if stream {
-            Ok(build_response(body))
+            let body = match self.take_permit() {
+              Some(permit) => wreq::Body::wrap(PermitBody::new(body, permit)),
+              None => body,
+            };
+            Ok(match self.take_signal() {
+              Some(signal) => build_response(wreq::Body::wrap(AbortableBody::new(body, signal))),
+              None => build_response(body),
+            })
           } else {
-            let bytes = BodyExt::collect(body)
-              .map_ok(|buf| buf.to_bytes())
-              .map_err(Error::Library)
-              .await?;
+            let receiving = Instant::now();
+            let signal = self.take_signal();
+            let collected = run_abortable(signal.as_ref(), async {
+              BodyExt::collect(body).await.map_err(Error::Library)
+            })
+            .await;
+            drop(signal);
+            drop(self.take_permit());
+            let collected = collected?;
+            if let Some(trailers) = collected.trailers() {
+              let _ = self.trailers.set(trailers.clone());
+            }
+            let bytes = collected.to_bytes();
+            if let Some(har) = &self.har {
+              har.record(&bytes, receiving.elapsed());
+            }
             self
               .body
               .store(Some(Arc::new(ResponseBody::Reusable(bytes.clone()))));
@@ -99,6 +234,72 @@ impl Response {
     }
   }
 
+  /// Read the start of the body, at most `limit` bytes or until `enough` accepts what was read,
+  /// leaving the whole body to be read or streamed afterwards.
+  pub(crate) async fn peek(
+    &self,
+    limit: usize,
+    enough: impl Fn(&[u8]) -> bool,
+  ) -> Result<Bytes, Error> {
+    let Some(arc) = self.body.swap(None) else {
+      return Err(Error::Memory);
+    };
+    let mut body = match Arc::try_unwrap(arc) {
+      Ok(ResponseBody::Streamable(body)) => body,
+      Ok(ResponseBody::Reusable(bytes)) => {
+        let prefix = bytes.slice(..limit.min(bytes.len()));
+        self
+          .body
+          .store(Some(Arc::new(ResponseBody::Reusable(bytes))));
+        return Ok(prefix);
+      }
+      Err(arc) => {
+        self.body.store(Some(arc));
+        return Err(Error::Memory);
+      }
+    };
+
+    let receiving = Instant::now();
+    let mut prefix = BytesMut::new();
+    let mut ended = false;
+    let signal = self.signal.lock().unwrap().clone();
+    run_abortable(signal.as_ref(), async {
+      while prefix.len() < limit && !enough(&prefix) {
+        let Some(frame) = body.frame().await else {
+          ended = true;
+          break;
+        };
+        match frame.map_err(Error::Library)?.into_data() {
+          Ok(data) => prefix.extend_from_slice(&data),
+          Err(frame) => {
+            if let Ok(trailers) = frame.into_trailers() {
+              let _ = self.trailers.set(trailers);
+            }
+            ended = true;
+            break;
+          }
+        }
+      }
+      Ok(())
+    })
+    .await?;
+
+    let prefix = prefix.freeze();
+    let rest = if ended {
+      // The whole body was read, so it is finished with as `bytes` would finish it.
+      drop(self.take_permit());
+      drop(self.take_signal());
+      if let Some(har) = &self.har {
+        har.record(&prefix, receiving.elapsed());
+      }
+      ResponseBody::Reusable(prefix.clone())
+    } else {
+      ResponseBody::Streamable(wreq::Body::wrap(Prefixed::new(prefix.clone(), body)))
+    };
+    self.body.store(Some(Arc::new(rest)));
+    Ok(prefix)
+  }
+
   /// Obtain a reusable response for operations that fully consume the body.
   pub async fn response(&self) -> Result<wreq::Response, Error> {
     self.reuse_response(false).await
@@ -152,15 +353,105 @@ impl Response {
   /// Close the response and drop any cached body state.
   pub fn close(&self) {
     self.body.swap(None);
+    drop(self.take_permit());
+  }
+
+  /// Trailing headers received after the body.
+  ///
+  /// Only available once the body has been buffered by one of the reading methods.
+  pub fn trailers(&self) -> Option<&wreq::header::HeaderMap> {
+    self.trailers.get()
+  }
+
+  /// Access the redirect chain that led to this response, oldest hop first.
+  pub fn history(&self) -> &[RedirectHop] {
+    &self.history
+  }
+
+  /// Earlier attempts that failed and were retried; empty when the first attempt succeeded.
+  pub fn attempts(&self) -> &[RetryAttempt] {
+    &self.attempts
+  }
+
+  pub(crate) fn set_attempts(&mut self, attempts: Vec<RetryAttempt>) {
+    self.attempts = attempts;
+  }
+
+  /// `1xx` responses received before this one, over HTTP/1.1.
+  pub fn informational(&self) -> &[InformationalResponse] {
+    &self.informational
+  }
+
+  pub(crate) fn set_informational(&mut self, informational: Vec<InformationalResponse>) {
+    self.informational = informational;
+  }
+
+  /// Cancel body reads once `signal` is aborted.
+  pub(crate) fn set_signal(&mut self, signal: Option<AbortSignal>) {
+    *self.signal.get_mut().unwrap() = signal;
+  }
+
+  /// Time from sending the request until the response headers arrived.
+  ///
+  /// Covers only the last request when redirects or refreshes were followed one hop at a time.
+  pub fn elapsed(&self) -> Option<Duration> {
+    self.elapsed
+  }
+
+  pub(crate) fn set_elapsed(&mut self, elapsed: Duration) {
+    self.elapsed = Some(elapsed);
   }
 
-  /// Access the redirect history extension.
-  pub fn history(&self) -> Vec<wreq::redirect::History> {
+  /// Hold `permit` until the body has been read or the response is closed.
+  pub(crate) fn set_permit(&mut self, permit: Option<ConcurrencyPermit>) {
+    *self.permit.get_mut().unwrap() = permit;
+  }
+
+  /// How the client's HTTP cache answered the request, or `None` when it had no part in it.
+  pub fn cache_status(&self) -> Option<CacheStatus> {
+    self.cache_status
+  }
+
+  pub(crate) fn set_cache_status(&mut self, status: Option<CacheStatus>) {
+    self.cache_status = status;
+  }
+
+  /// Store the body in a HAR entry once it has been read.
+  pub(crate) fn set_har(&mut self, har: Option<HarBody>) {
+    self.har = har;
+  }
+
+  fn take_permit(&self) -> Option<ConcurrencyPermit> {
+    self.permit.lock().unwrap().take()
+  }
+
+  /// Let go of the signal once the body is no longer read through the response, so that it can
+  /// be released.
+  fn take_signal(&self) -> Option<AbortSignal> {
+    self.signal.lock().unwrap().take()
+  }
+
+  /// Replace the redirect chain, used when hops span several underlying requests.
+  pub(crate) fn set_history(&mut self, history: Vec<RedirectHop>) {
+    self.history = history;
+  }
+
+  /// Parse every `Link` header, resolving targets against the response URI.
+  pub fn links(&self) -> Vec<Link> {
+    let base = url::Url::parse(&self.uri.to_string()).ok();
     self
-      .extensions
-      .get::<Extension<Vec<wreq::redirect::History>>>()
-      .map(|Extension(history)| history.clone())
-      .unwrap_or_default()
+      .headers
+      .get_all(wreq::header::LINK)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(parse_link_header)
+      .map(|mut link| {
+        if let Some(resolved) = base.as_ref().and_then(|base| base.join(&link.uri).ok()) {
+          link.uri = resolved.into();
+        }
+        link
+      })
+      .collect()
   }
 
   /// Access the TLS peer certificate, if available.
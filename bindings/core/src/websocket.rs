@@ -17,6 +17,7 @@ use wreq::{
   },
 };
 
+use crate::abort::{run_abortable, AbortSignal};
 use crate::error::Error;
 
 /// A WebSocket message wrapper.
@@ -132,7 +133,11 @@ pub struct WebSocket {
 }
 
 impl WebSocket {
-  pub async fn new(response: WebSocketResponse) -> Result<Self, Error> {
+  /// Complete the upgrade; once `signal` is aborted the socket is closed and pending receives fail.
+  pub async fn new(
+    response: WebSocketResponse,
+    signal: Option<AbortSignal>,
+  ) -> Result<Self, Error> {
     let version = response.version();
     let status = response.status();
     let remote_addr = response.remote_addr();
@@ -141,7 +146,7 @@ impl WebSocket {
     let websocket = response.into_websocket().await.map_err(Error::Library)?;
     let protocol = websocket.protocol().cloned();
     let (cmd, rx) = mpsc::unbounded_channel();
-    tokio::spawn(command_task(websocket, rx));
+    tokio::spawn(command_task(websocket, rx, signal));
 
     Ok(Self {
       version,
@@ -252,9 +257,22 @@ async fn close(
   send_command(cmd, |tx| Command::Close(code, reason, tx)).await
 }
 
-async fn command_task(ws: ws::WebSocket, mut rx: UnboundedReceiver<Command>) {
+async fn command_task(
+  ws: ws::WebSocket,
+  mut rx: UnboundedReceiver<Command>,
+  signal: Option<AbortSignal>,
+) {
   let (mut writer, mut reader) = ws.split();
-  while let Some(command) = rx.recv().await {
+  loop {
+    let command = match run_abortable(signal.as_ref(), async { Ok(rx.recv().await) }).await {
+      Ok(Some(command)) => command,
+      Ok(None) => break,
+      Err(_) => {
+        let _ = writer.close().await;
+        break;
+      }
+    };
+
     match command {
       Command::Send(message, tx) => {
         let res = writer.send(message.0).await.map_err(Error::Library);
@@ -274,15 +292,23 @@ async fn command_task(ws: ws::WebSocket, mut rx: UnboundedReceiver<Command>) {
             .map_err(Error::Library)
         };
 
-        let res = if let Some(timeout) = timeout {
-          match tokio::time::timeout(timeout, fut).await {
-            Ok(res) => res,
-            Err(err) => Err(Error::Timeout(err)),
+        let fut = async {
+          if let Some(timeout) = timeout {
+            match tokio::time::timeout(timeout, fut).await {
+              Ok(res) => res,
+              Err(err) => Err(Error::Timeout(err)),
+            }
+          } else {
+            fut.await
           }
-        } else {
-          fut.await
         };
+        let res = run_abortable(signal.as_ref(), fut).await;
+        let aborted = matches!(res, Err(Error::Aborted(_)));
         let _ = tx.send(res);
+        if aborted {
+          let _ = writer.close().await;
+          break;
+        }
       }
       Command::Close(code, reason, tx) => {
         let code = code.map(CloseCode::from).unwrap_or(CloseCode::NORMAL);
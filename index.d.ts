@@ -10,6 +10,141 @@ export declare class Client {
   delete(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
   head(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
   options(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
+  /** Iterate over the pages of a resource, following `rel="next"` links or a custom cursor. */
+  paginate(url: string, init?: RequestInit | undefined | null, options?: PaginateOptions | undefined | null): Paginator
+  /**
+   * Send many requests with at most `concurrency` in flight, yielding each result with its body
+   * read as soon as it finishes. Stopping the iteration early cancels what is left.
+   */
+  batch(requests: Array<string | BatchRequestInit>, options?: BatchOptions | undefined | null): BatchIterator
+  /**
+   * Stream `url` to the file at `path` without buffering it in memory.
+   *
+   * The body goes to `<path>.part` and replaces `path` once its length checks out. A download
+   * that fails part way leaves the partial file behind, and downloading to the same path again
+   * asks only for the rest with `Range` and `If-Range`. `<path>.part` is truncated when the
+   * server sends the whole file instead, and a response without `ETag` or `Last-Modified` can't
+   * be resumed later.
+   */
+  download(url: string, path: string, init?: RequestInit | undefined | null, options?: DownloadOptions | undefined | null): Promise<DownloadEntry>
+  /** Resolve a request without sending it, to inspect what would go on the wire or send it later. */
+  prepare(method: string, url: string, init?: RequestInit | undefined | null): PreparedRequest
+  /**
+   * Prepare the request described by a curl command line, such as one copied from browser
+   * devtools. `-k` is only accepted by a client created with `verify: false`.
+   */
+  fromCurl(command: string): Promise<PreparedRequest>
+  /** Requests in flight and waiting for a slot, or `null` when concurrency is not limited. */
+  concurrency(): ConcurrencyStats | null
+  /** Circuit breaker state of every host contacted so far, or `null` without a circuit breaker. */
+  circuits(): Array<CircuitEntry> | null
+  /** Exchanges recorded so far as a HAR 1.2 document, or `null` when recording is off. */
+  har(): { log: { version: string; entries: Array<any> } } | null
+  /**
+   * Write the exchanges recorded so far to `path` as a HAR 1.2 document, or to the `path` given
+   * in the HAR options.
+   */
+  saveHar(path?: string | undefined | null): Promise<void>
+  /** Forget the exchanges recorded so far. */
+  clearHar(): void
+  /** Drop every response the client's cache holds. */
+  clearCache(): Promise<void>
+  /** Routes and call log of a client created with `mock`, or `null` otherwise. */
+  get mock(): MockTransport | null
+}
+
+/**
+ * Async iterator over the results of `client.batch`, in the order requests finish.
+ *
+ * # Example
+ *
+ * ```javascript
+ * for await (const result of client.batch(urls, { concurrency: 64 })) {
+ *   if (result.error) console.error(result.url, result.error)
+ * }
+ * ```
+ */
+export declare class BatchIterator {
+  [Symbol.asyncIterator](): AsyncGenerator<BatchResultEntry, void, void>
+}
+
+/**
+ * Canned replies for a client created with `mock`, and a log of the requests it sent.
+ *
+ * # Example
+ *
+ * ```javascript
+ * const client = new Client({ mock: true })
+ * client.mock.on({ method: 'GET', url: 'https://api.test/users/*' }, { json: { id: 1 } })
+ * await client.get('https://api.test/users/1')
+ * console.log(client.mock.calls())
+ * ```
+ */
+export declare class MockTransport {
+  /**
+   * Answer requests matching `matcher` with `reply`. Routes are tried in the order they were
+   * added.
+   */
+  on(matcher: MockMatcherInit, reply: MockReplyInit): void
+  /** Requests sent so far, matched or not, in the order they were sent. */
+  calls(): Array<MockCallEntry>
+  clearCalls(): void
+  /** Remove every route and forget every call. */
+  reset(): void
+}
+
+/**
+ * Async iterator over the pages of a paginated resource.
+ *
+ * # Example
+ *
+ * ```javascript
+ * for await (const page of client.paginate('https://api.example.com/items')) {
+ *   const items = await page.json();
+ * }
+ * ```
+ */
+export declare class Paginator {
+  [Symbol.asyncIterator](): AsyncGenerator<ResponseHandle, void, void>
+}
+
+/**
+ * A request resolved the way it would be sent, without sending it.
+ *
+ * # Example
+ *
+ * ```javascript
+ * const prepared = client.prepare('POST', 'https://api.example.com/items', { json: item })
+ * console.log(prepared.url, prepared.headers)
+ * const response = await prepared.send()
+ * ```
+ */
+export declare class PreparedRequest {
+  get method(): string
+  /** The URL with the query merged in. */
+  get url(): string
+  /**
+   * Headers in wire order, including those added by the client's defaults and emulation.
+   *
+   * `Host`, `Content-Length` and cookies from the cookie store are added by the connection
+   * and are not listed.
+   */
+  get headers(): Array<[string, string]>
+  get body(): PreparedBodyEntry
+  /** The configured proxy the request goes through, without credentials. */
+  get proxy(): string | null
+  /**
+   * A bash curl command reproducing the request. Multipart bodies become `-F` parts, other
+   * streamed bodies and in-memory parts are read from stdin, and proxy credentials are left out.
+   */
+  toCurl(): string
+  /**
+   * Send the request as prepared. A prepared request can only be sent once, and is sent in a
+   * single attempt. It fails with `ERR_NITAI_UNSUPPORTED` when a retry policy, or a redirect
+   * policy that restricts hops, would apply to it. It is sent exactly as shown, so request and
+   * response hooks, the HTTP cache and coalescing of identical requests do not apply either.
+   */
+  send(): Promise<ResponseHandle>
 }
 
 /**
@@ -47,9 +182,29 @@ export declare class ResponseHandle {
   /** Returns the response `Content-Length` in bytes, or `-1` when not provided by the server. */
   get contentLength(): number
   get headers(): Record<string, Array<string>>
+  /**
+   * Trailing headers sent after the body, or `null` when there were none.
+   *
+   * Only populated once the body has been read via `text()`, `json()` or `bytes()`.
+   */
+  get trailers(): Record<string, Array<string>> | null
   get localAddr(): string | null
   get remoteAddr(): string | null
+  /** Links parsed from the `Link` headers (RFC 8288), resolved against the response URL. */
+  get links(): Array<LinkEntry>
   history(): Array<RedirectHistoryEntry>
+  /** Earlier attempts that failed and were retried; empty when the first attempt succeeded. */
+  get attempts(): Array<RetryAttemptEntry>
+  /**
+   * `1xx` responses received before this one, such as `103 Early Hints`; only reported over
+   * HTTP/1.1.
+   */
+  get informational(): Array<InformationalEntry>
+  /**
+   * How the client's cache answered the request: `hit`, `stale`, `revalidated` or `miss`, or
+   * `null` when the request did not go through a cache.
+   */
+  get cacheStatus(): 'hit' | 'stale' | 'revalidated' | 'miss' | null
   /**
    * Reads the response body as text.
    * The response is automatically cleaned up after consumption.
@@ -65,6 +220,12 @@ export declare class ResponseHandle {
    * The response is automatically cleaned up after consumption.
    */
   bytes(): Promise<Buffer>
+  /**
+   * Streams the body to `path` without buffering it in memory, replacing the file only once the
+   * whole body is written and its length matches `Content-Length`. Resolves with the number of
+   * bytes written.
+   */
+  saveTo(path: string, options?: DownloadOptions | undefined | null): Promise<number>
   /**
    * Explicitly closes the response and releases resources immediately.
    *
@@ -94,14 +255,153 @@ export interface BasicAuth {
   password?: string
 }
 
+export interface BatchOptions {
+  /** Requests in flight at once. Defaults to 16. */
+  concurrency?: number
+}
+
+export interface BatchRequestInit {
+  url: string
+  init?: RequestInit
+}
+
+/** A finished request of a batch. Failed requests have `error` set and no response fields. */
+export interface BatchResultEntry {
+  /** Position of the request in the batch. */
+  index: number
+  /** Final URL, after redirects. */
+  url: string
+  status?: number
+  headers?: Record<string, Array<string>>
+  body?: Buffer
+  /** The message the request failed with, starting with its `ERR_NITAI_*` code. */
+  error?: string
+}
+
+/** HTTP cache settings. */
+export interface CacheOptions {
+  /** Directory holding the cache, created when missing. Without it responses are kept in memory. */
+  path?: string
+  /** URLs kept in memory before the least recently stored is dropped. Defaults to 1000. */
+  maxEntries?: number
+  /**
+   * Largest body, in bytes, a stored response may have; larger ones are streamed without being
+   * stored. Defaults to 10 MiB.
+   */
+  maxBodySize?: number
+}
+
+/** A HAR file of recorded exchanges that answers requests in place of the network. */
+export interface CassetteOptions {
+  path: string
+  /**
+   * `"replay"` (the default) fails requests without a recording with `ERR_NITAI_CASSETTE`,
+   * `"recordMissing"` sends and records them, and `"record"` sends and records every request.
+   */
+  mode?: 'replay' | 'recordMissing' | 'record'
+  /** Whether requests must have the recorded body to match. Defaults to `true`. */
+  matchBody?: boolean
+  /**
+   * Serve each recording once instead of repeating the last match, so a request whose
+   * recordings were all served counts as unmatched.
+   */
+  strict?: boolean
+  /**
+   * Request headers whose values are replaced with `[redacted]` when recording. Defaults to
+   * `authorization`, `proxy-authorization` and `cookie`.
+   */
+  redactHeaders?: Array<string>
+}
+
+/** Circuit breaker settings; unset fields keep their defaults. */
+export interface CircuitBreakerOptions {
+  /** Share of failed requests that opens a host's circuit, from 0 to 1. Defaults to 0.5. */
+  failureThreshold?: number
+  /** Requests within the window needed before the failure rate is considered. Defaults to 5. */
+  minimumRequests?: number
+  /** Milliseconds of history used for the failure rate. Defaults to 60000. */
+  window?: number
+  /** Milliseconds an open circuit rejects requests before trying again. Defaults to 30000. */
+  cooldown?: number
+  /** Trial requests allowed at once while half-open. Defaults to 1. */
+  halfOpenRequests?: number
+  /** Response statuses counted as failures. Defaults to 500, 502, 503 and 504. */
+  statuses?: Array<number>
+}
+
+export interface CircuitEntry {
+  host: string
+  /** `closed`, `open` or `half-open`. */
+  state: string
+  /** Requests counted within the window. */
+  requests: number
+  /** Failures counted within the window. */
+  failures: number
+  /** Milliseconds until an open circuit lets a trial request through. */
+  retryIn?: number
+}
+
 export interface ClientInit {
   emulation?: string | EmulationOptions
   userAgent?: string
-  headers?: Record<string, string | Array<string>>
+  headers?: Record<string, string | Array<string>> | Array<[string, string]>
+  /** Header names in wire order, which may include the emulation's default headers. */
+  headerOrder?: Array<string>
   referer?: boolean
   history?: boolean
   allowRedirects?: boolean
   maxRedirects?: number
+  /** Also follow `Refresh` headers and `<meta http-equiv="refresh">` tags. */
+  followRefresh?: boolean
+  /** Checks and rewrites every redirect hop; ignored when `allowRedirects` is `false`. */
+  redirect?: RedirectOptions
+  /**
+   * Follow redirects one hop at a time so `history()` includes each hop's headers, cookies,
+   * address and timing.
+   */
+  detailedHistory?: boolean
+  /** Retry policy applied to every request; `true` uses the defaults. */
+  retry?: boolean | RetryOptions
+  /** Paces requests per host; shared by every request sent through the client. */
+  rateLimit?: RateLimitOptions
+  /** Caps requests in flight through the client, queueing the rest. */
+  concurrency?: ConcurrencyOptions
+  /** Fails requests fast with `ERR_NITAI_CIRCUIT_OPEN` for hosts that keep failing. */
+  circuitBreaker?: boolean | CircuitBreakerOptions
+  /** Records every exchange as a HAR 1.2 archive, read with `har()`; `true` keeps it in memory. */
+  har?: boolean | HarOptions
+  /** Answers requests from a recorded HAR file instead of the network. */
+  cassette?: CassetteOptions
+  /**
+   * Answers requests with replies registered through `client.mock`; `true` fails requests no
+   * route matches.
+   */
+  mock?: boolean | MockOptions
+  /**
+   * Called before every request is sent, including retries and redirect hops. Change the
+   * request in place or return (or resolve with) the request to send; fields left out of a
+   * returned request keep their value.
+   */
+  beforeRequest?: (request: HookRequestEntry) => unknown
+  /**
+   * Called with every response. Return (or resolve with) nothing to keep it, `'retry'` to send
+   * the request again or `{ status, headers, body, json }` to answer with that instead.
+   */
+  afterResponse?: (response: HookResponseEntry, request: HookRequestEntry) => unknown
+  /** Called when a request fails, returning what `afterResponse` does. */
+  onError?: (error: HookErrorEntry, request: HookRequestEntry) => unknown
+  /**
+   * Send identical `GET` and `HEAD` requests in flight at the same time once, giving every
+   * caller a response over the same buffered body. Responses over 10 MiB are not buffered, and
+   * each waiting caller sends its own request instead. Requests with their own timeouts, retry,
+   * priority, rate limit key, proxy, emulation or redirect policy are always sent alone.
+   */
+  singleFlight?: boolean
+  /**
+   * Keeps responses to `GET` requests and answers from them while they are fresh, following
+   * `Cache-Control`, `Expires` and `Vary`; `true` keeps them in memory.
+   */
+  cache?: boolean | CacheOptions
   cookieStore?: boolean
   timeout?: number
   connectTimeout?: number
@@ -133,9 +433,63 @@ export interface ClientInit {
   zstd?: boolean
 }
 
+/** Caps on requests in flight through a client; requests over a cap wait in a queue. */
+export interface ConcurrencyOptions {
+  /** Most requests in flight at once across all hosts. */
+  maxInFlight?: number
+  /** Most requests in flight at once to any single host. */
+  maxPerHost?: number
+  /** Limits for specific hosts, overriding `maxPerHost`. */
+  hosts?: Record<string, number>
+  /** Milliseconds a request may wait in the queue before failing with `ERR_NITAI_QUEUE_TIMEOUT`. */
+  queueTimeout?: number
+}
+
+export interface ConcurrencyStats {
+  inFlight: number
+  queued: number
+  /** Hosts with requests in flight or queued. */
+  hosts: Record<string, HostConcurrencyStats>
+}
+
+export interface CookieEntry {
+  name: string
+  value: string
+  domain?: string
+  path?: string
+}
+
 /** use this instead of delete because delete is a reserved keyword in JavaScript */
 export declare function delete_(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
+/** A file written by `client.download`. */
+export interface DownloadEntry {
+  /** Final URL, after redirects. */
+  url: string
+  status: number
+  headers: Record<string, Array<string>>
+  path: string
+  /** Size of the file in bytes. */
+  size: number
+  /** Whether the download continued a partial file instead of starting over. */
+  resumed: boolean
+}
+
+export interface DownloadOptions {
+  /**
+   * Called as chunks are written to disk, at most every 100 ms, and once more when the whole
+   * body is written.
+   */
+  onProgress?: (progress: DownloadProgressEntry) => void
+}
+
+export interface DownloadProgressEntry {
+  /** Bytes in the file so far, including those kept from an interrupted download. */
+  downloaded: number
+  /** Size of the whole file, or `null` when the server did not announce it. */
+  total?: number
+}
+
 export interface EmulationOptions {
   preset?: string
   os?: string
@@ -145,14 +499,182 @@ export interface EmulationOptions {
 
 export declare function get(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
+/** HAR recording settings. */
+export interface HarOptions {
+  /**
+   * File the archive is written to by `saveHar()` without a path, and when the client is
+   * garbage collected.
+   */
+  path?: string
+  /** Bytes of each request and response body to keep. Longer bodies are truncated. */
+  maxBodySize?: number
+}
+
 export declare function head(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
+export interface HookErrorEntry {
+  /** The message the request would fail with, starting with its `ERR_NITAI_*` code. */
+  message: string
+}
+
+/** A response made up in place of the server's. */
+export interface HookReplyInit {
+  /** Defaults to 200. */
+  status?: number
+  headers?: Record<string, string | Array<string>> | Array<[string, string]>
+  body?: string | Buffer
+  /** Sent as the body, with `content-type: application/json` unless a content type is set. */
+  json?: any
+}
+
+/** A request about to be sent, as the client resolved it. */
+export interface HookRequestEntry {
+  method: string
+  url: string
+  /** Headers set on the request; the client's defaults and emulation headers are added after. */
+  headers: Record<string, Array<string>>
+  /** The request body, or `null` when it is empty or streamed. */
+  body?: Buffer
+}
+
+/** A response as hooks see it. The body is left for the caller to read. */
+export interface HookResponseEntry {
+  status: number
+  url: string
+  headers: Record<string, Array<string>>
+}
+
+export interface HostConcurrencyStats {
+  inFlight: number
+  queued: number
+}
+
+/** A `1xx` response received before the final one, such as `103 Early Hints`. */
+export interface InformationalEntry {
+  status: number
+  headers: Record<string, Array<string>>
+}
+
+export interface LinkEntry {
+  uri: string
+  rel: Array<string>
+  params: Record<string, string>
+}
+
+/** One part of a `multipart/form-data` body. Exactly one of `value`, `data` or `path` must be set. */
+export interface MockCallEntry {
+  method: string
+  url: string
+  /** Headers as sent, including the client's defaults and emulation headers. */
+  headers: Record<string, Array<string>>
+  /** The request body, unless it was empty or streamed. */
+  body?: Buffer
+  /**
+   * Index of the route that answered, in the order routes were added, or `null` when none
+   * matched.
+   */
+  route?: number
+}
+
+/** What a request must look like to match a route. Unset fields match anything. */
+export interface MockMatcherInit {
+  method?: string
+  /**
+   * A glob over the whole URL, where `*` stays within a path segment and `**` does not, or a
+   * regular expression.
+   */
+  url?: string | RegExp
+  /** Headers the request must carry with exactly these values. */
+  headers?: Record<string, string | Array<string>> | Array<[string, string]>
+}
+
+export interface MockOptions {
+  /**
+   * Send requests no route matches over the network instead of failing them with
+   * `ERR_NITAI_MOCK`.
+   */
+  passthrough?: boolean
+}
+
+export interface MockReplyInit {
+  /** Defaults to 200. */
+  status?: number
+  headers?: Record<string, string | Array<string>> | Array<[string, string]>
+  body?: string | Buffer
+  /** Sent as the body, with `content-type: application/json` unless a content type is set. */
+  json?: any
+  /**
+   * Milliseconds to wait before answering. A delay past the request's `timeout` or `readTimeout`
+   * fails it the way a slow server would, and aborting the request ends the wait.
+   */
+  delay?: number
+  /**
+   * Fail with a transport error instead of answering: `ERR_NITAI_TIMEOUT` once the request's
+   * timeout runs out, `ERR_NITAI_CONNECTION_RESET`, or `ERR_NITAI_CONNECT` for `refused` and
+   * `tls`.
+   */
+  error?: 'timeout' | 'reset' | 'refused' | 'tls'
+  /** Times the route answers before it stops matching; unlimited by default. */
+  times?: number
+}
+
+/** A regular expression, such as a `RegExp`. Only the `i` flag is honoured. */
+export interface MockUrlPattern {
+  source: string
+  flags?: string
+}
+
+export interface MultipartField {
+  name: string
+  /** Text value of a plain form field. */
+  value?: string
+  /** File contents, either in memory or as a stream of chunks. */
+  data?: Buffer | AsyncIterable<Buffer | Uint8Array | string> | ReadableStream
+  /** Path of a file streamed from disk. */
+  path?: string
+  /** Defaults to the file name of `path`. */
+  filename?: string
+  contentType?: string
+  /** Size in bytes of a streamed `data`, so the form can be sent with a `Content-Length`. */
+  length?: number
+  headers?: Record<string, string | Array<string>>
+}
+
 export declare function options(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
+export interface PaginateOptions {
+  /** Stop after this many pages. */
+  maxPages?: number
+  /** Computes the next page from the current one instead of following `rel="next"` links. */
+  cursor?: (page: ResponseHandle) => string | Record<string, string> | null | undefined | Promise<string | Record<string, string> | null | undefined>
+}
+
+/** How nested `query` and `form` values are flattened, and how spaces are encoded. */
+export interface ParamsEncodingOptions {
+  /**
+   * `"repeat"` (`a=1&a=2`, default), `"brackets"` (`a[]=1`), `"indices"` (`a[0]=1`) or
+   * `"comma"` (`a=1,2`).
+   */
+  arrayFormat?: string
+  /** `"brackets"` (`a[b]=1`, default) or `"dots"` (`a.b=1`). */
+  objectFormat?: string
+  /** `"plus"` (default) or `"percent"` (`%20`). */
+  spaces?: string
+}
+
 export declare function patch(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
 export declare function post(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
+export interface PreparedBodyEntry {
+  /** `"none"`, `"buffered"` (JSON, forms and buffers) or `"stream"` (streams and multipart). */
+  kind: string
+  /** Length in bytes of a buffered body. */
+  size?: number
+  /** Contents of a buffered body. */
+  bytes?: Buffer
+}
+
 export interface ProxyConfig {
   uri: string
   username?: string
@@ -161,22 +683,111 @@ export interface ProxyConfig {
 
 export declare function put(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
+/** Pacing of requests sent through a client, tracked separately for each host. */
+export interface RateLimitOptions {
+  /** Requests allowed per `per` milliseconds for hosts without an override. */
+  requests?: number
+  /** Length of the window in milliseconds. Defaults to 1000. */
+  per?: number
+  /** Requests that may be sent back to back after an idle period. Defaults to 1. */
+  burst?: number
+  /** Limits for specific hosts, or for keys passed as `rateLimitKey`. */
+  hosts?: Record<string, RateLimitRule>
+  /** Smallest random delay in milliseconds added before every request. Defaults to 0. */
+  minDelay?: number
+  /** Largest random delay in milliseconds added before every request. Defaults to `minDelay`. */
+  maxDelay?: number
+}
+
+/** Token bucket limit for requests to a host. */
+export interface RateLimitRule {
+  /** Requests allowed per `per` milliseconds. */
+  requests: number
+  /** Length of the window in milliseconds. Defaults to 1000. */
+  per?: number
+  /** Requests that may be sent back to back after an idle period. Defaults to 1. */
+  burst?: number
+}
+
 export interface RedirectHistoryEntry {
+  /** `"http"`, `"refresh"` (a `Refresh` header) or `"meta"` (a meta refresh tag). */
+  kind: string
   status: number
   uri: string
   previous: string
+  /**
+   * Headers of the redirecting response; empty unless the hop was followed on its own
+   * (see `detailedHistory`).
+   */
+  headers: Record<string, Array<string>>
+  /** Cookies set by the redirecting response. */
+  cookies: Array<CookieEntry>
+  remoteAddr?: string
+  /** Milliseconds until the redirecting response's headers arrived. */
+  elapsed?: number
+}
+
+/** Redirect handling beyond `allowRedirects`; redirects are then followed one hop at a time. */
+export interface RedirectOptions {
+  /** Stop at redirects to another scheme, host or port. Defaults to `false`. */
+  sameOriginOnly?: boolean
+  /** Stop at redirects from `https` to `http`. Defaults to `false`. */
+  denyDowngrade?: boolean
+  /**
+   * Drop `Authorization`, `Proxy-Authorization` and `Cookie` headers on cross-origin hops.
+   * Defaults to `true`.
+   */
+  stripSensitiveHeaders?: boolean
+  /** Switch `POST` to a body-less `GET` on 301 and 302, as browsers do. Defaults to `true`. */
+  rewritePost?: boolean
+  /**
+   * Called for every hop with a `RedirectStep`. Return (or resolve with) `'follow'`, `'stop'`
+   * to return the redirect response, or `{ url, method, headers }` to follow with changes.
+   */
+  onRedirect?: (step: RedirectStepEntry) => unknown
+}
+
+/** A redirect about to be followed. */
+export interface RedirectStepEntry {
+  hop: number
+  status: number
+  from: string
+  to: string
+  /** Method of the next request. */
+  method: string
+  /** Headers of the next request, after sensitive ones were stripped. */
+  headers: Record<string, Array<string>>
 }
 
 export declare function request(url: string, init?: RequestInit | undefined | null): Promise<ResponseHandle>
 
 export interface RequestInit {
   method?: string
-  headers?: Record<string, string | Array<string>>
+  headers?: Record<string, string | Array<string>> | Array<[string, string]>
+  /** Header names in wire order, which may include the emulation's default headers. */
+  headerOrder?: Array<string>
   defaultHeaders?: boolean
   cookies?: Array<string>
   emulation?: string | EmulationOptions
   allowRedirects?: boolean
   maxRedirects?: number
+  /** Also follow `Refresh` headers and `<meta http-equiv="refresh">` tags. */
+  followRefresh?: boolean
+  /** Checks and rewrites every redirect hop, overriding the client's `redirect`. */
+  redirect?: RedirectOptions
+  /**
+   * Follow redirects one hop at a time so `history()` includes each hop's headers, cookies,
+   * address and timing.
+   */
+  detailedHistory?: boolean
+  /** Overrides the client's retry policy; `false` disables retries. */
+  retry?: boolean | RetryOptions
+  /** Rate limit bucket for the request instead of the URL host. */
+  rateLimitKey?: string
+  /** Position in the client's queue; higher values are sent first. Defaults to 0. */
+  priority?: number
+  /** Milliseconds to wait in the client's queue, overriding its `queueTimeout`. */
+  queueTimeout?: number
   gzip?: boolean
   brotli?: boolean
   deflate?: boolean
@@ -184,20 +795,84 @@ export interface RequestInit {
   auth?: string
   bearerAuth?: string
   basicAuth?: BasicAuth
-  query?: Record<string, string | Array<string>>
-  form?: Record<string, string | Array<string>>
+  /** Query parameters as an object or ordered `[key, value]` pairs; values may be nested. */
+  query?: Record<string, unknown> | Array<[string, unknown]>
+  /** Form fields as an object or ordered `[key, value]` pairs; values may be nested. */
+  form?: Record<string, unknown> | Array<[string, unknown]>
+  paramsEncoding?: ParamsEncodingOptions
   json?: any
-  body?: string | Buffer
+  /** Raw request body. Only one of `body`, `json`, `form` and `multipart` may be given. */
+  body?: string | Buffer | Uint8Array | AsyncIterable<Buffer | Uint8Array | string> | ReadableStream
+  /**
+   * Size in bytes of a streamed `body`, sent as `Content-Length` instead of chunked encoding.
+   * A string or Buffer `body` is always sent with its own size, which this must then match.
+   */
+  bodyLength?: number
+  /** Trailing headers sent after the body, which is then transferred with chunked encoding. */
+  trailers?: Record<string, string | Array<string>>
+  /** Parts of a `multipart/form-data` body, sent in order. */
+  multipart?: Array<MultipartField>
+  /**
+   * Send `Expect: 100-continue` and hold the body back until the server answers `100 Continue`:
+   * `true` waits up to a second for the answer, a number that many milliseconds.
+   */
+  expectContinue?: boolean | number
+  /**
+   * Called with every `1xx` response received before the final one, such as `103 Early Hints`.
+   * Interim responses are only reported over HTTP/1.1.
+   */
+  onInformational?: (response: InformationalEntry) => void
   timeout?: number
   readTimeout?: number
   version?: string
   proxy?: ProxyConfig
   localAddress?: string
   interface?: string
+  /** Cancels the request, including reads of its body, when aborted. */
+  signal?: AbortSignal
+}
+
+export interface RetryAttemptEntry {
+  attempt: number
+  status?: number
+  error?: string
+  /** Milliseconds waited before the next attempt. */
+  delay: number
+}
+
+/** Retry policy; unset fields keep their defaults. */
+export interface RetryOptions {
+  /** Total attempts, including the first one. Defaults to 3. */
+  maxAttempts?: number
+  /** Retry when the connection could not be established. Defaults to `true`. */
+  retryConnectErrors?: boolean
+  /** Retry when the request timed out. Defaults to `true`. */
+  retryTimeouts?: boolean
+  /** Retry responses with these status codes. Defaults to 408, 429, 502, 503 and 504. */
+  statuses?: Array<number>
+  /** Only retry `GET`, `HEAD`, `OPTIONS`, `PUT`, `DELETE` and `TRACE`. Defaults to `true`. */
+  idempotentOnly?: boolean
+  /** Delay before the first retry in milliseconds. Defaults to 100. */
+  baseDelay?: number
+  /** Growth factor of the delay between retries. Defaults to 2. */
+  multiplier?: number
+  /** Upper bound of the backoff delay in milliseconds. Defaults to 10000. */
+  maxDelay?: number
+  /** Fraction of each delay that is randomized, from 0 to 1. Defaults to 0.5. */
+  jitter?: number
+  /** Wait as long as a `Retry-After` header asks. Defaults to `true`. */
+  respectRetryAfter?: boolean
+  /**
+   * Give up instead of waiting when `Retry-After` asks for more milliseconds than this.
+   * Defaults to 60000.
+   */
+  maxRetryAfter?: number
 }
 
 export interface WebSocketInit {
-  headers?: Record<string, string | Array<string>>
+  headers?: Record<string, string | Array<string>> | Array<[string, string]>
+  /** Header names in wire order, which may include the emulation's default headers. */
+  headerOrder?: Array<string>
   defaultHeaders?: boolean
   cookies?: Array<string>
   emulation?: string | EmulationOptions
@@ -216,4 +891,6 @@ export interface WebSocketInit {
   proxy?: ProxyConfig
   localAddress?: string
   interface?: string
+  /** Cancels the request, including reads of its body, when aborted. */
+  signal?: AbortSignal
 }
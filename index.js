@@ -558,6 +558,7 @@ if (!nativeBinding) {
 
 module.exports = nativeBinding
 module.exports.Client = nativeBinding.Client
+module.exports.Paginator = nativeBinding.Paginator
 module.exports.ResponseHandle = nativeBinding.ResponseHandle
 module.exports.delete_ = nativeBinding.delete_
 module.exports.get = nativeBinding.get
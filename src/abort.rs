@@ -0,0 +1,83 @@
+use napi::bindgen_prelude::{
+  spawn, FromNapiValue, Function, Promise, Result, TypeName, Unknown, ValidateNapiValue,
+};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{sys, Env, Status, ValueType};
+use nitai_bindings_core::abort::AbortSignal;
+
+/// Returns a promise that resolves with a description of the signal's reason once it aborts,
+/// and a function that removes the listener again, resolving the promise with `null`.
+const ADAPTER: &str = r#"(signal) => {
+  if (signal === null || typeof signal !== 'object' || typeof signal.addEventListener !== 'function' || typeof signal.aborted !== 'boolean') {
+    throw new TypeError('signal must be an AbortSignal')
+  }
+  const describe = (reason) => {
+    if (reason instanceof Error) return reason.message ? `${reason.name}: ${reason.message}` : reason.name
+    return String(reason)
+  }
+  let release = () => {}
+  const aborted = new Promise((resolve) => {
+    if (signal.aborted) {
+      resolve(describe(signal.reason))
+      return
+    }
+    const onAbort = () => resolve(describe(signal.reason))
+    signal.addEventListener('abort', onAbort, { once: true })
+    release = () => {
+      signal.removeEventListener('abort', onAbort)
+      resolve(null)
+    }
+  })
+  return [aborted, release]
+}"#;
+
+// Weak so that a request waiting to be released does not keep the process alive.
+type ReleaseFn = ThreadsafeFunction<(), (), (), Status, false, true>;
+
+/// A JavaScript `AbortSignal`, bridged to a core [`AbortSignal`].
+pub struct JsAbortSignal {
+  aborted: Promise<Option<String>>,
+  release: ReleaseFn,
+}
+
+impl JsAbortSignal {
+  /// Bridge to a core signal. The listener on the JavaScript signal is removed once the request
+  /// is done with the core one, so a reused `AbortController` doesn't collect a listener per
+  /// request.
+  pub(crate) fn into_signal(self) -> AbortSignal {
+    let signal = AbortSignal::new();
+    let trigger = signal.handle();
+    let Self { aborted, release } = self;
+    spawn(async move {
+      if let Ok(Some(reason)) = aborted.await {
+        trigger.abort(reason);
+      }
+    });
+    signal.on_release(move || {
+      release.call((), ThreadsafeFunctionCallMode::NonBlocking);
+    });
+    signal
+  }
+}
+
+impl TypeName for JsAbortSignal {
+  fn type_name() -> &'static str {
+    "AbortSignal"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Object
+  }
+}
+
+impl ValidateNapiValue for JsAbortSignal {}
+
+impl FromNapiValue for JsAbortSignal {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> Result<Self> {
+    let signal = Unknown::from_napi_value(env, napi_val)?;
+    let adapter: Function<Unknown, (Promise<Option<String>>, ReleaseFn)> =
+      Env::from_raw(env).run_script(ADAPTER)?;
+    let (aborted, release) = adapter.call(signal)?;
+    Ok(Self { aborted, release })
+  }
+}
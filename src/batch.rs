@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{AsyncGenerator, Buffer, Either, Result};
+use napi_derive::napi;
+use nitai_bindings_core::batch::{Batch, BatchRequest, BatchResult, DEFAULT_BATCH_CONCURRENCY};
+use nitai_bindings_core::client::Client as CoreClient;
+use tokio::sync::Mutex;
+use wreq::Method;
+
+use crate::error::napi_error_for;
+use crate::request_options::{napi_invalid, ParsedRequest, RequestInit};
+use crate::response_handle::flatten_headers;
+
+#[napi(object, object_to_js = false)]
+pub struct BatchRequestInit {
+  pub url: String,
+  pub init: Option<RequestInit>,
+}
+
+#[napi(object)]
+pub struct BatchOptions {
+  /// Requests in flight at once. Defaults to 16.
+  pub concurrency: Option<u32>,
+}
+
+/// A finished request of a batch. Failed requests have `error` set and no response fields.
+#[napi(object)]
+pub struct BatchResultEntry {
+  /// Position of the request in the batch.
+  pub index: u32,
+  /// Final URL, after redirects.
+  pub url: String,
+  pub status: Option<u16>,
+  pub headers: Option<HashMap<String, Vec<String>>>,
+  pub body: Option<Buffer>,
+  /// The message the request failed with, starting with its `ERR_NITAI_*` code.
+  pub error: Option<String>,
+}
+
+/// Async iterator over the results of `client.batch`, in the order requests finish.
+///
+/// # Example
+///
+/// ```javascript
+/// for await (const result of client.batch(urls, { concurrency: 64 })) {
+///   if (result.error) console.error(result.url, result.error)
+/// }
+/// ```
+#[napi(async_iterator)]
+pub struct BatchIterator {
+  inner: Arc<Mutex<Batch>>,
+  urls: Arc<Vec<String>>,
+}
+
+impl BatchIterator {
+  pub(crate) fn new(
+    client: CoreClient,
+    requests: Vec<Either<String, BatchRequestInit>>,
+    options: Option<BatchOptions>,
+  ) -> Result<Self> {
+    let concurrency = match options.and_then(|options| options.concurrency) {
+      Some(0) => {
+        return Err(napi_invalid(
+          "batch concurrency must be at least 1".to_string(),
+        ))
+      }
+      Some(concurrency) => concurrency as usize,
+      None => DEFAULT_BATCH_CONCURRENCY,
+    };
+
+    let mut urls = Vec::with_capacity(requests.len());
+    let mut batch = Vec::with_capacity(requests.len());
+    for request in requests {
+      let (url, init) = match request {
+        Either::A(url) => (url, None),
+        Either::B(BatchRequestInit { url, init }) => (url, init),
+      };
+      let ParsedRequest { method, request } = match init {
+        Some(init) => init.parse()?,
+        None => ParsedRequest::default(),
+      };
+      urls.push(url.clone());
+      batch.push(BatchRequest {
+        method: method.unwrap_or(Method::GET),
+        url,
+        request,
+      });
+    }
+
+    Ok(Self {
+      inner: Arc::new(Mutex::new(Batch::new(Some(client), batch, concurrency))),
+      urls: Arc::new(urls),
+    })
+  }
+}
+
+#[napi]
+impl AsyncGenerator for BatchIterator {
+  type Yield = BatchResultEntry;
+  type Next = ();
+  type Return = ();
+
+  fn next(
+    &mut self,
+    _value: Option<Self::Next>,
+  ) -> impl Future<Output = Result<Option<Self::Yield>>> + Send + 'static {
+    let inner = Arc::clone(&self.inner);
+    let urls = Arc::clone(&self.urls);
+    async move {
+      let result = inner.lock().await.next().await;
+      Ok(result.map(|result| result_entry(result, &urls)))
+    }
+  }
+
+  fn complete(
+    &mut self,
+    value: Option<Self::Return>,
+  ) -> impl Future<Output = Result<Option<Self::Return>>> + Send + 'static {
+    let inner = Arc::clone(&self.inner);
+    async move {
+      inner.lock().await.cancel();
+      Ok(value)
+    }
+  }
+}
+
+fn result_entry(result: BatchResult, urls: &[String]) -> BatchResultEntry {
+  let index = result.index;
+  match result.outcome {
+    Ok((response, body)) => BatchResultEntry {
+      index: index as u32,
+      url: response.uri.to_string(),
+      status: Some(response.status.as_u16()),
+      headers: Some(flatten_headers(&response.headers)),
+      body: Some(body.to_vec().into()),
+      error: None,
+    },
+    Err(err) => BatchResultEntry {
+      index: index as u32,
+      url: urls[index].clone(),
+      status: None,
+      headers: None,
+      body: None,
+      error: Some(napi_error_for(&err).reason),
+    },
+  }
+}
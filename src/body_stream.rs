@@ -0,0 +1,107 @@
+use std::io;
+
+use bytes::Bytes;
+use napi::bindgen_prelude::{
+  Buffer, FromNapiValue, Function, Promise, Result, TypeName, Unknown, ValidateNapiValue,
+};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{sys, Env, Status, ValueType};
+use nitai_bindings_core::body::ProducerBody;
+
+/// Turns any supported source into a `pull(cancel)` function resolving to the next chunk as a
+/// Buffer, or `null` once the source is exhausted or cancelled.
+const ADAPTER: &str = r#"(source) => {
+  let iterator
+  if (ArrayBuffer.isView(source) || source instanceof ArrayBuffer) {
+    iterator = [source][Symbol.iterator]()
+  } else if (typeof source[Symbol.asyncIterator] === 'function') {
+    iterator = source[Symbol.asyncIterator]()
+  } else if (typeof source.getReader === 'function') {
+    const reader = source.getReader()
+    iterator = { next: () => reader.read(), return: () => reader.cancel() }
+  } else if (typeof source[Symbol.iterator] === 'function') {
+    iterator = source[Symbol.iterator]()
+  } else {
+    throw new TypeError('body stream must be an async iterable, iterable or ReadableStream')
+  }
+  const toBuffer = (chunk) => {
+    if (typeof chunk === 'string') return Buffer.from(chunk)
+    if (Buffer.isBuffer(chunk)) return chunk
+    if (ArrayBuffer.isView(chunk)) return Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength)
+    if (chunk instanceof ArrayBuffer) return Buffer.from(chunk)
+    throw new TypeError('body stream chunks must be strings, Buffers, typed arrays or ArrayBuffers')
+  }
+  return (cancel) => {
+    if (cancel) {
+      return Promise.resolve(typeof iterator.return === 'function' ? iterator.return() : undefined)
+        .catch(() => {})
+        .then(() => null)
+    }
+    return Promise.resolve(iterator.next()).then((result) => (result.done ? null : toBuffer(result.value)))
+  }
+}"#;
+
+type PullFn = ThreadsafeFunction<bool, Promise<Option<Buffer>>, bool, Status, false>;
+
+/// A JavaScript source of body chunks: a Node `Readable`, a WHATWG `ReadableStream`, any
+/// (async) iterable of strings, Buffers or typed arrays, or a single typed array.
+///
+/// Chunks are only pulled when the connection is ready for more data.
+pub struct BodyStream {
+  pull: PullFn,
+}
+
+impl BodyStream {
+  pub(crate) fn into_body(self, length: Option<u64>) -> ProducerBody {
+    let pull = self.pull;
+    ProducerBody::new(length, move |tx| async move {
+      loop {
+        let chunk = match pull.call_async(false).await {
+          Ok(promise) => promise.await,
+          Err(err) => Err(err),
+        };
+
+        match chunk {
+          Ok(Some(buffer)) => {
+            if tx
+              .send(Ok(Bytes::copy_from_slice(buffer.as_ref())))
+              .await
+              .is_err()
+            {
+              // The request went away; let the source release its resources.
+              pull.call(true, ThreadsafeFunctionCallMode::NonBlocking);
+              break;
+            }
+          }
+          Ok(None) => break,
+          Err(err) => {
+            let _ = tx.send(Err(io::Error::other(err.reason))).await;
+            break;
+          }
+        }
+      }
+    })
+  }
+}
+
+impl TypeName for BodyStream {
+  fn type_name() -> &'static str {
+    "AsyncIterable<Buffer>"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Object
+  }
+}
+
+impl ValidateNapiValue for BodyStream {}
+
+impl FromNapiValue for BodyStream {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> Result<Self> {
+    let source = Unknown::from_napi_value(env, napi_val)?;
+    let adapter: Function<Unknown, PullFn> = Env::from_raw(env).run_script(ADAPTER)?;
+    Ok(Self {
+      pull: adapter.call(source)?,
+    })
+  }
+}
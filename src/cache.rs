@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{Either, Result as NapiResult};
+use napi_derive::napi;
+use nitai_bindings_core::client::{CacheStatus, HttpCache};
+
+use crate::error::to_napi_error;
+use crate::request_options::napi_invalid;
+
+/// HTTP cache settings.
+#[napi(object)]
+pub struct CacheOptions {
+  /// Directory holding the cache, created when missing. Without it responses are kept in memory.
+  pub path: Option<String>,
+  /// URLs kept in memory before the least recently stored is dropped. Defaults to 1000.
+  pub max_entries: Option<u32>,
+  /// Largest body, in bytes, a stored response may have; larger ones are streamed without being
+  /// stored. Defaults to 10 MiB.
+  pub max_body_size: Option<u32>,
+}
+
+/// `true` caches in memory with the defaults and `false` leaves caching off.
+pub(crate) fn parse_cache(
+  options: Either<bool, CacheOptions>,
+) -> NapiResult<Option<Arc<HttpCache>>> {
+  let options = match options {
+    Either::A(true) => return Ok(Some(Arc::new(HttpCache::memory()))),
+    Either::A(false) => return Ok(None),
+    Either::B(options) => options,
+  };
+
+  let mut cache = match options.path {
+    Some(path) => HttpCache::disk(path).map_err(to_napi_error)?,
+    None => HttpCache::memory(),
+  };
+  if let Some(max_entries) = options.max_entries {
+    if max_entries == 0 {
+      return Err(napi_invalid(
+        "cache maxEntries must be at least 1".to_string(),
+      ));
+    }
+    cache = cache.with_max_entries(max_entries as usize);
+  }
+  if let Some(max_body_size) = options.max_body_size {
+    cache = cache.with_max_body_size(max_body_size as usize);
+  }
+  Ok(Some(Arc::new(cache)))
+}
+
+pub(crate) fn cache_status_name(status: CacheStatus) -> &'static str {
+  match status {
+    CacheStatus::Hit => "hit",
+    CacheStatus::Stale => "stale",
+    CacheStatus::Revalidated => "revalidated",
+    CacheStatus::Miss => "miss",
+  }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use http::HeaderName;
+use napi::bindgen_prelude::Result as NapiResult;
+use napi_derive::napi;
+use nitai_bindings_core::client::{Cassette, CassetteMode};
+
+use crate::error::to_napi_error;
+use crate::request_options::napi_invalid;
+
+/// A HAR file of recorded exchanges that answers requests in place of the network.
+#[napi(object)]
+pub struct CassetteOptions {
+  pub path: String,
+  /// `"replay"` (the default) fails requests without a recording with `ERR_NITAI_CASSETTE`,
+  /// `"recordMissing"` sends and records them, and `"record"` sends and records every request.
+  #[napi(ts_type = "'replay' | 'recordMissing' | 'record'")]
+  pub mode: Option<String>,
+  /// Whether requests must have the recorded body to match. Defaults to `true`.
+  pub match_body: Option<bool>,
+  /// Serve each recording once instead of repeating the last match, so a request whose
+  /// recordings were all served counts as unmatched.
+  pub strict: Option<bool>,
+  /// Request headers whose values are replaced with `[redacted]` when recording. Defaults to
+  /// `authorization`, `proxy-authorization` and `cookie`.
+  pub redact_headers: Option<Vec<String>>,
+}
+
+pub(crate) fn parse_cassette(options: CassetteOptions) -> NapiResult<Arc<Cassette>> {
+  let mode = match options.mode.as_deref() {
+    None | Some("replay") => CassetteMode::Replay,
+    Some("recordMissing") => CassetteMode::RecordMissing,
+    Some("record") => CassetteMode::Record,
+    Some(other) => return Err(napi_invalid(format!("unsupported cassette mode: {other}"))),
+  };
+
+  let mut cassette = Cassette::open(options.path, mode)
+    .map_err(to_napi_error)?
+    .with_match_body(options.match_body.unwrap_or(true))
+    .with_strict(options.strict.unwrap_or(false));
+  if let Some(names) = options.redact_headers {
+    let names = names
+      .iter()
+      .map(|name| {
+        HeaderName::from_bytes(name.as_bytes())
+          .map_err(|_| napi_invalid(format!("invalid header name to redact: {name}")))
+      })
+      .collect::<NapiResult<_>>()?;
+    cassette = cassette.with_redacted_headers(names);
+  }
+  Ok(Arc::new(cassette))
+}
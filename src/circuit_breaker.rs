@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use http::StatusCode;
+use napi::bindgen_prelude::{Either, Result as NapiResult};
+use napi_derive::napi;
+use nitai_bindings_core::client::{
+  CircuitBreaker, CircuitBreakerPolicy, CircuitState, CircuitStatus,
+};
+
+use crate::request_options::{duration_from_millis, napi_invalid};
+use crate::retry::millis;
+
+/// Circuit breaker settings; unset fields keep their defaults.
+#[napi(object)]
+pub struct CircuitBreakerOptions {
+  /// Share of failed requests that opens a host's circuit, from 0 to 1. Defaults to 0.5.
+  pub failure_threshold: Option<f64>,
+  /// Requests within the window needed before the failure rate is considered. Defaults to 5.
+  pub minimum_requests: Option<u32>,
+  /// Milliseconds of history used for the failure rate. Defaults to 60000.
+  pub window: Option<u32>,
+  /// Milliseconds an open circuit rejects requests before trying again. Defaults to 30000.
+  pub cooldown: Option<u32>,
+  /// Trial requests allowed at once while half-open. Defaults to 1.
+  pub half_open_requests: Option<u32>,
+  /// Response statuses counted as failures. Defaults to 500, 502, 503 and 504.
+  pub statuses: Option<Vec<u16>>,
+}
+
+#[napi(object)]
+pub struct CircuitEntry {
+  pub host: String,
+  /// `closed`, `open` or `half-open`.
+  pub state: String,
+  /// Requests counted within the window.
+  pub requests: u32,
+  /// Failures counted within the window.
+  pub failures: u32,
+  /// Milliseconds until an open circuit lets a trial request through.
+  pub retry_in: Option<f64>,
+}
+
+/// `true` enables the default policy and `false` leaves the breaker off.
+pub(crate) fn parse_circuit_breaker(
+  options: Either<bool, CircuitBreakerOptions>,
+) -> NapiResult<Option<Arc<CircuitBreaker>>> {
+  let options = match options {
+    Either::A(true) => return Ok(Some(Arc::new(CircuitBreaker::default()))),
+    Either::A(false) => return Ok(None),
+    Either::B(options) => options,
+  };
+
+  let mut policy = CircuitBreakerPolicy::default();
+
+  if let Some(failure_threshold) = options.failure_threshold {
+    if !(failure_threshold > 0.0 && failure_threshold <= 1.0) {
+      return Err(napi_invalid(
+        "failureThreshold must be greater than 0 and at most 1".to_string(),
+      ));
+    }
+    policy.failure_threshold = failure_threshold;
+  }
+
+  if let Some(statuses) = options.statuses {
+    policy.statuses = statuses
+      .into_iter()
+      .map(|status| {
+        StatusCode::from_u16(status)
+          .map_err(|_| napi_invalid(format!("invalid circuit breaker status code {status}")))
+      })
+      .collect::<NapiResult<_>>()?;
+  }
+
+  if let Some(minimum_requests) = options.minimum_requests {
+    policy.minimum_requests = minimum_requests.max(1) as usize;
+  }
+
+  if let Some(window) = options.window {
+    policy.window = duration_from_millis(window);
+  }
+
+  if let Some(cooldown) = options.cooldown {
+    policy.cooldown = duration_from_millis(cooldown);
+  }
+
+  if let Some(half_open_requests) = options.half_open_requests {
+    policy.half_open_requests = half_open_requests.max(1);
+  }
+
+  Ok(Some(Arc::new(CircuitBreaker::new(policy))))
+}
+
+impl From<CircuitStatus> for CircuitEntry {
+  fn from(status: CircuitStatus) -> Self {
+    let state = match status.state {
+      CircuitState::Closed => "closed",
+      CircuitState::Open => "open",
+      CircuitState::HalfOpen => "half-open",
+    };
+    Self {
+      host: status.host,
+      state: state.to_string(),
+      requests: status.requests as u32,
+      failures: status.failures as u32,
+      retry_in: status.retry_in.map(millis),
+    }
+  }
+}
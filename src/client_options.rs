@@ -1,24 +1,80 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 
 use napi::bindgen_prelude::{Either, Result as NapiResult};
 use napi_derive::napi;
-use nitai_bindings_core::client::{ClientBuilder, TlsVerification};
+use nitai_bindings_core::client::{ClientBuilder, SingleFlight, TlsVerification};
 use wreq::tls;
 
+use crate::cache::{parse_cache, CacheOptions};
+use crate::cassette::{parse_cassette, CassetteOptions};
+use crate::circuit_breaker::{parse_circuit_breaker, CircuitBreakerOptions};
+use crate::concurrency::{parse_concurrency, ConcurrencyOptions};
 use crate::emulation::{parse_optional_emulation, EmulationOptions};
+use crate::har::{parse_har, HarOptions};
+use crate::hooks::{parse_hooks, HookHandler};
+use crate::mock::{parse_mock, MockOptions};
+use crate::rate_limit::{parse_rate_limit, RateLimitOptions};
+use crate::redirect::{parse_redirect, RedirectOptions};
 use crate::request_options::{
-  convert_header_map, duration_from_millis, napi_invalid, parse_ip, parse_proxy, ProxyConfig,
+  convert_headers, duration_from_millis, napi_invalid, order_headers, parse_ip, parse_proxy,
+  HeadersInit, ProxyConfig,
 };
+use crate::retry::{parse_retry, RetryOptions};
 
-#[napi(object)]
+#[napi(object, object_to_js = false)]
 pub struct ClientInit {
   pub emulation: Option<Either<String, EmulationOptions>>,
   pub user_agent: Option<String>,
-  pub headers: Option<HashMap<String, Either<String, Vec<String>>>>,
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+  /// Header names in wire order, which may include the emulation's default headers.
+  pub header_order: Option<Vec<String>>,
   pub referer: Option<bool>,
   pub history: Option<bool>,
   pub allow_redirects: Option<bool>,
   pub max_redirects: Option<u32>,
+  /// Also follow `Refresh` headers and `<meta http-equiv="refresh">` tags.
+  pub follow_refresh: Option<bool>,
+  /// Checks and rewrites every redirect hop; ignored when `allowRedirects` is `false`.
+  pub redirect: Option<RedirectOptions>,
+  /// Follow redirects one hop at a time so `history()` includes each hop's headers, cookies,
+  /// address and timing.
+  pub detailed_history: Option<bool>,
+  /// Retry policy applied to every request; `true` uses the defaults.
+  pub retry: Option<Either<bool, RetryOptions>>,
+  /// Paces requests per host; shared by every request sent through the client.
+  pub rate_limit: Option<RateLimitOptions>,
+  /// Caps requests in flight through the client, queueing the rest.
+  pub concurrency: Option<ConcurrencyOptions>,
+  /// Fails requests fast with `ERR_NITAI_CIRCUIT_OPEN` for hosts that keep failing.
+  pub circuit_breaker: Option<Either<bool, CircuitBreakerOptions>>,
+  /// Records every exchange as a HAR 1.2 archive, read with `har()`; `true` keeps it in memory.
+  pub har: Option<Either<bool, HarOptions>>,
+  /// Answers requests from a recorded HAR file instead of the network.
+  pub cassette: Option<CassetteOptions>,
+  /// Answers requests with replies registered through `client.mock`; `true` fails requests no
+  /// route matches.
+  pub mock: Option<Either<bool, MockOptions>>,
+  /// Called before every request is sent, including retries and redirect hops. Change the
+  /// request in place or return (or resolve with) the request to send; fields left out of a
+  /// returned request keep their value.
+  #[napi(ts_type = "(request: HookRequestEntry) => unknown")]
+  pub before_request: Option<HookHandler>,
+  /// Called with every response. Return (or resolve with) nothing to keep it, `'retry'` to send
+  /// the request again or `{ status, headers, body, json }` to answer with that instead.
+  #[napi(ts_type = "(response: HookResponseEntry, request: HookRequestEntry) => unknown")]
+  pub after_response: Option<HookHandler>,
+  /// Called when a request fails, returning what `afterResponse` does.
+  #[napi(ts_type = "(error: HookErrorEntry, request: HookRequestEntry) => unknown")]
+  pub on_error: Option<HookHandler>,
+  /// Send identical `GET` and `HEAD` requests in flight at the same time once, giving every
+  /// caller a response over the same buffered body. Responses over 10 MiB are not buffered, and
+  /// each waiting caller sends its own request instead. Requests with their own timeouts, retry,
+  /// priority, rate limit key, proxy, emulation or redirect policy are always sent alone.
+  pub single_flight: Option<bool>,
+  /// Keeps responses to `GET` requests and answers from them while they are fresh, following
+  /// `Cache-Control`, `Expires` and `Vary`; `true` keeps them in memory.
+  pub cache: Option<Either<bool, CacheOptions>>,
   pub cookie_store: Option<bool>,
   pub timeout: Option<u32>,
   pub connect_timeout: Option<u32>,
@@ -62,14 +118,42 @@ impl ClientInit {
       builder.user_agent = Some(user_agent);
     }
 
+    let mut header_names = Vec::new();
     if let Some(headers) = self.headers {
-      builder.headers = Some(convert_header_map(headers)?);
+      let (headers, names) = convert_headers(headers)?;
+      builder.headers = Some(headers);
+      header_names = names;
     }
+    builder.orig_headers = order_headers(header_names, self.header_order)?;
 
     builder.referer = self.referer;
     builder.history = self.history;
     builder.allow_redirects = self.allow_redirects;
     builder.max_redirects = self.max_redirects.map(|v| v as usize);
+    builder.follow_refresh = self.follow_refresh;
+    builder.redirect = self.redirect.map(parse_redirect);
+    builder.detailed_history = self.detailed_history;
+    builder.retry = self.retry.map(parse_retry).transpose()?;
+    builder.rate_limiter = self.rate_limit.map(parse_rate_limit).transpose()?;
+    builder.concurrency = self.concurrency.map(parse_concurrency).transpose()?;
+    builder.circuit_breaker = self
+      .circuit_breaker
+      .map(parse_circuit_breaker)
+      .transpose()?
+      .flatten();
+    builder.har = self.har.and_then(parse_har);
+    builder.cassette = self.cassette.map(parse_cassette).transpose()?;
+    builder.mock = self.mock.and_then(parse_mock);
+    builder.middleware.extend(parse_hooks(
+      self.before_request,
+      self.after_response,
+      self.on_error,
+    ));
+    builder.single_flight = self
+      .single_flight
+      .filter(|enabled| *enabled)
+      .map(|_| Arc::new(SingleFlight::new()));
+    builder.cache = self.cache.map(parse_cache).transpose()?.flatten();
     builder.cookie_store = self.cookie_store;
 
     if let Some(timeout) = self.timeout {
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use napi::bindgen_prelude::Result as NapiResult;
+use napi_derive::napi;
+use nitai_bindings_core::client::{ConcurrencyLimiter, ConcurrencyStats as CoreStats};
+
+use crate::request_options::{duration_from_millis, napi_invalid};
+
+/// Caps on requests in flight through a client; requests over a cap wait in a queue.
+#[napi(object)]
+pub struct ConcurrencyOptions {
+  /// Most requests in flight at once across all hosts.
+  pub max_in_flight: Option<u32>,
+  /// Most requests in flight at once to any single host.
+  pub max_per_host: Option<u32>,
+  /// Limits for specific hosts, overriding `maxPerHost`.
+  pub hosts: Option<HashMap<String, u32>>,
+  /// Milliseconds a request may wait in the queue before failing with `ERR_NITAI_QUEUE_TIMEOUT`.
+  pub queue_timeout: Option<u32>,
+}
+
+#[napi(object)]
+pub struct HostConcurrencyStats {
+  pub in_flight: u32,
+  pub queued: u32,
+}
+
+#[napi(object)]
+pub struct ConcurrencyStats {
+  pub in_flight: u32,
+  pub queued: u32,
+  /// Hosts with requests in flight or queued.
+  pub hosts: HashMap<String, HostConcurrencyStats>,
+}
+
+pub(crate) fn parse_concurrency(options: ConcurrencyOptions) -> NapiResult<ConcurrencyLimiter> {
+  let positive = |value: Option<u32>, name: &str| match value {
+    Some(0) => Err(napi_invalid(format!("{name} must be at least 1"))),
+    value => Ok(value.map(|value| value as usize)),
+  };
+
+  let mut limiter = ConcurrencyLimiter::new(
+    positive(options.max_in_flight, "maxInFlight")?,
+    positive(options.max_per_host, "maxPerHost")?,
+  );
+
+  for (host, limit) in options.hosts.unwrap_or_default() {
+    let limit = positive(Some(limit), "host concurrency limit")?.unwrap_or(1);
+    limiter = limiter.with_host_limit(host, limit);
+  }
+
+  if let Some(queue_timeout) = options.queue_timeout {
+    limiter = limiter.with_queue_timeout(duration_from_millis(queue_timeout));
+  }
+
+  Ok(limiter)
+}
+
+impl From<CoreStats> for ConcurrencyStats {
+  fn from(stats: CoreStats) -> Self {
+    Self {
+      in_flight: stats.in_flight as u32,
+      queued: stats.queued as u32,
+      hosts: stats
+        .hosts
+        .into_iter()
+        .map(|(host, counts)| {
+          (
+            host,
+            HostConcurrencyStats {
+              in_flight: counts.in_flight as u32,
+              queued: counts.queued as u32,
+            },
+          )
+        })
+        .collect(),
+    }
+  }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{
+  FromNapiValue, Function, Result as NapiResult, TypeName, Unknown, ValidateNapiValue,
+};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{sys, Env, Status, ValueType};
+use napi_derive::napi;
+use nitai_bindings_core::{Download, DownloadProgress, ProgressCallback};
+
+use crate::response_handle::flatten_headers;
+
+/// Calls the handler with each progress update. A throwing handler must not take the process
+/// down from a thread-safe call, so its errors are dropped.
+const ADAPTER: &str = r#"(handler) => {
+  if (typeof handler !== 'function') {
+    throw new TypeError('onProgress must be a function')
+  }
+  return (progress) => {
+    try {
+      handler(progress)
+    } catch {}
+  }
+}"#;
+
+#[napi(object)]
+pub struct DownloadProgressEntry {
+  /// Bytes in the file so far, including those kept from an interrupted download.
+  pub downloaded: f64,
+  /// Size of the whole file, or `null` when the server did not announce it.
+  pub total: Option<f64>,
+}
+
+#[napi(object, object_to_js = false)]
+pub struct DownloadOptions {
+  /// Called as chunks are written to disk, at most every 100 ms, and once more when the whole
+  /// body is written.
+  #[napi(ts_type = "(progress: DownloadProgressEntry) => void")]
+  pub on_progress: Option<ProgressHandler>,
+}
+
+/// A file written by `client.download`.
+#[napi(object)]
+pub struct DownloadEntry {
+  /// Final URL, after redirects.
+  pub url: String,
+  pub status: u16,
+  pub headers: HashMap<String, Vec<String>>,
+  pub path: String,
+  /// Size of the file in bytes.
+  pub size: f64,
+  /// Whether the download continued a partial file instead of starting over.
+  pub resumed: bool,
+}
+
+impl From<Download> for DownloadEntry {
+  fn from(download: Download) -> Self {
+    Self {
+      url: download.response.uri.to_string(),
+      status: download.response.status.as_u16(),
+      headers: flatten_headers(&download.response.headers),
+      path: download.path.to_string_lossy().into_owned(),
+      size: download.size as f64,
+      resumed: download.resumed,
+    }
+  }
+}
+
+// Weak so that a pending download does not keep the process alive on its own account.
+type ProgressFn =
+  ThreadsafeFunction<DownloadProgressEntry, (), DownloadProgressEntry, Status, false, true>;
+
+/// A JavaScript `onProgress` function.
+pub struct ProgressHandler {
+  call: ProgressFn,
+}
+
+pub(crate) fn parse_progress(options: Option<DownloadOptions>) -> Option<ProgressCallback> {
+  let handler = options?.on_progress?;
+  Some(Arc::new(move |progress: DownloadProgress| {
+    handler.call.call(
+      DownloadProgressEntry {
+        downloaded: progress.downloaded as f64,
+        total: progress.total.map(|total| total as f64),
+      },
+      ThreadsafeFunctionCallMode::NonBlocking,
+    );
+  }))
+}
+
+impl TypeName for ProgressHandler {
+  fn type_name() -> &'static str {
+    "(progress: DownloadProgressEntry) => void"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Function
+  }
+}
+
+impl ValidateNapiValue for ProgressHandler {}
+
+impl FromNapiValue for ProgressHandler {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> NapiResult<Self> {
+    let handler = Unknown::from_napi_value(env, napi_val)?;
+    let adapter: Function<Unknown, ProgressFn> = Env::from_raw(env).run_script(ADAPTER)?;
+    Ok(Self {
+      call: adapter.call(handler)?,
+    })
+  }
+}
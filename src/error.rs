@@ -3,6 +3,11 @@ use napi::{Error as NapiError, Status};
 use nitai_bindings_core::Error;
 
 pub fn to_napi_error(err: Error) -> NapiError {
+  napi_error_for(&err)
+}
+
+/// The error [`to_napi_error`] would throw, for errors that stay with their owner.
+pub(crate) fn napi_error_for(err: &Error) -> NapiError {
   match err {
     Error::Memory => napi_error(
       Status::GenericFailure,
@@ -24,6 +29,61 @@ pub fn to_napi_error(err: Error) -> NapiError {
       "websocket disconnected",
       "ERR_NITAI_WEBSOCKET_DISCONNECTED",
     ),
+    Error::TooManyRedirects(max) => napi_error(
+      Status::GenericFailure,
+      format!("too many redirects (limit {max})"),
+      "ERR_NITAI_TOO_MANY_REDIRECTS",
+    ),
+    Error::NonReplayableBody => napi_error(
+      Status::GenericFailure,
+      "request body cannot be replayed",
+      "ERR_NITAI_NON_REPLAYABLE_BODY",
+    ),
+    Error::Aborted(reason) => napi_error(
+      Status::Cancelled,
+      format!("request aborted: {reason}"),
+      "ERR_NITAI_ABORTED",
+    ),
+    Error::QueueTimeout(waited) => napi_error(
+      Status::GenericFailure,
+      format!("no request slot freed up within {waited:?}"),
+      "ERR_NITAI_QUEUE_TIMEOUT",
+    ),
+    Error::CircuitOpen(host) => napi_error(
+      Status::GenericFailure,
+      format!("circuit breaker open for {host}"),
+      "ERR_NITAI_CIRCUIT_OPEN",
+    ),
+    Error::Callback(message) => napi_error(
+      Status::GenericFailure,
+      format!("callback error: {message}"),
+      "ERR_NITAI_CALLBACK",
+    ),
+    Error::Curl(message) => napi_error(
+      Status::InvalidArg,
+      format!("invalid curl command: {message}"),
+      "ERR_NITAI_CURL",
+    ),
+    Error::Cassette(message) => napi_error(
+      Status::GenericFailure,
+      format!("cassette error: {message}"),
+      "ERR_NITAI_CASSETTE",
+    ),
+    Error::Mock(message) => napi_error(
+      Status::GenericFailure,
+      format!("mock error: {message}"),
+      "ERR_NITAI_MOCK",
+    ),
+    Error::Download(message) => napi_error(
+      Status::GenericFailure,
+      format!("download error: {message}"),
+      "ERR_NITAI_DOWNLOAD",
+    ),
+    Error::Unsupported(message) => napi_error(
+      Status::GenericFailure,
+      format!("unsupported: {message}"),
+      "ERR_NITAI_UNSUPPORTED",
+    ),
     Error::InvalidHeaderName(err) => napi_error(
       Status::InvalidArg,
       format!("invalid header name: {err}"),
@@ -39,6 +99,16 @@ pub fn to_napi_error(err: Error) -> NapiError {
       format!("operation timed out: {err}"),
       "ERR_NITAI_TIMEOUT",
     ),
+    Error::Connect(err) => napi_error(
+      Status::GenericFailure,
+      format!("connection failed: {err}"),
+      "ERR_NITAI_CONNECT",
+    ),
+    Error::ConnectionReset(err) => napi_error(
+      Status::GenericFailure,
+      format!("connection reset: {err}"),
+      "ERR_NITAI_CONNECTION_RESET",
+    ),
     Error::Builder(err) => napi_error(
       Status::GenericFailure,
       format!("failed to build request: {err}"),
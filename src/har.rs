@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use napi::bindgen_prelude::Either;
+use napi_derive::napi;
+use nitai_bindings_core::client::HarRecorder;
+
+/// HAR recording settings.
+#[napi(object)]
+pub struct HarOptions {
+  /// File the archive is written to by `saveHar()` without a path, and when the client is
+  /// garbage collected.
+  pub path: Option<String>,
+  /// Bytes of each request and response body to keep. Longer bodies are truncated.
+  pub max_body_size: Option<u32>,
+}
+
+/// `true` records in memory with the defaults and `false` leaves recording off.
+pub(crate) fn parse_har(options: Either<bool, HarOptions>) -> Option<Arc<HarRecorder>> {
+  let options = match options {
+    Either::A(true) => return Some(Arc::new(HarRecorder::new())),
+    Either::A(false) => return None,
+    Either::B(options) => options,
+  };
+
+  let mut recorder = HarRecorder::new();
+  if let Some(max_body_size) = options.max_body_size {
+    recorder = recorder.with_max_body_size(max_body_size as usize);
+  }
+  if let Some(path) = options.path {
+    recorder = recorder.with_path(path);
+  }
+  Some(Arc::new(recorder))
+}
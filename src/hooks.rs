@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use http::StatusCode;
+use napi::bindgen_prelude::{
+  Buffer, Either, FromNapiValue, Function, Promise, Result as NapiResult, TypeName, Unknown,
+  ValidateNapiValue,
+};
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi::{sys, Env, Status, ValueType};
+use napi_derive::napi;
+use nitai_bindings_core::client::{HookAction, HookRequest, Middleware};
+use nitai_bindings_core::{Error, Response};
+use wreq::Uri;
+
+use crate::error::napi_error_for;
+use crate::mock::reply_body;
+use crate::request_options::{convert_headers, napi_invalid, parse_method, HeadersInit};
+use crate::response_handle::flatten_headers;
+
+/// Calls the hook with the arguments it expects and normalizes what it returns into a
+/// `HookResult`.
+const ADAPTER: &str = r#"(handler) => {
+  if (typeof handler !== 'function') {
+    throw new TypeError('request hooks must be functions')
+  }
+  return ({ request, response, error }) => Promise.resolve()
+    .then(() => {
+      if (response) return handler(response, request)
+      if (error) return handler(error, request)
+      return handler(request)
+    })
+    .then((result) => {
+      if (!response && !error) {
+        const next = result === undefined ? request : result
+        if (next === null || typeof next !== 'object') {
+          throw new TypeError('beforeRequest must return nothing or a request')
+        }
+        const pick = (key) => (next[key] === undefined ? request[key] : next[key])
+        return {
+          action: 'continue',
+          request: {
+            method: pick('method'),
+            url: String(pick('url')),
+            headers: pick('headers'),
+            body: pick('body'),
+          },
+        }
+      }
+      if (result === undefined || result === null) return { action: 'continue' }
+      if (result === 'retry') return { action: 'retry' }
+      if (typeof result === 'object') return { action: 'replace', reply: result }
+      throw new TypeError("response hooks must return nothing, 'retry' or a response")
+    })
+}"#;
+
+/// A request about to be sent, as the client resolved it.
+#[napi(object)]
+pub struct HookRequestEntry {
+  pub method: String,
+  pub url: String,
+  /// Headers set on the request; the client's defaults and emulation headers are added after.
+  pub headers: HashMap<String, Vec<String>>,
+  /// The request body, or `null` when it is empty or streamed.
+  pub body: Option<Buffer>,
+}
+
+/// A response as hooks see it. The body is left for the caller to read.
+#[napi(object)]
+pub struct HookResponseEntry {
+  pub status: u16,
+  pub url: String,
+  pub headers: HashMap<String, Vec<String>>,
+}
+
+#[napi(object)]
+pub struct HookErrorEntry {
+  /// The message the request would fail with, starting with its `ERR_NITAI_*` code.
+  pub message: String,
+}
+
+#[napi(object)]
+pub struct HookCall {
+  pub request: HookRequestEntry,
+  pub response: Option<HookResponseEntry>,
+  pub error: Option<HookErrorEntry>,
+}
+
+/// Changes to a request. Fields a hook leaves out keep their value, and a `null` body leaves a
+/// streamed body in place.
+#[napi(object, object_to_js = false)]
+pub struct HookRequestInit {
+  pub method: Option<String>,
+  pub url: Option<String>,
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+  #[napi(ts_type = "string | Buffer | null")]
+  pub body: Option<Either<String, Buffer>>,
+}
+
+/// A response made up in place of the server's.
+#[napi(object, object_to_js = false)]
+pub struct HookReplyInit {
+  /// Defaults to 200.
+  pub status: Option<u16>,
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+  #[napi(ts_type = "string | Buffer")]
+  pub body: Option<Either<String, Buffer>>,
+  /// Sent as the body, with `content-type: application/json` unless a content type is set.
+  pub json: Option<serde_json::Value>,
+}
+
+#[napi(object, object_to_js = false)]
+pub struct HookResult {
+  pub action: String,
+  pub request: Option<HookRequestInit>,
+  pub reply: Option<HookReplyInit>,
+}
+
+// Weak so that a client holding the hooks does not keep the process alive.
+type HookFn = ThreadsafeFunction<HookCall, Promise<HookResult>, HookCall, Status, false, true>;
+
+/// A JavaScript `beforeRequest`, `afterResponse` or `onError` function.
+pub struct HookHandler {
+  call: Arc<HookFn>,
+}
+
+impl HookHandler {
+  async fn call(&self, name: &str, call: HookCall) -> Result<HookResult, Error> {
+    match self.call.call_async(call).await {
+      Ok(promise) => promise.await,
+      Err(err) => Err(err),
+    }
+    .map_err(|err| Error::Callback(format!("{name} failed: {}", err.reason)))
+  }
+}
+
+/// The hooks set on a `ClientInit`, run as the client's middleware.
+struct JsHooks {
+  before_request: Option<HookHandler>,
+  after_response: Option<HookHandler>,
+  on_error: Option<HookHandler>,
+}
+
+impl Middleware for JsHooks {
+  fn before_request<'a>(
+    &'a self,
+    request: &'a mut HookRequest,
+  ) -> BoxFuture<'a, Result<(), Error>> {
+    let Some(handler) = &self.before_request else {
+      return Box::pin(future::ready(Ok(())));
+    };
+    async move {
+      let call = HookCall {
+        request: request_entry(request),
+        response: None,
+        error: None,
+      };
+      let result = handler.call("beforeRequest", call).await?;
+      if let Some(init) = result.request {
+        apply_request(request, init)
+          .map_err(|err| Error::Callback(format!("beforeRequest failed: {}", err.reason)))?;
+      }
+      Ok(())
+    }
+    .boxed()
+  }
+
+  fn after_response<'a>(
+    &'a self,
+    request: &'a HookRequest,
+    response: &'a Response,
+  ) -> BoxFuture<'a, Result<HookAction, Error>> {
+    let Some(handler) = &self.after_response else {
+      return Box::pin(future::ready(Ok(HookAction::Continue)));
+    };
+    async move {
+      let call = HookCall {
+        request: request_entry(request),
+        response: Some(HookResponseEntry {
+          status: response.status.as_u16(),
+          url: response.uri.to_string(),
+          headers: flatten_headers(&response.headers),
+        }),
+        error: None,
+      };
+      let result = handler.call("afterResponse", call).await?;
+      hook_action("afterResponse", request, result)
+    }
+    .boxed()
+  }
+
+  fn on_error<'a>(
+    &'a self,
+    request: &'a HookRequest,
+    error: &'a Error,
+  ) -> BoxFuture<'a, Result<HookAction, Error>> {
+    let Some(handler) = &self.on_error else {
+      return Box::pin(future::ready(Ok(HookAction::Continue)));
+    };
+    async move {
+      let call = HookCall {
+        request: request_entry(request),
+        response: None,
+        error: Some(HookErrorEntry {
+          message: napi_error_for(error).reason,
+        }),
+      };
+      let result = handler.call("onError", call).await?;
+      hook_action("onError", request, result)
+    }
+    .boxed()
+  }
+}
+
+/// Middleware running the given hooks, or `None` when none is set.
+pub(crate) fn parse_hooks(
+  before_request: Option<HookHandler>,
+  after_response: Option<HookHandler>,
+  on_error: Option<HookHandler>,
+) -> Option<Arc<dyn Middleware>> {
+  if before_request.is_none() && after_response.is_none() && on_error.is_none() {
+    return None;
+  }
+  Some(Arc::new(JsHooks {
+    before_request,
+    after_response,
+    on_error,
+  }))
+}
+
+fn request_entry(request: &HookRequest) -> HookRequestEntry {
+  HookRequestEntry {
+    method: request.method.to_string(),
+    url: request.uri.to_string(),
+    headers: flatten_headers(&request.headers),
+    body: request.body.as_ref().map(|body| body.to_vec().into()),
+  }
+}
+
+fn apply_request(request: &mut HookRequest, init: HookRequestInit) -> NapiResult<()> {
+  if let Some(method) = init.method {
+    request.method = parse_method(&method)?;
+  }
+  if let Some(url) = init.url {
+    request.uri = url
+      .parse::<Uri>()
+      .ok()
+      .filter(|uri| matches!(uri.scheme_str(), Some("http" | "https")))
+      .ok_or_else(|| napi_invalid(format!("invalid url {url}")))?;
+  }
+  if let Some(headers) = init.headers {
+    request.headers = convert_headers(headers)?.0;
+  }
+  request.body = match init.body {
+    Some(Either::A(text)) => Some(text.into()),
+    Some(Either::B(buffer)) => Some(buffer.to_vec().into()),
+    None => None,
+  };
+  Ok(())
+}
+
+fn hook_action(name: &str, request: &HookRequest, result: HookResult) -> Result<HookAction, Error> {
+  match (result.action.as_str(), result.reply) {
+    ("retry", _) => Ok(HookAction::Retry),
+    ("replace", Some(reply)) => {
+      let response = reply_response(request, reply)
+        .map_err(|err| Error::Callback(format!("{name} failed: {}", err.reason)))?;
+      Ok(HookAction::Replace(response))
+    }
+    _ => Ok(HookAction::Continue),
+  }
+}
+
+fn reply_response(request: &HookRequest, reply: HookReplyInit) -> NapiResult<Response> {
+  let status = match reply.status {
+    Some(status) => StatusCode::from_u16(status)
+      .map_err(|_| napi_invalid(format!("invalid status code: {status}")))?,
+    None => StatusCode::OK,
+  };
+  let mut headers = match reply.headers {
+    Some(headers) => convert_headers(headers)?.0,
+    None => Default::default(),
+  };
+  let body = reply_body(reply.body, reply.json, &mut headers)?;
+  Ok(request.respond(status, headers, body))
+}
+
+impl TypeName for HookHandler {
+  fn type_name() -> &'static str {
+    "(...args: any[]) => unknown"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Function
+  }
+}
+
+impl ValidateNapiValue for HookHandler {}
+
+impl FromNapiValue for HookHandler {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> NapiResult<Self> {
+    let handler = Unknown::from_napi_value(env, napi_val)?;
+    let adapter: Function<Unknown, HookFn> = Env::from_raw(env).run_script(ADAPTER)?;
+    Ok(Self {
+      call: Arc::new(adapter.call(handler)?),
+    })
+  }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{
+  FromNapiValue, Function, Result as NapiResult, TypeName, Unknown, ValidateNapiValue,
+};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{sys, Env, Status, ValueType};
+use napi_derive::napi;
+use nitai_bindings_core::{InformationalCallback, InformationalResponse};
+
+use crate::response_handle::flatten_headers;
+
+/// Calls the handler with each interim response. A throwing handler must not take the process
+/// down from a thread-safe call, so its errors are dropped.
+const ADAPTER: &str = r#"(handler) => {
+  if (typeof handler !== 'function') {
+    throw new TypeError('onInformational must be a function')
+  }
+  return (response) => {
+    try {
+      handler(response)
+    } catch {}
+  }
+}"#;
+
+/// A `1xx` response received before the final one, such as `103 Early Hints`.
+#[napi(object)]
+pub struct InformationalEntry {
+  pub status: u16,
+  pub headers: HashMap<String, Vec<String>>,
+}
+
+impl From<&InformationalResponse> for InformationalEntry {
+  fn from(response: &InformationalResponse) -> Self {
+    Self {
+      status: response.status.as_u16(),
+      headers: flatten_headers(&response.headers),
+    }
+  }
+}
+
+// Weak so that a pending request does not keep the process alive on its own account.
+type InformationalFn =
+  ThreadsafeFunction<InformationalEntry, (), InformationalEntry, Status, false, true>;
+
+/// A JavaScript `onInformational` function.
+pub struct InformationalHandler {
+  call: InformationalFn,
+}
+
+pub(crate) fn parse_informational(handler: InformationalHandler) -> InformationalCallback {
+  Arc::new(move |response: InformationalResponse| {
+    handler.call.call(
+      InformationalEntry::from(&response),
+      ThreadsafeFunctionCallMode::NonBlocking,
+    );
+  })
+}
+
+impl TypeName for InformationalHandler {
+  fn type_name() -> &'static str {
+    "(response: InformationalEntry) => void"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Function
+  }
+}
+
+impl ValidateNapiValue for InformationalHandler {}
+
+impl FromNapiValue for InformationalHandler {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> NapiResult<Self> {
+    let handler = Unknown::from_napi_value(env, napi_val)?;
+    let adapter: Function<Unknown, InformationalFn> = Env::from_raw(env).run_script(ADAPTER)?;
+    Ok(Self {
+      call: adapter.call(handler)?,
+    })
+  }
+}
@@ -1,35 +1,93 @@
 #![deny(clippy::all)]
 
+mod abort;
+mod batch;
+mod body_stream;
+mod cache;
+mod cassette;
+mod circuit_breaker;
 mod client_options;
+mod concurrency;
+mod download;
 mod emulation;
 mod error;
+mod har;
+mod hooks;
+mod informational;
+mod mock;
+mod multipart;
+mod pagination;
+mod prepare;
+mod rate_limit;
+mod redirect;
 mod request_options;
 mod response_handle;
+mod retry;
 
+pub use batch::{BatchIterator, BatchOptions, BatchRequestInit, BatchResultEntry};
+pub use cache::CacheOptions;
+pub use cassette::CassetteOptions;
+pub use circuit_breaker::{CircuitBreakerOptions, CircuitEntry};
 pub use client_options::ClientInit;
-pub use request_options::{BasicAuth, ProxyConfig, RequestInit, WebSocketInit};
-pub use response_handle::{RedirectHistoryEntry, ResponseHandle};
+pub use concurrency::{ConcurrencyOptions, ConcurrencyStats, HostConcurrencyStats};
+pub use download::{DownloadEntry, DownloadOptions, DownloadProgressEntry};
+pub use har::HarOptions;
+pub use hooks::{HookErrorEntry, HookReplyInit, HookRequestEntry, HookResponseEntry};
+pub use mock::{
+  MockCallEntry, MockMatcherInit, MockOptions, MockReplyInit, MockTransport, MockUrlPattern,
+};
+pub use multipart::MultipartField;
+pub use pagination::{PaginateOptions, Paginator};
+pub use prepare::{PreparedBodyEntry, PreparedRequest};
+pub use rate_limit::{RateLimitOptions, RateLimitRule};
+pub use redirect::{RedirectOptions, RedirectStepEntry};
+pub use request_options::{
+  BasicAuth, ParamsEncodingOptions, ProxyConfig, RequestInit, WebSocketInit,
+};
+pub use response_handle::{CookieEntry, LinkEntry, RedirectHistoryEntry, ResponseHandle};
+pub use retry::{RetryAttemptEntry, RetryOptions};
+
+use std::path::{Path, PathBuf};
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use nitai_bindings_core::{
   client::{Client as CoreClient, ClientBuilder},
-  execute_request,
+  download, execute_request,
+  pagination::Paginator as CorePaginator,
+  CurlBody, CurlCommand, Error,
 };
 use wreq::Method;
 
+use crate::download::parse_progress;
 use crate::error::to_napi_error;
-use crate::request_options::{parse_method, ParsedRequest};
+use crate::multipart::describe_form;
+use crate::prepare::redact_proxy;
+use crate::request_options::{napi_invalid, parse_method, ParsedRequest};
 
 #[napi]
 pub struct Client {
   inner: CoreClient,
+  /// The configured proxy, reported by `prepare`.
+  proxy: Option<String>,
+  /// Whether TLS certificate verification is off, which curl's `-k` needs.
+  insecure: bool,
 }
 
 #[napi]
 impl Client {
   #[napi(constructor)]
   pub fn new(init: Option<ClientInit>) -> Result<Self> {
+    // Every configured proxy matches all URLs, so the first one is always used.
+    let proxy = init
+      .as_ref()
+      .filter(|init| init.no_proxy != Some(true))
+      .and_then(|init| init.proxies.as_ref()?.first())
+      .map(|proxy| redact_proxy(&proxy.uri));
+    let insecure = init
+      .as_ref()
+      .is_some_and(|init| matches!(init.verify, Some(Either::A(false))));
+
     let builder = if let Some(init) = init {
       init.build()?
     } else {
@@ -37,7 +95,11 @@ impl Client {
     };
 
     let client = builder.build().map_err(to_napi_error)?;
-    Ok(Self { inner: client })
+    Ok(Self {
+      inner: client,
+      proxy,
+      insecure,
+    })
   }
 
   #[napi]
@@ -141,6 +203,212 @@ impl Client {
     )
     .await
   }
+
+  /// Iterate over the pages of a resource, following `rel="next"` links or a custom cursor.
+  #[napi]
+  pub fn paginate(
+    &self,
+    url: String,
+    init: Option<RequestInit>,
+    options: Option<PaginateOptions>,
+  ) -> Result<Paginator> {
+    let ParsedRequest { method, request } = match init {
+      Some(init) => init.parse()?,
+      None => ParsedRequest::default(),
+    };
+    let PaginateOptions { max_pages, cursor } = options.unwrap_or(PaginateOptions {
+      max_pages: None,
+      cursor: None,
+    });
+
+    let inner = CorePaginator::new(
+      Some(self.inner.clone()),
+      method.unwrap_or(Method::GET),
+      url,
+      request,
+      max_pages.map(|max| max as usize),
+    );
+    Ok(Paginator::new(inner, cursor))
+  }
+
+  /// Send many requests with at most `concurrency` in flight, yielding each result with its body
+  /// read as soon as it finishes. Stopping the iteration early cancels what is left.
+  #[napi]
+  pub fn batch(
+    &self,
+    requests: Vec<Either<String, BatchRequestInit>>,
+    options: Option<BatchOptions>,
+  ) -> Result<BatchIterator> {
+    BatchIterator::new(self.inner.clone(), requests, options)
+  }
+
+  /// Stream `url` to the file at `path` without buffering it in memory.
+  ///
+  /// The body goes to `<path>.part` and replaces `path` once its length checks out. A download
+  /// that fails part way leaves the partial file behind, and downloading to the same path again
+  /// asks only for the rest with `Range` and `If-Range`. `<path>.part` is truncated when the
+  /// server sends the whole file instead, and a response without `ETag` or `Last-Modified` can't
+  /// be resumed later.
+  #[napi]
+  pub async fn download(
+    &self,
+    url: String,
+    path: String,
+    init: Option<RequestInit>,
+    options: Option<DownloadOptions>,
+  ) -> Result<DownloadEntry> {
+    let ParsedRequest { method, request } = match init {
+      Some(init) => init.parse()?,
+      None => ParsedRequest::default(),
+    };
+    if method.is_some_and(|method| method != Method::GET) {
+      return Err(napi_invalid(
+        "downloads are always GET requests".to_string(),
+      ));
+    }
+
+    download(
+      Some(self.inner.clone()),
+      &url,
+      path,
+      request,
+      parse_progress(options),
+    )
+    .await
+    .map(DownloadEntry::from)
+    .map_err(to_napi_error)
+  }
+
+  /// Resolve a request without sending it, to inspect what would go on the wire or send it later.
+  #[napi]
+  pub fn prepare(
+    &self,
+    method: String,
+    url: String,
+    init: Option<RequestInit>,
+  ) -> Result<PreparedRequest> {
+    let method = parse_method(&method)?;
+    let proxy = init
+      .as_ref()
+      .and_then(|init| init.proxy.as_ref())
+      .map(|proxy| redact_proxy(&proxy.uri))
+      .or_else(|| self.proxy.clone());
+    let form = init
+      .as_ref()
+      .and_then(|init| init.multipart.as_deref())
+      .map(describe_form);
+    let ParsedRequest { request, .. } = match init {
+      Some(init) => init.parse()?,
+      None => ParsedRequest::default(),
+    };
+
+    let prepared = self
+      .inner
+      .prepare(method, &url, request)
+      .map_err(to_napi_error)?;
+    Ok(PreparedRequest::new(prepared, proxy, self.insecure, form))
+  }
+
+  /// Prepare the request described by a curl command line, such as one copied from browser
+  /// devtools. `-k` is only accepted by a client created with `verify: false`.
+  #[napi]
+  pub async fn from_curl(&self, command: String) -> Result<PreparedRequest> {
+    // `-d @file` and `-F name=@file` touch the file system, which stays off the event loop.
+    let command = tokio::task::spawn_blocking(move || {
+      let command = CurlCommand::parse(&command)?;
+      let form = match &command.body {
+        CurlBody::Form(parts) => Some(parts.clone()),
+        _ => None,
+      };
+      let insecure = command.insecure;
+      let proxy = command.proxy.as_deref().map(redact_proxy);
+      Ok::<_, Error>((insecure, proxy, form, command.into_request()?))
+    })
+    .await
+    .map_err(|err| napi_invalid(format!("curl command could not be read: {err}")))?;
+    let (insecure, proxy, form, (method, url, request)) = command.map_err(to_napi_error)?;
+    if insecure && !self.insecure {
+      return Err(napi_invalid(
+        "curl -k needs a client created with `verify: false`".to_string(),
+      ));
+    }
+
+    let proxy = proxy.or_else(|| self.proxy.clone());
+    let prepared = self
+      .inner
+      .prepare(method, &url, request)
+      .map_err(to_napi_error)?;
+    Ok(PreparedRequest::new(prepared, proxy, self.insecure, form))
+  }
+
+  /// Requests in flight and waiting for a slot, or `null` when concurrency is not limited.
+  #[napi]
+  pub fn concurrency(&self) -> Option<ConcurrencyStats> {
+    self.inner.concurrency_stats().map(ConcurrencyStats::from)
+  }
+
+  /// Circuit breaker state of every host contacted so far, or `null` without a circuit breaker.
+  #[napi]
+  pub fn circuits(&self) -> Option<Vec<CircuitEntry>> {
+    self
+      .inner
+      .circuit_statuses()
+      .map(|statuses| statuses.into_iter().map(CircuitEntry::from).collect())
+  }
+
+  /// Exchanges recorded so far as a HAR 1.2 document, or `null` when recording is off.
+  #[napi(ts_return_type = "{ log: { version: string; entries: Array<any> } } | null")]
+  pub fn har(&self) -> Option<serde_json::Value> {
+    self.inner.har().map(|recorder| recorder.to_json())
+  }
+
+  /// Write the exchanges recorded so far to `path` as a HAR 1.2 document, or to the `path` given
+  /// in the HAR options.
+  #[napi]
+  pub async fn save_har(&self, path: Option<String>) -> Result<()> {
+    let recorder = self
+      .inner
+      .har()
+      .cloned()
+      .ok_or_else(|| napi_invalid("HAR recording is not enabled".to_string()))?;
+    let path = path
+      .map(PathBuf::from)
+      .or_else(|| recorder.path().map(Path::to_path_buf))
+      .ok_or_else(|| {
+        napi_invalid("saveHar needs a path when the HAR options give none".to_string())
+      })?;
+    tokio::task::spawn_blocking(move || recorder.save(path))
+      .await
+      .map_err(|err| napi_invalid(format!("HAR could not be written: {err}")))?
+      .map_err(|err| to_napi_error(Error::IO(err)))
+  }
+
+  /// Forget the exchanges recorded so far.
+  #[napi]
+  pub fn clear_har(&self) {
+    if let Some(recorder) = self.inner.har() {
+      recorder.clear();
+    }
+  }
+
+  /// Drop every response the client's cache holds.
+  #[napi]
+  pub async fn clear_cache(&self) -> Result<()> {
+    // A disk cache removes its files, which stays off the event loop.
+    let Some(cache) = self.inner.cache().cloned() else {
+      return Ok(());
+    };
+    tokio::task::spawn_blocking(move || cache.clear())
+      .await
+      .map_err(|err| napi_invalid(format!("cache could not be cleared: {err}")))?
+      .map_err(to_napi_error)
+  }
+
+  /// Routes and call log of a client created with `mock`, or `null` otherwise.
+  #[napi(getter)]
+  pub fn mock(&self) -> Option<MockTransport> {
+    self.inner.mock().cloned().map(MockTransport::new)
+  }
 }
 
 #[napi]
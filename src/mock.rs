@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use napi::bindgen_prelude::{Buffer, Either, Result as NapiResult};
+use napi_derive::napi;
+use nitai_bindings_core::client::{
+  MockFailure, MockMatcher, MockReply, MockRoute, MockTransport as CoreMock, UrlPattern,
+};
+
+use crate::error::to_napi_error;
+use crate::request_options::{
+  convert_headers, duration_from_millis, napi_invalid, parse_method, HeadersInit,
+};
+use crate::response_handle::flatten_headers;
+
+#[napi(object)]
+pub struct MockOptions {
+  /// Send requests no route matches over the network instead of failing them with
+  /// `ERR_NITAI_MOCK`.
+  pub passthrough: Option<bool>,
+}
+
+/// A regular expression, such as a `RegExp`. Only the `i` flag is honoured.
+#[napi(object)]
+pub struct MockUrlPattern {
+  pub source: String,
+  pub flags: Option<String>,
+}
+
+/// What a request must look like to match a route. Unset fields match anything.
+#[napi(object, object_to_js = false)]
+pub struct MockMatcherInit {
+  pub method: Option<String>,
+  /// A glob over the whole URL, where `*` stays within a path segment and `**` does not, or a
+  /// regular expression.
+  #[napi(ts_type = "string | RegExp")]
+  pub url: Option<Either<String, MockUrlPattern>>,
+  /// Headers the request must carry with exactly these values.
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+}
+
+#[napi(object, object_to_js = false)]
+pub struct MockReplyInit {
+  /// Defaults to 200.
+  pub status: Option<u16>,
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+  #[napi(ts_type = "string | Buffer")]
+  pub body: Option<Either<String, Buffer>>,
+  /// Sent as the body, with `content-type: application/json` unless a content type is set.
+  pub json: Option<serde_json::Value>,
+  /// Milliseconds to wait before answering. A delay past the request's `timeout` or `readTimeout`
+  /// fails it the way a slow server would, and aborting the request ends the wait.
+  pub delay: Option<u32>,
+  /// Fail with a transport error instead of answering: `ERR_NITAI_TIMEOUT` once the request's
+  /// timeout runs out, `ERR_NITAI_CONNECTION_RESET`, or `ERR_NITAI_CONNECT` for `refused` and
+  /// `tls`.
+  #[napi(ts_type = "'timeout' | 'reset' | 'refused' | 'tls'")]
+  pub error: Option<String>,
+  /// Times the route answers before it stops matching; unlimited by default.
+  pub times: Option<u32>,
+}
+
+#[napi(object)]
+pub struct MockCallEntry {
+  pub method: String,
+  pub url: String,
+  /// Headers as sent, including the client's defaults and emulation headers.
+  pub headers: HashMap<String, Vec<String>>,
+  /// The request body, unless it was empty or streamed.
+  pub body: Option<Buffer>,
+  /// Index of the route that answered, in the order routes were added, or `null` when none
+  /// matched.
+  pub route: Option<u32>,
+}
+
+/// Canned replies for a client created with `mock`, and a log of the requests it sent.
+///
+/// # Example
+///
+/// ```javascript
+/// const client = new Client({ mock: true })
+/// client.mock.on({ method: 'GET', url: 'https://api.test/users/*' }, { json: { id: 1 } })
+/// await client.get('https://api.test/users/1')
+/// console.log(client.mock.calls())
+/// ```
+#[napi]
+pub struct MockTransport {
+  inner: Arc<CoreMock>,
+}
+
+impl MockTransport {
+  pub(crate) fn new(inner: Arc<CoreMock>) -> Self {
+    Self { inner }
+  }
+}
+
+#[napi]
+impl MockTransport {
+  /// Answer requests matching `matcher` with `reply`. Routes are tried in the order they were
+  /// added.
+  #[napi]
+  pub fn on(&self, matcher: MockMatcherInit, reply: MockReplyInit) -> NapiResult<()> {
+    let times = reply.times.map(|times| times as usize);
+    self.inner.add(MockRoute {
+      matcher: parse_matcher(matcher)?,
+      reply: parse_reply(reply)?,
+      times,
+    });
+    Ok(())
+  }
+
+  /// Requests sent so far, matched or not, in the order they were sent.
+  #[napi]
+  pub fn calls(&self) -> Vec<MockCallEntry> {
+    self
+      .inner
+      .calls()
+      .into_iter()
+      .map(|call| MockCallEntry {
+        method: call.method.to_string(),
+        url: call.uri.to_string(),
+        headers: flatten_headers(&call.headers),
+        body: call.body.map(|body| body.to_vec().into()),
+        route: call.route.map(|route| route as u32),
+      })
+      .collect()
+  }
+
+  #[napi]
+  pub fn clear_calls(&self) {
+    self.inner.clear_calls();
+  }
+
+  /// Remove every route and forget every call.
+  #[napi]
+  pub fn reset(&self) {
+    self.inner.reset();
+  }
+}
+
+/// `true` enables mocking and `false` leaves it off.
+pub(crate) fn parse_mock(options: Either<bool, MockOptions>) -> Option<Arc<CoreMock>> {
+  match options {
+    Either::A(true) => Some(Arc::new(CoreMock::new())),
+    Either::A(false) => None,
+    Either::B(options) => Some(Arc::new(
+      CoreMock::new().with_passthrough(options.passthrough.unwrap_or(false)),
+    )),
+  }
+}
+
+fn parse_matcher(matcher: MockMatcherInit) -> NapiResult<MockMatcher> {
+  let url = match matcher.url {
+    Some(Either::A(glob)) => Some(UrlPattern::glob(&glob)),
+    Some(Either::B(pattern)) => {
+      let case_insensitive = pattern.flags.is_some_and(|flags| flags.contains('i'));
+      Some(UrlPattern::regex(&pattern.source, case_insensitive))
+    }
+    None => None,
+  };
+
+  let headers = match matcher.headers {
+    Some(headers) => {
+      let (headers, _) = convert_headers(headers)?;
+      let mut current = None;
+      let mut pairs = Vec::with_capacity(headers.len());
+      for (name, value) in headers {
+        if let Some(name) = name {
+          current = Some(name);
+        }
+        if let Some(name) = &current {
+          pairs.push((name.clone(), value));
+        }
+      }
+      pairs
+    }
+    None => Vec::new(),
+  };
+
+  Ok(MockMatcher {
+    method: matcher.method.as_deref().map(parse_method).transpose()?,
+    url: url.transpose().map_err(to_napi_error)?,
+    headers,
+  })
+}
+
+fn parse_reply(reply: MockReplyInit) -> NapiResult<MockReply> {
+  let status = match reply.status {
+    Some(status) => StatusCode::from_u16(status)
+      .map_err(|_| napi_invalid(format!("invalid status code: {status}")))?,
+    None => StatusCode::OK,
+  };
+
+  let mut headers = match reply.headers {
+    Some(headers) => convert_headers(headers)?.0,
+    None => Default::default(),
+  };
+
+  let body = reply_body(reply.body, reply.json, &mut headers)?;
+
+  let failure = match reply.error.as_deref() {
+    None => None,
+    Some("timeout") => Some(MockFailure::Timeout),
+    Some("reset") => Some(MockFailure::ConnectionReset),
+    Some("refused") => Some(MockFailure::ConnectionRefused),
+    Some("tls") => Some(MockFailure::Tls),
+    Some(other) => return Err(napi_invalid(format!("unsupported mock error: {other}"))),
+  };
+
+  Ok(MockReply {
+    status,
+    headers,
+    body,
+    delay: reply.delay.map(duration_from_millis),
+    failure,
+  })
+}
+
+/// The body of a made-up reply given as `body` or `json`, setting the JSON content type unless
+/// `headers` has one.
+pub(crate) fn reply_body(
+  body: Option<Either<String, Buffer>>,
+  json: Option<serde_json::Value>,
+  headers: &mut HeaderMap,
+) -> NapiResult<Bytes> {
+  match (body, json) {
+    (Some(_), Some(_)) => Err(napi_invalid(
+      "a reply takes either body or json, not both".to_string(),
+    )),
+    (Some(Either::A(text)), None) => Ok(text.into()),
+    (Some(Either::B(buffer)), None) => Ok(buffer.to_vec().into()),
+    (None, Some(json)) => {
+      headers
+        .entry(header::CONTENT_TYPE)
+        .or_insert(HeaderValue::from_static("application/json"));
+      serde_json::to_vec(&json)
+        .map(Bytes::from)
+        .map_err(|err| napi_invalid(format!("invalid json: {err}")))
+    }
+    (None, None) => Ok(Bytes::new()),
+  }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use napi::bindgen_prelude::{Buffer, Either, Result as NapiResult};
+use napi_derive::napi;
+use nitai_bindings_core::{body::ProducerBody, CurlFormPart};
+use wreq::multipart::{Form, Part};
+
+use crate::body_stream::BodyStream;
+use crate::request_options::{convert_header_map, napi_invalid};
+
+/// One part of a `multipart/form-data` body. Exactly one of `value`, `data` or `path` must be set.
+#[napi(object, object_to_js = false)]
+pub struct MultipartField {
+  pub name: String,
+  /// Text value of a plain form field.
+  pub value: Option<String>,
+  /// File contents, either in memory or as a stream of chunks.
+  #[napi(ts_type = "Buffer | AsyncIterable<Buffer | Uint8Array | string> | ReadableStream")]
+  pub data: Option<Either<Buffer, BodyStream>>,
+  /// Path of a file streamed from disk.
+  pub path: Option<String>,
+  /// Defaults to the file name of `path`.
+  pub filename: Option<String>,
+  pub content_type: Option<String>,
+  /// Size in bytes of a streamed `data`, so the form can be sent with a `Content-Length`.
+  pub length: Option<i64>,
+  pub headers: Option<HashMap<String, Either<String, Vec<String>>>>,
+}
+
+pub(crate) fn build_form(fields: Vec<MultipartField>) -> NapiResult<Form> {
+  fields.into_iter().try_fold(Form::new(), |form, field| {
+    let name = field.name.clone();
+    Ok(form.part(name, build_part(field)?))
+  })
+}
+
+/// The parts as `curl -F` would send them; contents given as `data` are read from stdin.
+pub(crate) fn describe_form(fields: &[MultipartField]) -> Vec<CurlFormPart> {
+  fields
+    .iter()
+    .map(|field| match (&field.value, &field.path) {
+      (Some(value), _) => CurlFormPart::Text {
+        name: field.name.clone(),
+        value: value.clone(),
+      },
+      (None, path) => CurlFormPart::File {
+        name: field.name.clone(),
+        path: path.clone().unwrap_or_else(|| "-".to_string()),
+        content_type: field.content_type.clone(),
+        filename: field.filename.clone(),
+      },
+    })
+    .collect()
+}
+
+fn build_part(field: MultipartField) -> NapiResult<Part> {
+  let MultipartField {
+    name,
+    value,
+    data,
+    path,
+    filename,
+    content_type,
+    length,
+    headers,
+  } = field;
+
+  let length = length
+    .map(|value| {
+      u64::try_from(value)
+        .map_err(|_| napi_invalid(format!("multipart field {name:?} has a negative length")))
+    })
+    .transpose()?;
+
+  let (mut part, default_filename) = match (value, data, path) {
+    (Some(text), None, None) => (Part::text(text), None),
+    (None, Some(Either::A(buffer)), None) => (Part::bytes(buffer.as_ref().to_vec()), None),
+    (None, Some(Either::B(stream)), None) => {
+      let body = wreq::Body::wrap(stream.into_body(length));
+      let part = match length {
+        Some(length) => Part::stream_with_length(body, length),
+        None => Part::stream(body),
+      };
+      (part, None)
+    }
+    (None, None, Some(path)) => {
+      let size = std::fs::metadata(&path)
+        .map_err(|err| napi_invalid(format!("cannot read multipart file {path:?}: {err}")))?
+        .len();
+      let default_filename = Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+      let body = wreq::Body::wrap(ProducerBody::file(path, Some(size)));
+      (Part::stream_with_length(body, size), default_filename)
+    }
+    _ => {
+      return Err(napi_invalid(format!(
+        "multipart field {name:?} needs exactly one of value, data or path"
+      )))
+    }
+  };
+
+  if let Some(filename) = filename.or(default_filename) {
+    part = part.file_name(filename);
+  }
+
+  if let Some(content_type) = content_type {
+    part = part.mime_str(&content_type).map_err(|err| {
+      napi_invalid(format!(
+        "invalid content type for multipart field {name:?}: {err}"
+      ))
+    })?;
+  }
+
+  if let Some(headers) = headers {
+    part = part.headers(convert_header_map(headers)?);
+  }
+
+  Ok(part)
+}
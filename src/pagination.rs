@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{AsyncGenerator, Either, Promise, Result};
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi::Status;
+use napi_derive::napi;
+use nitai_bindings_core::pagination::{NextPage, Paginator as CorePaginator};
+use tokio::sync::Mutex;
+
+use crate::error::to_napi_error;
+use crate::response_handle::ResponseHandle;
+
+/// A cursor result: a URL for the next page, query parameters to merge into the
+/// original request, or `null` to stop. New parameters are added in name order, so the same
+/// cursor always builds the same URL.
+type CursorValue = Option<Either<String, BTreeMap<String, String>>>;
+
+type CursorFn = ThreadsafeFunction<
+  ResponseHandle,
+  Either<Promise<CursorValue>, CursorValue>,
+  ResponseHandle,
+  Status,
+  false,
+>;
+
+#[napi(object, object_to_js = false)]
+pub struct PaginateOptions {
+  /// Stop after this many pages.
+  pub max_pages: Option<u32>,
+  /// Computes the next page from the current one instead of following `rel="next"` links.
+  #[napi(
+    ts_type = "(page: ResponseHandle) => string | Record<string, string> | null | undefined | Promise<string | Record<string, string> | null | undefined>"
+  )]
+  pub cursor: Option<CursorFn>,
+}
+
+/// Async iterator over the pages of a paginated resource.
+///
+/// # Example
+///
+/// ```javascript
+/// for await (const page of client.paginate('https://api.example.com/items')) {
+///   const items = await page.json();
+/// }
+/// ```
+#[napi(async_iterator)]
+pub struct Paginator {
+  state: Arc<Mutex<PaginatorState>>,
+}
+
+struct PaginatorState {
+  inner: CorePaginator,
+  cursor: Option<Arc<CursorFn>>,
+}
+
+impl Paginator {
+  pub(crate) fn new(inner: CorePaginator, cursor: Option<CursorFn>) -> Self {
+    Self {
+      state: Arc::new(Mutex::new(PaginatorState {
+        inner,
+        cursor: cursor.map(Arc::new),
+      })),
+    }
+  }
+}
+
+#[napi]
+impl AsyncGenerator for Paginator {
+  type Yield = ResponseHandle;
+  type Next = ();
+  type Return = ();
+
+  fn next(
+    &mut self,
+    _value: Option<Self::Next>,
+  ) -> impl Future<Output = Result<Option<Self::Yield>>> + Send + 'static {
+    let state = Arc::clone(&self.state);
+    async move { state.lock().await.next_page().await }
+  }
+}
+
+impl PaginatorState {
+  async fn next_page(&mut self) -> Result<Option<ResponseHandle>> {
+    let Some(response) = self.inner.fetch().await.map_err(to_napi_error)? else {
+      return Ok(None);
+    };
+    let response = Arc::new(response);
+
+    if let Some(cursor) = &self.cursor {
+      let value = match cursor
+        .call_async(ResponseHandle::view(Arc::clone(&response)))
+        .await?
+      {
+        Either::A(promise) => promise.await?,
+        Either::B(value) => value,
+      };
+
+      let next = match value {
+        Some(Either::A(url)) => NextPage::Url(url),
+        Some(Either::B(query)) => NextPage::Query(query.into_iter().collect()),
+        None => NextPage::Done,
+      };
+      self.inner.set_next(next, &response.uri);
+    }
+
+    Ok(Some(ResponseHandle::from_shared(response)))
+  }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use napi::bindgen_prelude::Result as NapiResult;
+use napi_derive::napi;
+use nitai_bindings_core::client::{RateLimit, RateLimiter};
+
+use crate::request_options::{duration_from_millis, napi_invalid};
+
+/// Token bucket limit for requests to a host.
+#[napi(object)]
+pub struct RateLimitRule {
+  /// Requests allowed per `per` milliseconds.
+  pub requests: u32,
+  /// Length of the window in milliseconds. Defaults to 1000.
+  pub per: Option<u32>,
+  /// Requests that may be sent back to back after an idle period. Defaults to 1.
+  pub burst: Option<u32>,
+}
+
+/// Pacing of requests sent through a client, tracked separately for each host.
+#[napi(object)]
+pub struct RateLimitOptions {
+  /// Requests allowed per `per` milliseconds for hosts without an override.
+  pub requests: Option<u32>,
+  /// Length of the window in milliseconds. Defaults to 1000.
+  pub per: Option<u32>,
+  /// Requests that may be sent back to back after an idle period. Defaults to 1.
+  pub burst: Option<u32>,
+  /// Limits for specific hosts, or for keys passed as `rateLimitKey`.
+  pub hosts: Option<HashMap<String, RateLimitRule>>,
+  /// Smallest random delay in milliseconds added before every request. Defaults to 0.
+  pub min_delay: Option<u32>,
+  /// Largest random delay in milliseconds added before every request. Defaults to `minDelay`.
+  pub max_delay: Option<u32>,
+}
+
+pub(crate) fn parse_rate_limit(options: RateLimitOptions) -> NapiResult<Arc<RateLimiter>> {
+  let default = options
+    .requests
+    .map(|requests| parse_rule(requests, options.per, options.burst))
+    .transpose()?;
+
+  let mut limiter = RateLimiter::new(default);
+
+  for (host, rule) in options.hosts.unwrap_or_default() {
+    let limit = parse_rule(rule.requests, rule.per, rule.burst)?;
+    limiter = limiter.with_override(host, limit);
+  }
+
+  let min_delay = options.min_delay.unwrap_or(0);
+  let max_delay = options.max_delay.unwrap_or(min_delay);
+  if max_delay < min_delay {
+    return Err(napi_invalid(
+      "maxDelay must not be less than minDelay".to_string(),
+    ));
+  }
+  limiter = limiter.with_delay(
+    duration_from_millis(min_delay),
+    duration_from_millis(max_delay),
+  );
+
+  Ok(Arc::new(limiter))
+}
+
+fn parse_rule(requests: u32, per: Option<u32>, burst: Option<u32>) -> NapiResult<RateLimit> {
+  if requests == 0 {
+    return Err(napi_invalid(
+      "rate limit requests must be at least 1".to_string(),
+    ));
+  }
+
+  let per = per
+    .map(duration_from_millis)
+    .unwrap_or(Duration::from_secs(1));
+  if per.is_zero() {
+    return Err(napi_invalid(
+      "rate limit per must be at least 1".to_string(),
+    ));
+  }
+
+  Ok(RateLimit {
+    burst: burst.unwrap_or(1).max(1),
+    ..RateLimit::new(requests, per)
+  })
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures_util::FutureExt;
+use napi::bindgen_prelude::{
+  FromNapiValue, Function, Promise, Result as NapiResult, TypeName, Unknown, ValidateNapiValue,
+};
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi::{sys, Env, Status, ValueType};
+use napi_derive::napi;
+use nitai_bindings_core::client::{RedirectAction, RedirectCallback, RedirectPolicy, RedirectStep};
+use nitai_bindings_core::Error;
+use wreq::Uri;
+
+use crate::request_options::{convert_headers, parse_method, HeadersInit};
+use crate::response_handle::flatten_headers;
+
+/// Normalizes whatever the handler returns into a `RedirectDecision`.
+const ADAPTER: &str = r#"(handler) => {
+  if (typeof handler !== 'function') {
+    throw new TypeError('onRedirect must be a function')
+  }
+  return (step) => Promise.resolve()
+    .then(() => handler(step))
+    .then((decision) => {
+      if (decision === undefined || decision === true || decision === 'follow') return { action: 'follow' }
+      if (decision === false || decision === 'stop') return { action: 'stop' }
+      if (decision !== null && typeof decision === 'object') {
+        return {
+          action: 'modify',
+          url: decision.url === undefined ? undefined : String(decision.url),
+          method: decision.method,
+          headers: decision.headers,
+        }
+      }
+      throw new TypeError("onRedirect must return 'follow', 'stop' or an object of changes")
+    })
+}"#;
+
+/// Redirect handling beyond `allowRedirects`; redirects are then followed one hop at a time.
+#[napi(object, object_to_js = false)]
+pub struct RedirectOptions {
+  /// Stop at redirects to another scheme, host or port. Defaults to `false`.
+  pub same_origin_only: Option<bool>,
+  /// Stop at redirects from `https` to `http`. Defaults to `false`.
+  pub deny_downgrade: Option<bool>,
+  /// Drop `Authorization`, `Proxy-Authorization` and `Cookie` headers on cross-origin hops.
+  /// Defaults to `true`.
+  pub strip_sensitive_headers: Option<bool>,
+  /// Switch `POST` to a body-less `GET` on 301 and 302, as browsers do. Defaults to `true`.
+  pub rewrite_post: Option<bool>,
+  /// Called for every hop with a `RedirectStep`. Return (or resolve with) `'follow'`, `'stop'`
+  /// to return the redirect response, or `{ url, method, headers }` to follow with changes.
+  pub on_redirect: Option<RedirectHandler>,
+}
+
+/// A redirect about to be followed.
+#[napi(object)]
+pub struct RedirectStepEntry {
+  pub hop: u32,
+  pub status: u16,
+  pub from: String,
+  pub to: String,
+  /// Method of the next request.
+  pub method: String,
+  /// Headers of the next request, after sensitive ones were stripped.
+  pub headers: HashMap<String, Vec<String>>,
+}
+
+#[napi(object, object_to_js = false)]
+pub struct RedirectDecision {
+  pub action: String,
+  pub url: Option<String>,
+  pub method: Option<String>,
+  pub headers: Option<HeadersInit>,
+}
+
+// Weak so that a client holding the handler does not keep the process alive.
+type HandlerFn = ThreadsafeFunction<
+  RedirectStepEntry,
+  Promise<RedirectDecision>,
+  RedirectStepEntry,
+  Status,
+  false,
+  true,
+>;
+
+/// A JavaScript `onRedirect` function.
+pub struct RedirectHandler {
+  call: Arc<HandlerFn>,
+}
+
+impl RedirectHandler {
+  async fn decide(call: Arc<HandlerFn>, step: RedirectStep) -> Result<RedirectAction, Error> {
+    let entry = RedirectStepEntry {
+      hop: step.hop as u32,
+      status: step.status.as_u16(),
+      from: step.from.to_string(),
+      to: step.to.to_string(),
+      method: step.method.to_string(),
+      headers: flatten_headers(&step.headers),
+    };
+
+    let decision = match call.call_async(entry).await {
+      Ok(promise) => promise.await,
+      Err(err) => Err(err),
+    }
+    .map_err(callback_error)?;
+
+    match decision.action.as_str() {
+      "follow" => Ok(RedirectAction::Follow),
+      "stop" => Ok(RedirectAction::Stop),
+      _ => {
+        let url = decision
+          .url
+          .map(|url| {
+            url
+              .parse::<Uri>()
+              .ok()
+              .filter(|uri| matches!(uri.scheme_str(), Some("http" | "https")))
+              .ok_or_else(|| Error::Callback(format!("onRedirect returned an invalid url {url}")))
+          })
+          .transpose()?;
+        let method = decision
+          .method
+          .map(|method| parse_method(&method))
+          .transpose()
+          .map_err(callback_error)?;
+        let headers = decision
+          .headers
+          .map(|headers| convert_headers(headers).map(|(headers, _)| headers))
+          .transpose()
+          .map_err(callback_error)?;
+        Ok(RedirectAction::Modify {
+          url,
+          method,
+          headers,
+        })
+      }
+    }
+  }
+}
+
+fn callback_error(err: napi::Error) -> Error {
+  Error::Callback(format!("onRedirect failed: {}", err.reason))
+}
+
+pub(crate) fn parse_redirect(options: RedirectOptions) -> RedirectPolicy {
+  let defaults = RedirectPolicy::default();
+  RedirectPolicy {
+    same_origin_only: options
+      .same_origin_only
+      .unwrap_or(defaults.same_origin_only),
+    deny_downgrade: options.deny_downgrade.unwrap_or(defaults.deny_downgrade),
+    strip_sensitive_headers: options
+      .strip_sensitive_headers
+      .unwrap_or(defaults.strip_sensitive_headers),
+    rewrite_post: options.rewrite_post.unwrap_or(defaults.rewrite_post),
+    callback: options.on_redirect.map(|handler| -> RedirectCallback {
+      let call = handler.call;
+      Arc::new(move |step| RedirectHandler::decide(call.clone(), step).boxed())
+    }),
+  }
+}
+
+impl TypeName for RedirectHandler {
+  fn type_name() -> &'static str {
+    "(step: RedirectStepEntry) => unknown"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Function
+  }
+}
+
+impl ValidateNapiValue for RedirectHandler {}
+
+impl FromNapiValue for RedirectHandler {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> NapiResult<Self> {
+    let handler = Unknown::from_napi_value(env, napi_val)?;
+    let adapter: Function<Unknown, HandlerFn> = Env::from_raw(env).run_script(ADAPTER)?;
+    Ok(Self {
+      call: Arc::new(adapter.call(handler)?),
+    })
+  }
+}
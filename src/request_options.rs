@@ -1,15 +1,23 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
 use std::time::Duration;
 
-use napi::bindgen_prelude::{Buffer, Either, Result as NapiResult};
+use napi::bindgen_prelude::{Buffer, Either, Either3, Result as NapiResult};
 use napi::{Error as NapiError, Status};
 use napi_derive::napi;
+use nitai_bindings_core::client::DEFAULT_EXPECT_CONTINUE;
+use nitai_bindings_core::params::{ArrayFormat, ObjectFormat, ParamsEncoding, SpaceEncoding};
 use nitai_bindings_core::request::{Request, WebSocketRequest};
-use wreq::header::{HeaderMap, HeaderName, HeaderValue};
+use wreq::header::{HeaderMap, HeaderName, HeaderValue, OrigHeaderMap};
 use wreq::{self, Method, Proxy, Version};
 
+use crate::abort::JsAbortSignal;
+use crate::body_stream::BodyStream;
 use crate::emulation::{parse_optional_emulation, EmulationOptions};
+use crate::informational::{parse_informational, InformationalHandler};
+use crate::multipart::{build_form, MultipartField};
+use crate::redirect::{parse_redirect, RedirectOptions};
+use crate::retry::{parse_retry, RetryOptions};
 
 #[napi(object)]
 pub struct BasicAuth {
@@ -24,15 +32,45 @@ pub struct ProxyConfig {
   pub password: Option<String>,
 }
 
+/// How nested `query` and `form` values are flattened, and how spaces are encoded.
 #[napi(object)]
+pub struct ParamsEncodingOptions {
+  /// `"repeat"` (`a=1&a=2`, default), `"brackets"` (`a[]=1`), `"indices"` (`a[0]=1`) or
+  /// `"comma"` (`a=1,2`).
+  pub array_format: Option<String>,
+  /// `"brackets"` (`a[b]=1`, default) or `"dots"` (`a.b=1`).
+  pub object_format: Option<String>,
+  /// `"plus"` (default) or `"percent"` (`%20`).
+  pub spaces: Option<String>,
+}
+
+#[napi(object, object_to_js = false)]
 pub struct RequestInit {
   pub method: Option<String>,
-  pub headers: Option<HashMap<String, Either<String, Vec<String>>>>,
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+  /// Header names in wire order, which may include the emulation's default headers.
+  pub header_order: Option<Vec<String>>,
   pub default_headers: Option<bool>,
   pub cookies: Option<Vec<String>>,
   pub emulation: Option<Either<String, EmulationOptions>>,
   pub allow_redirects: Option<bool>,
   pub max_redirects: Option<u32>,
+  /// Also follow `Refresh` headers and `<meta http-equiv="refresh">` tags.
+  pub follow_refresh: Option<bool>,
+  /// Checks and rewrites every redirect hop, overriding the client's `redirect`.
+  pub redirect: Option<RedirectOptions>,
+  /// Follow redirects one hop at a time so `history()` includes each hop's headers, cookies,
+  /// address and timing.
+  pub detailed_history: Option<bool>,
+  /// Overrides the client's retry policy; `false` disables retries.
+  pub retry: Option<Either<bool, RetryOptions>>,
+  /// Rate limit bucket for the request instead of the URL host.
+  pub rate_limit_key: Option<String>,
+  /// Position in the client's queue; higher values are sent first. Defaults to 0.
+  pub priority: Option<i32>,
+  /// Milliseconds to wait in the client's queue, overriding its `queueTimeout`.
+  pub queue_timeout: Option<u32>,
   pub gzip: Option<bool>,
   pub brotli: Option<bool>,
   pub deflate: Option<bool>,
@@ -40,21 +78,50 @@ pub struct RequestInit {
   pub auth: Option<String>,
   pub bearer_auth: Option<String>,
   pub basic_auth: Option<BasicAuth>,
-  pub query: Option<HashMap<String, Either<String, Vec<String>>>>,
-  pub form: Option<HashMap<String, Either<String, Vec<String>>>>,
+  /// Query parameters as an object or ordered `[key, value]` pairs; values may be nested.
+  #[napi(ts_type = "Record<string, unknown> | Array<[string, unknown]>")]
+  pub query: Option<serde_json::Value>,
+  /// Form fields as an object or ordered `[key, value]` pairs; values may be nested.
+  #[napi(ts_type = "Record<string, unknown> | Array<[string, unknown]>")]
+  pub form: Option<serde_json::Value>,
+  pub params_encoding: Option<ParamsEncodingOptions>,
   pub json: Option<serde_json::Value>,
-  pub body: Option<Either<String, Buffer>>,
+  /// Raw request body. Only one of `body`, `json`, `form` and `multipart` may be given.
+  #[napi(
+    ts_type = "string | Buffer | Uint8Array | AsyncIterable<Buffer | Uint8Array | string> | ReadableStream"
+  )]
+  pub body: Option<Either3<String, Buffer, BodyStream>>,
+  /// Size in bytes of a streamed `body`, sent as `Content-Length` instead of chunked encoding.
+  /// A string or Buffer `body` is always sent with its own size, which this must then match.
+  pub body_length: Option<i64>,
+  /// Trailing headers sent after the body, which is then transferred with chunked encoding.
+  pub trailers: Option<HashMap<String, Either<String, Vec<String>>>>,
+  /// Parts of a `multipart/form-data` body, sent in order.
+  pub multipart: Option<Vec<MultipartField>>,
+  /// Send `Expect: 100-continue` and hold the body back until the server answers `100 Continue`:
+  /// `true` waits up to a second for the answer, a number that many milliseconds.
+  pub expect_continue: Option<Either<bool, u32>>,
+  /// Called with every `1xx` response received before the final one, such as `103 Early Hints`.
+  /// Interim responses are only reported over HTTP/1.1.
+  #[napi(ts_type = "(response: InformationalEntry) => void")]
+  pub on_informational: Option<InformationalHandler>,
   pub timeout: Option<u32>,
   pub read_timeout: Option<u32>,
   pub version: Option<String>,
   pub proxy: Option<ProxyConfig>,
   pub local_address: Option<String>,
   pub interface: Option<String>,
+  /// Cancels the request, including reads of its body, when aborted.
+  #[napi(ts_type = "AbortSignal")]
+  pub signal: Option<JsAbortSignal>,
 }
 
-#[napi(object)]
+#[napi(object, object_to_js = false)]
 pub struct WebSocketInit {
-  pub headers: Option<HashMap<String, Either<String, Vec<String>>>>,
+  #[napi(ts_type = "Record<string, string | Array<string>> | Array<[string, string]>")]
+  pub headers: Option<HeadersInit>,
+  /// Header names in wire order, which may include the emulation's default headers.
+  pub header_order: Option<Vec<String>>,
   pub default_headers: Option<bool>,
   pub cookies: Option<Vec<String>>,
   pub emulation: Option<Either<String, EmulationOptions>>,
@@ -73,6 +140,9 @@ pub struct WebSocketInit {
   pub proxy: Option<ProxyConfig>,
   pub local_address: Option<String>,
   pub interface: Option<String>,
+  /// Cancels the request, including reads of its body, when aborted.
+  #[napi(ts_type = "AbortSignal")]
+  pub signal: Option<JsAbortSignal>,
 }
 
 #[derive(Default)]
@@ -100,9 +170,13 @@ impl WebSocketInit {
   pub fn parse(self) -> NapiResult<ParsedWebSocketRequest> {
     let mut request = WebSocketRequest::default();
 
+    let mut header_names = Vec::new();
     if let Some(headers) = self.headers {
-      request.headers = Some(convert_header_map(headers)?);
+      let (headers, names) = convert_headers(headers)?;
+      request.headers = Some(headers);
+      header_names = names;
     }
+    request.orig_headers = order_headers(header_names, self.header_order)?;
 
     if let Some(emulation) = parse_optional_emulation(self.emulation)? {
       request.emulation = Some(emulation);
@@ -159,6 +233,8 @@ impl WebSocketInit {
       request.interface = Some(interface);
     }
 
+    request.signal = self.signal.map(JsAbortSignal::into_signal);
+
     Ok(ParsedWebSocketRequest { request })
   }
 }
@@ -167,11 +243,19 @@ fn fill_request(request: &mut Request, init: RequestInit) -> NapiResult<Option<M
   let RequestInit {
     method,
     headers,
+    header_order,
     default_headers,
     cookies,
     emulation,
     allow_redirects,
     max_redirects,
+    follow_refresh,
+    redirect,
+    detailed_history,
+    retry,
+    rate_limit_key,
+    priority,
+    queue_timeout,
     gzip,
     brotli,
     deflate,
@@ -181,25 +265,52 @@ fn fill_request(request: &mut Request, init: RequestInit) -> NapiResult<Option<M
     basic_auth,
     query,
     form,
+    params_encoding,
     json,
     body,
+    body_length,
+    trailers,
+    multipart,
+    expect_continue,
+    on_informational,
     timeout,
     read_timeout,
     version,
     proxy,
     local_address,
     interface,
+    signal,
   } = init;
 
   let parsed_method = method.map(|value| parse_method(&value)).transpose()?;
 
+  let sources = [
+    ("body", body.is_some()),
+    ("json", json.is_some()),
+    ("form", form.is_some()),
+    ("multipart", multipart.is_some()),
+  ]
+  .into_iter()
+  .filter_map(|(name, set)| set.then_some(name))
+  .collect::<Vec<_>>();
+  if sources.len() > 1 {
+    return Err(napi_invalid(format!(
+      "only one body may be given, got {}",
+      sources.join(", ")
+    )));
+  }
+
   if let Some(emulation) = parse_optional_emulation(emulation)? {
     request.emulation = Some(emulation);
   }
 
+  let mut header_names = Vec::new();
   if let Some(headers) = headers {
-    request.headers = Some(convert_header_map(headers)?);
+    let (headers, names) = convert_headers(headers)?;
+    request.headers = Some(headers);
+    header_names = names;
   }
+  request.orig_headers = order_headers(header_names, header_order)?;
 
   if let Some(default_headers) = default_headers {
     request.default_headers = Some(default_headers);
@@ -211,6 +322,13 @@ fn fill_request(request: &mut Request, init: RequestInit) -> NapiResult<Option<M
 
   request.allow_redirects = allow_redirects;
   request.max_redirects = max_redirects.map(|value| value as usize);
+  request.follow_refresh = follow_refresh;
+  request.redirect = redirect.map(parse_redirect);
+  request.detailed_history = detailed_history;
+  request.retry = retry.map(parse_retry).transpose()?;
+  request.rate_limit_key = rate_limit_key;
+  request.priority = priority;
+  request.queue_timeout = queue_timeout.map(duration_from_millis);
   request.gzip = gzip;
   request.brotli = brotli;
   request.deflate = deflate;
@@ -219,23 +337,66 @@ fn fill_request(request: &mut Request, init: RequestInit) -> NapiResult<Option<M
   request.bearer_auth = bearer_auth;
   request.basic_auth = basic_auth.map(|basic| (basic.username, basic.password));
 
+  let (encoding, space_encoding) = parse_params_encoding(params_encoding)?;
+
   if let Some(query) = query {
-    request.query = Some(convert_pairs(query)?);
+    request.query = Some(flatten_params(encoding, &query, "query")?);
   }
 
   if let Some(form) = form {
-    request.form = Some(convert_pairs(form)?);
+    request.form = Some(flatten_params(encoding, &form, "form")?);
   }
 
+  request.space_encoding = space_encoding;
+
   request.json = json;
 
+  let body_length = body_length
+    .map(|value| {
+      u64::try_from(value).map_err(|_| napi_invalid(format!("invalid body length {value}")))
+    })
+    .transpose()?;
+  // Strings and buffers are sent with their own length, which a different one can't override.
+  let fixed_length = match &body {
+    Some(Either3::A(text)) => Some(text.len() as u64),
+    Some(Either3::B(buffer)) => Some(buffer.len() as u64),
+    Some(Either3::C(_)) => None,
+    None if body_length.is_some() => {
+      return Err(napi_invalid("bodyLength requires a body".to_string()));
+    }
+    None => None,
+  };
+  if let (Some(length), Some(actual)) = (body_length, fixed_length) {
+    if length != actual {
+      return Err(napi_invalid(format!(
+        "bodyLength {length} does not match the {actual}-byte body"
+      )));
+    }
+  }
+
   if let Some(body) = body {
     request.body = Some(match body {
-      Either::A(text) => wreq::Body::from(text),
-      Either::B(buffer) => wreq::Body::from(buffer.as_ref().to_vec()),
+      Either3::A(text) => wreq::Body::from(text),
+      Either3::B(buffer) => wreq::Body::from(buffer.as_ref().to_vec()),
+      Either3::C(stream) => wreq::Body::wrap(stream.into_body(body_length)),
     });
   }
 
+  if let Some(trailers) = trailers {
+    request.trailers = Some(convert_header_map(trailers)?);
+  }
+
+  if let Some(multipart) = multipart {
+    request.multipart = Some(build_form(multipart)?);
+  }
+
+  request.expect_continue = match expect_continue {
+    Some(Either::A(true)) => Some(DEFAULT_EXPECT_CONTINUE),
+    Some(Either::A(false)) | None => None,
+    Some(Either::B(wait)) => Some(duration_from_millis(wait)),
+  };
+  request.on_informational = on_informational.map(parse_informational);
+
   request.timeout = timeout.map(duration_from_millis);
   request.read_timeout = read_timeout.map(duration_from_millis);
 
@@ -255,28 +416,86 @@ fn fill_request(request: &mut Request, init: RequestInit) -> NapiResult<Option<M
     request.interface = Some(interface);
   }
 
+  request.signal = signal.map(JsAbortSignal::into_signal);
+
   Ok(parsed_method)
 }
 
+/// Headers as a record, or as `[name, value]` pairs whose order and casing are kept.
+pub(crate) type HeadersInit =
+  Either<Vec<Vec<String>>, HashMap<String, Either<String, Vec<String>>>>;
+
+/// Convert headers, returning the names of ordered pairs as they were spelled.
+pub(crate) fn convert_headers(headers: HeadersInit) -> NapiResult<(HeaderMap, Vec<String>)> {
+  match headers {
+    Either::A(pairs) => {
+      let mut map = HeaderMap::new();
+      let mut names = Vec::with_capacity(pairs.len());
+      for pair in pairs {
+        let [name, value]: [String; 2] = pair
+          .try_into()
+          .map_err(|_| napi_invalid("header pairs must be [name, value] arrays".to_string()))?;
+        map.append(
+          parse_header_name(&name)?,
+          parse_header_value(&name, &value)?,
+        );
+        names.push(name);
+      }
+      Ok((map, names))
+    }
+    Either::B(record) => Ok((convert_header_map(record)?, Vec::new())),
+  }
+}
+
+/// Build the wire order of headers: names from `order` first, then the remaining `names`.
+///
+/// Listed names take the casing used in `names` when the header was also given as a pair, which
+/// HTTP/1.1 sends verbatim.
+pub(crate) fn order_headers(
+  names: Vec<String>,
+  order: Option<Vec<String>>,
+) -> NapiResult<Option<OrigHeaderMap>> {
+  if names.is_empty() && order.is_none() {
+    return Ok(None);
+  }
+
+  let listed: Vec<String> = order
+    .unwrap_or_default()
+    .into_iter()
+    .map(|listed| {
+      names
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(&listed))
+        .cloned()
+        .unwrap_or(listed)
+    })
+    .collect();
+
+  let mut seen = HashSet::new();
+  let mut orig = OrigHeaderMap::new();
+  for name in listed.into_iter().chain(names) {
+    if seen.insert(parse_header_name(&name)?) {
+      orig.insert(name);
+    }
+  }
+
+  Ok(Some(orig))
+}
+
 pub(crate) fn convert_header_map(
   headers: HashMap<String, Either<String, Vec<String>>>,
 ) -> NapiResult<HeaderMap> {
   let mut map = HeaderMap::new();
   for (name, value) in headers {
-    let header_name = HeaderName::from_bytes(name.as_bytes())
-      .map_err(|err| napi_invalid(format!("invalid header name {name:?}: {err}")))?;
+    let header_name = parse_header_name(&name)?;
 
     match value {
       Either::A(value) => {
-        let value = HeaderValue::from_str(&value)
-          .map_err(|err| napi_invalid(format!("invalid header value for {name:?}: {err}")))?;
-        map.insert(header_name.clone(), value);
+        map.insert(header_name, parse_header_value(&name, &value)?);
       }
       Either::B(values) => {
         for raw in values {
-          let value = HeaderValue::from_str(&raw)
-            .map_err(|err| napi_invalid(format!("invalid header value for {name:?}: {err}")))?;
-          map.append(header_name.clone(), value);
+          map.append(header_name.clone(), parse_header_value(&name, &raw)?);
         }
       }
     }
@@ -284,6 +503,16 @@ pub(crate) fn convert_header_map(
   Ok(map)
 }
 
+fn parse_header_name(name: &str) -> NapiResult<HeaderName> {
+  HeaderName::from_bytes(name.as_bytes())
+    .map_err(|err| napi_invalid(format!("invalid header name {name:?}: {err}")))
+}
+
+fn parse_header_value(name: &str, value: &str) -> NapiResult<HeaderValue> {
+  HeaderValue::from_str(value)
+    .map_err(|err| napi_invalid(format!("invalid header value for {name:?}: {err}")))
+}
+
 pub(crate) fn convert_cookies(cookies: Vec<String>) -> NapiResult<Vec<HeaderValue>> {
   cookies
     .into_iter()
@@ -311,6 +540,57 @@ pub(crate) fn convert_pairs(
   Ok(pairs)
 }
 
+fn parse_params_encoding(
+  options: Option<ParamsEncodingOptions>,
+) -> NapiResult<(ParamsEncoding, Option<SpaceEncoding>)> {
+  let Some(options) = options else {
+    return Ok((ParamsEncoding::default(), None));
+  };
+
+  let mut encoding = ParamsEncoding::default();
+
+  if let Some(format) = options.array_format {
+    encoding.array_format = match format.as_str() {
+      "repeat" => ArrayFormat::Repeat,
+      "brackets" => ArrayFormat::Brackets,
+      "indices" => ArrayFormat::Indices,
+      "comma" => ArrayFormat::Comma,
+      other => return Err(napi_invalid(format!("unsupported array format: {other}"))),
+    };
+  }
+
+  if let Some(format) = options.object_format {
+    encoding.object_format = match format.as_str() {
+      "brackets" => ObjectFormat::Brackets,
+      "dots" => ObjectFormat::Dots,
+      other => return Err(napi_invalid(format!("unsupported object format: {other}"))),
+    };
+  }
+
+  let space_encoding = options
+    .spaces
+    .map(|spaces| match spaces.as_str() {
+      "plus" => Ok(SpaceEncoding::Plus),
+      "percent" => Ok(SpaceEncoding::Percent),
+      other => Err(napi_invalid(format!("unsupported space encoding: {other}"))),
+    })
+    .transpose()?;
+
+  Ok((encoding, space_encoding))
+}
+
+fn flatten_params(
+  encoding: ParamsEncoding,
+  value: &serde_json::Value,
+  field: &str,
+) -> NapiResult<Vec<(String, String)>> {
+  encoding.flatten(value).ok_or_else(|| {
+    napi_invalid(format!(
+      "{field} must be an object or a list of [key, value] pairs"
+    ))
+  })
+}
+
 pub(crate) fn parse_method(method: &str) -> NapiResult<Method> {
   Method::from_bytes(method.as_bytes())
     .map_err(|err| napi_invalid(format!("invalid HTTP method: {err}")))
@@ -5,10 +5,14 @@ use std::sync::Arc;
 use http::Version;
 use napi::bindgen_prelude::{Buffer, Result};
 use napi_derive::napi;
-use nitai_bindings_core::response::Response;
+use nitai_bindings_core::response::{RedirectKind, Response};
 use wreq::header::{HeaderMap, HeaderValue};
 
+use crate::cache::cache_status_name;
+use crate::download::{parse_progress, DownloadOptions};
 use crate::error::to_napi_error;
+use crate::informational::InformationalEntry;
+use crate::retry::{millis, RetryAttemptEntry};
 
 /// HTTP response handle with automatic resource cleanup.
 ///
@@ -41,11 +45,36 @@ pub struct ResponseHandle {
   consumed: Arc<AtomicBool>,
 }
 
+#[napi(object)]
+pub struct LinkEntry {
+  pub uri: String,
+  pub rel: Vec<String>,
+  pub params: HashMap<String, String>,
+}
+
+#[napi(object)]
+pub struct CookieEntry {
+  pub name: String,
+  pub value: String,
+  pub domain: Option<String>,
+  pub path: Option<String>,
+}
+
 #[napi(object)]
 pub struct RedirectHistoryEntry {
+  /// `"http"`, `"refresh"` (a `Refresh` header) or `"meta"` (a meta refresh tag).
+  pub kind: String,
   pub status: u16,
   pub uri: String,
   pub previous: String,
+  /// Headers of the redirecting response; empty unless the hop was followed on its own
+  /// (see `detailedHistory`).
+  pub headers: HashMap<String, Vec<String>>,
+  /// Cookies set by the redirecting response.
+  pub cookies: Vec<CookieEntry>,
+  pub remote_addr: Option<String>,
+  /// Milliseconds until the redirecting response's headers arrived.
+  pub elapsed: Option<f64>,
 }
 
 impl ResponseHandle {
@@ -63,6 +92,17 @@ impl ResponseHandle {
     }
   }
 
+  /// A handle sharing `inner` that leaves the body alone when dropped.
+  ///
+  /// Used when the same response is handed to a JavaScript callback while another
+  /// handle still owns it.
+  pub fn view(inner: Arc<Response>) -> Self {
+    Self {
+      inner,
+      consumed: Arc::new(AtomicBool::new(true)),
+    }
+  }
+
   pub fn as_shared(&self) -> Arc<Response> {
     Arc::clone(&self.inner)
   }
@@ -119,6 +159,14 @@ impl ResponseHandle {
     flatten_headers(&self.inner.headers)
   }
 
+  /// Trailing headers sent after the body, or `null` when there were none.
+  ///
+  /// Only populated once the body has been read via `text()`, `json()` or `bytes()`.
+  #[napi(getter)]
+  pub fn trailers(&self) -> Option<HashMap<String, Vec<String>>> {
+    self.inner.trailers().map(flatten_headers)
+  }
+
   #[napi(getter)]
   pub fn local_addr(&self) -> Option<String> {
     self.inner.local_addr.map(|addr| addr.to_string())
@@ -129,20 +177,90 @@ impl ResponseHandle {
     self.inner.remote_addr.map(|addr| addr.to_string())
   }
 
+  /// Links parsed from the `Link` headers (RFC 8288), resolved against the response URL.
+  #[napi(getter)]
+  pub fn links(&self) -> Vec<LinkEntry> {
+    self
+      .inner
+      .links()
+      .into_iter()
+      .map(|link| LinkEntry {
+        uri: link.uri,
+        rel: link.rel,
+        params: link.params.into_iter().collect(),
+      })
+      .collect()
+  }
+
   #[napi]
   pub fn history(&self) -> Vec<RedirectHistoryEntry> {
     self
       .inner
       .history()
-      .into_iter()
-      .map(|history| RedirectHistoryEntry {
-        status: history.status().as_u16(),
-        uri: history.uri().to_string(),
-        previous: history.previous().to_string(),
+      .iter()
+      .map(|hop| RedirectHistoryEntry {
+        kind: format_redirect_kind(hop.kind).into(),
+        status: hop.status.as_u16(),
+        uri: hop.uri.to_string(),
+        previous: hop.previous.to_string(),
+        headers: flatten_headers(&hop.headers),
+        cookies: hop
+          .cookies()
+          .into_iter()
+          .map(|cookie| CookieEntry {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: cookie.domain().map(str::to_string),
+            path: cookie.path().map(str::to_string),
+          })
+          .collect(),
+        remote_addr: hop.remote_addr.map(|addr| addr.to_string()),
+        elapsed: hop.elapsed.map(millis),
       })
       .collect()
   }
 
+  /// Earlier attempts that failed and were retried; empty when the first attempt succeeded.
+  #[napi(getter)]
+  pub fn attempts(&self) -> Vec<RetryAttemptEntry> {
+    self
+      .inner
+      .attempts()
+      .iter()
+      .map(|attempt| RetryAttemptEntry {
+        attempt: attempt.attempt,
+        status: attempt.status.map(|status| status.as_u16()),
+        error: attempt.error.clone(),
+        delay: millis(attempt.delay),
+      })
+      .collect()
+  }
+
+  /// `1xx` responses received before this one, such as `103 Early Hints`; only reported over
+  /// HTTP/1.1.
+  #[napi(getter)]
+  pub fn informational(&self) -> Vec<InformationalEntry> {
+    self
+      .inner
+      .informational()
+      .iter()
+      .map(InformationalEntry::from)
+      .collect()
+  }
+
+  /// How the client's cache answered the request: `hit`, `stale`, `revalidated` or `miss`, or
+  /// `null` when the request did not go through a cache.
+  #[napi(
+    getter,
+    ts_return_type = "'hit' | 'stale' | 'revalidated' | 'miss' | null"
+  )]
+  pub fn cache_status(&self) -> Option<String> {
+    self
+      .inner
+      .cache_status()
+      .map(|status| cache_status_name(status).to_string())
+  }
+
   /// Reads the response body as text.
   /// The response is automatically cleaned up after consumption.
   #[napi]
@@ -170,6 +288,20 @@ impl ResponseHandle {
     Ok(bytes.to_vec().into())
   }
 
+  /// Streams the body to `path` without buffering it in memory, replacing the file only once the
+  /// whole body is written and its length matches `Content-Length`. Resolves with the number of
+  /// bytes written.
+  #[napi]
+  pub async fn save_to(&self, path: String, options: Option<DownloadOptions>) -> Result<f64> {
+    let size = self
+      .inner
+      .save_to(path, parse_progress(options))
+      .await
+      .map_err(to_napi_error)?;
+    self.mark_consumed();
+    Ok(size as f64)
+  }
+
   /// Explicitly closes the response and releases resources immediately.
   ///
   /// **Note:** This method is optional. Response resources are automatically
@@ -219,7 +351,15 @@ fn format_version(version: Version) -> String {
   }
 }
 
-fn flatten_headers(headers: &HeaderMap) -> HashMap<String, Vec<String>> {
+fn format_redirect_kind(kind: RedirectKind) -> &'static str {
+  match kind {
+    RedirectKind::Http => "http",
+    RedirectKind::RefreshHeader => "refresh",
+    RedirectKind::MetaRefresh => "meta",
+  }
+}
+
+pub(crate) fn flatten_headers(headers: &HeaderMap) -> HashMap<String, Vec<String>> {
   let mut map: HashMap<String, Vec<String>> = HashMap::new();
   for (name, value) in headers.iter() {
     map
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use http::StatusCode;
+use napi::bindgen_prelude::{Either, Result as NapiResult};
+use napi_derive::napi;
+use nitai_bindings_core::client::RetryPolicy;
+
+use crate::request_options::{duration_from_millis, napi_invalid};
+
+/// Retry policy; unset fields keep their defaults.
+#[napi(object)]
+pub struct RetryOptions {
+  /// Total attempts, including the first one. Defaults to 3.
+  pub max_attempts: Option<u32>,
+  /// Retry when the connection could not be established. Defaults to `true`.
+  pub retry_connect_errors: Option<bool>,
+  /// Retry when the request timed out. Defaults to `true`.
+  pub retry_timeouts: Option<bool>,
+  /// Retry responses with these status codes. Defaults to 408, 429, 502, 503 and 504.
+  pub statuses: Option<Vec<u16>>,
+  /// Only retry `GET`, `HEAD`, `OPTIONS`, `PUT`, `DELETE` and `TRACE`. Defaults to `true`.
+  pub idempotent_only: Option<bool>,
+  /// Delay before the first retry in milliseconds. Defaults to 100.
+  pub base_delay: Option<u32>,
+  /// Growth factor of the delay between retries. Defaults to 2.
+  pub multiplier: Option<f64>,
+  /// Upper bound of the backoff delay in milliseconds. Defaults to 10000.
+  pub max_delay: Option<u32>,
+  /// Fraction of each delay that is randomized, from 0 to 1. Defaults to 0.5.
+  pub jitter: Option<f64>,
+  /// Wait as long as a `Retry-After` header asks. Defaults to `true`.
+  pub respect_retry_after: Option<bool>,
+  /// Give up instead of waiting when `Retry-After` asks for more milliseconds than this.
+  /// Defaults to 60000.
+  pub max_retry_after: Option<u32>,
+}
+
+#[napi(object)]
+pub struct RetryAttemptEntry {
+  pub attempt: u32,
+  pub status: Option<u16>,
+  pub error: Option<String>,
+  /// Milliseconds waited before the next attempt.
+  pub delay: f64,
+}
+
+/// `true` enables the default policy and `false` disables retries.
+pub(crate) fn parse_retry(retry: Either<bool, RetryOptions>) -> NapiResult<RetryPolicy> {
+  let options = match retry {
+    Either::A(true) => return Ok(RetryPolicy::default()),
+    Either::A(false) => {
+      return Ok(RetryPolicy {
+        max_attempts: 1,
+        ..RetryPolicy::default()
+      })
+    }
+    Either::B(options) => options,
+  };
+
+  let mut policy = RetryPolicy::default();
+
+  if let Some(max_attempts) = options.max_attempts {
+    if max_attempts == 0 {
+      return Err(napi_invalid("maxAttempts must be at least 1".to_string()));
+    }
+    policy.max_attempts = max_attempts;
+  }
+
+  if let Some(statuses) = options.statuses {
+    policy.statuses = statuses
+      .into_iter()
+      .map(|status| {
+        StatusCode::from_u16(status)
+          .map_err(|_| napi_invalid(format!("invalid retry status code {status}")))
+      })
+      .collect::<NapiResult<_>>()?;
+  }
+
+  if let Some(multiplier) = options.multiplier {
+    if !multiplier.is_finite() || multiplier < 1.0 {
+      return Err(napi_invalid("multiplier must be at least 1".to_string()));
+    }
+    policy.multiplier = multiplier;
+  }
+
+  if let Some(jitter) = options.jitter {
+    if !(0.0..=1.0).contains(&jitter) {
+      return Err(napi_invalid("jitter must be between 0 and 1".to_string()));
+    }
+    policy.jitter = jitter;
+  }
+
+  policy.retry_connect_errors = options
+    .retry_connect_errors
+    .unwrap_or(policy.retry_connect_errors);
+  policy.retry_timeouts = options.retry_timeouts.unwrap_or(policy.retry_timeouts);
+  policy.idempotent_only = options.idempotent_only.unwrap_or(policy.idempotent_only);
+  policy.respect_retry_after = options
+    .respect_retry_after
+    .unwrap_or(policy.respect_retry_after);
+
+  if let Some(base_delay) = options.base_delay {
+    policy.base_delay = duration_from_millis(base_delay);
+  }
+
+  if let Some(max_delay) = options.max_delay {
+    policy.max_delay = duration_from_millis(max_delay);
+  }
+
+  if let Some(max_retry_after) = options.max_retry_after {
+    policy.max_retry_after = duration_from_millis(max_retry_after);
+  }
+
+  Ok(policy)
+}
+
+pub(crate) fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}